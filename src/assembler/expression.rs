@@ -0,0 +1,289 @@
+// Expressions of the as65 syntax
+//
+// Numbers: $ff (hex), %1010 (binary), @17 (octal), 0x1f, 42, 'A'
+// `*` is the address of the current line.
+//
+// Operators from the strongest to the weakest binding:
+//
+// - ~ ! < > lo hi        ... unary (`<` and lo: low byte, `>` and hi: high byte)
+// * / %                  ... multiplication, division, modulo
+// + -
+// << >>
+// < <= > >=
+// = == != <>             ... comparisons are 1 (true) or 0 (false)
+// &
+// ^
+// |
+//
+// The value of an expression is unknown (`None`) if it uses a symbol
+// that is not defined yet, this is fine in the first pass.
+
+/// What a symbol is when it is looked up
+pub enum Lookup {
+    Value(i64),
+    // Not defined (yet):
+    Unknown,
+}
+
+/// Evaluates an expression
+pub fn evaluate(
+    text: &str,
+    pc: u16,
+    lookup: &dyn Fn(&str) -> Lookup,
+) -> Result<Option<i64>, String> {
+    let tokens = tokenize(text)?;
+    if tokens.is_empty() {
+        return Err("expression expected".to_string());
+    }
+    let mut parser = Parser {
+        tokens,
+        at: 0,
+        pc,
+        lookup,
+    };
+    let value = parser.binary(0)?;
+    if parser.at != parser.tokens.len() {
+        return Err(format!("unexpected `{}`", parser.tokens[parser.at]));
+    }
+    Ok(value)
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Operator(&'static str),
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Operator(op) => write!(f, "{}", op),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+// The longer operators have to come first
+const OPERATORS: [&str; 20] = [
+    "<<", ">>", "<=", ">=", "==", "!=", "<>", "+", "-", "*", "/", "%", "&", "|", "^", "~", "!",
+    "<", ">", "=",
+];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        if ch.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let rest: String = chars[i..].iter().collect();
+        if ch == '(' {
+            tokens.push(Token::Open);
+            i += 1;
+        } else if ch == ')' {
+            tokens.push(Token::Close);
+            i += 1;
+        } else if ch == '\'' {
+            // 'A' (and '' for a quote)
+            let value = match (chars.get(i + 1), chars.get(i + 2), chars.get(i + 3)) {
+                (Some('\''), Some('\''), Some('\'')) => {
+                    i += 4;
+                    '\''
+                }
+                (Some(c), Some('\''), _) => {
+                    i += 3;
+                    *c
+                }
+                _ => return Err("invalid character constant".to_string()),
+            };
+            tokens.push(Token::Number(value as i64));
+        } else if ch == '$' || ch == '@' || ch == '%' && !after_value(&tokens) {
+            let radix = match ch {
+                '$' => 16,
+                '%' => 2,
+                _ => 8,
+            };
+            let (value, length) = number(&chars[i + 1..], radix)?;
+            tokens.push(Token::Number(value));
+            i += 1 + length;
+        } else if ch.is_ascii_digit() {
+            let (value, length) = if rest.starts_with("0x") || rest.starts_with("0X") {
+                let (value, length) = number(&chars[i + 2..], 16)?;
+                (value, length + 2)
+            } else {
+                number(&chars[i..], 10)?
+            };
+            tokens.push(Token::Number(value));
+            i += length;
+        } else if ch.is_alphabetic() || ch == '_' || ch == '.' {
+            let length = chars[i..]
+                .iter()
+                .take_while(|c| c.is_alphanumeric() || **c == '_' || **c == '.')
+                .count();
+            tokens.push(Token::Name(chars[i..i + length].iter().collect()));
+            i += length;
+        } else {
+            match OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+                Some(op) => {
+                    tokens.push(Token::Operator(op));
+                    i += op.len();
+                }
+                None => return Err(format!("unexpected `{}`", ch)),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// `%` after a value is the modulo and not a binary number
+fn after_value(tokens: &[Token]) -> bool {
+    matches!(
+        tokens.last(),
+        Some(Token::Number(_)) | Some(Token::Name(_)) | Some(Token::Close)
+    )
+}
+
+/// Reads the digits of a number, returns the value and how many
+/// characters were read
+fn number(chars: &[char], radix: u32) -> Result<(i64, usize), String> {
+    let length = chars.iter().take_while(|c| c.is_alphanumeric()).count();
+    let digits: String = chars[..length].iter().collect();
+    match i64::from_str_radix(&digits, radix) {
+        Ok(value) if value <= u32::MAX as i64 => Ok((value, length)),
+        _ => Err(format!("`{}` is not a number", digits)),
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    at: usize,
+    pc: u16,
+    lookup: &'a dyn Fn(&str) -> Lookup,
+}
+
+/// The binary operators by how strong they bind
+fn precedence(op: &str) -> Option<u8> {
+    match op {
+        "|" => Some(1),
+        "^" => Some(2),
+        "&" => Some(3),
+        "=" | "==" | "!=" | "<>" => Some(4),
+        "<" | "<=" | ">" | ">=" => Some(5),
+        "<<" | ">>" => Some(6),
+        "+" | "-" => Some(7),
+        "*" | "/" | "%" => Some(8),
+        _ => None,
+    }
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.at)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        token
+    }
+
+    /// Is the next token the start of a value (`lo` and `hi`
+    /// are operators like `lo(x)` and `lo~x`)
+    fn operand_follows(&self) -> bool {
+        match self.peek() {
+            None | Some(Token::Close) => false,
+            Some(Token::Operator(op)) => precedence(op).is_none(),
+            _ => true,
+        }
+    }
+
+    /// Parses operators that bind stronger than `min`
+    fn binary(&mut self, min: u8) -> Result<Option<i64>, String> {
+        let mut left = self.unary()?;
+        while let Some(Token::Operator(op)) = self.peek() {
+            let op = *op;
+            let strength = match precedence(op) {
+                Some(strength) if strength > min => strength,
+                _ => break,
+            };
+            self.at += 1;
+            let right = self.binary(strength)?;
+            left = match (left, right) {
+                (Some(l), Some(r)) => Some(apply(op, l, r)?),
+                _ => None,
+            };
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Option<i64>, String> {
+        match self.next() {
+            Some(Token::Operator("-")) => Ok(self.unary()?.map(|v| v.wrapping_neg())),
+            Some(Token::Operator("+")) => self.unary(),
+            Some(Token::Operator("~")) => Ok(self.unary()?.map(|v| !v)),
+            Some(Token::Operator("!")) => Ok(self.unary()?.map(|v| (v == 0) as i64)),
+            Some(Token::Operator("<")) => Ok(self.unary()?.map(|v| v & 0xFF)),
+            Some(Token::Operator(">")) => Ok(self.unary()?.map(|v| (v >> 8) & 0xFF)),
+            Some(Token::Operator("*")) => Ok(Some(self.pc as i64)),
+            Some(Token::Number(value)) => Ok(Some(value)),
+            Some(Token::Open) => {
+                let value = self.binary(0)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(value),
+                    _ => Err("missing `)`".to_string()),
+                }
+            }
+            Some(Token::Name(name)) => {
+                let function = name.to_ascii_lowercase();
+                if (function == "lo" || function == "hi") && self.operand_follows() {
+                    let value = self.unary()?;
+                    return Ok(value.map(|v| {
+                        if function == "lo" {
+                            v & 0xFF
+                        } else {
+                            (v >> 8) & 0xFF
+                        }
+                    }));
+                }
+                match (self.lookup)(&name) {
+                    Lookup::Value(value) => Ok(Some(value)),
+                    Lookup::Unknown => Ok(None),
+                }
+            }
+            Some(token) => Err(format!("unexpected `{}`", token)),
+            None => Err("unexpected end of the expression".to_string()),
+        }
+    }
+}
+
+fn apply(op: &str, l: i64, r: i64) -> Result<i64, String> {
+    Ok(match op {
+        "|" => l | r,
+        "^" => l ^ r,
+        "&" => l & r,
+        "=" | "==" => (l == r) as i64,
+        "!=" | "<>" => (l != r) as i64,
+        "<" => (l < r) as i64,
+        "<=" => (l <= r) as i64,
+        ">" => (l > r) as i64,
+        ">=" => (l >= r) as i64,
+        "<<" => l.checked_shl(r as u32).unwrap_or(0),
+        ">>" => l.checked_shr(r as u32).unwrap_or(0),
+        "+" => l.wrapping_add(r),
+        "-" => l.wrapping_sub(r),
+        "*" => l.wrapping_mul(r),
+        "/" | "%" if r == 0 => return Err("division by zero".to_string()),
+        "/" => l.checked_div(r).ok_or("the division overflows")?,
+        "%" => l.checked_rem(r).ok_or("the division overflows")?,
+        _ => return Err(format!("unknown operator `{}`", op)),
+    })
+}
@@ -0,0 +1,938 @@
+// A two-pass assembler for the syntax of as65
+//
+// It understands what `bin/6502_functional_test.a65` uses:
+//
+// label   lda #$12    ;comment     labels start in the first column
+// lda #$12                         (a mnemonic there is no label)
+// name    equ expr                 a constant
+// name    = expr                   a variable (can be set again)
+//         org expr                 where the next bytes go
+//         db 1,'A',"text"          bytes
+//         dw label,$1234           words (little endian)
+//         ds expr                  reserves bytes (filled with 0)
+//         align [expr]             aligns to a multiple of expr (2)
+//         code / data / bss        segments (bss is not written)
+//         if expr / else / endif   conditional assembly
+// name    macro / endm             macros, \1 to \9 are the parameters
+//                                  and \? is unique for every expansion
+//         include "file"
+//         end [start]
+//
+// The first pass finds the addresses of all labels, the second one
+// writes the bytes. An operand that is not known in the first pass
+// (a forward reference) is always assembled as absolute and not as
+// zeropage, like `noopt` does in as65.
+
+mod expression;
+
+pub use crate::chip::Cpu;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::chip::Chip;
+use crate::disassembler::{opcode, Mode};
+use crate::instruction::Mnemonic;
+use crate::loader::Image;
+use crate::symbols::SymbolTable;
+use expression::{evaluate, Lookup};
+
+/// How deep macros can call other macros (and files include files)
+const MAX_DEPTH: usize = 32;
+
+/// Why the assembly failed and where
+#[derive(Debug, PartialEq, Clone)]
+pub struct AssemblerError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.file.is_empty() {
+            write!(f, "line {}: {}", self.line, self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.file, self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for AssemblerError {}
+
+/// The result of an assembly
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Assembly {
+    // The bytes (the start address is set by `end`):
+    pub image: Image,
+    // The addresses of all labels:
+    pub labels: SymbolTable,
+    // The values of all symbols (labels, constants and variables):
+    pub symbols: HashMap<String, i64>,
+}
+
+impl Assembly {
+    /// The bytes from the lowest to the highest assembled address
+    /// and where they start, like the binary output of as65.
+    ///
+    /// Bytes in between that were not assembled are $FF.
+    pub fn binary(&self) -> (u16, Vec<u8>) {
+        let start = match self.image.segments.iter().map(|s| s.address).min() {
+            Some(start) => start as usize,
+            None => return (0, Vec::new()),
+        };
+        let end = self
+            .image
+            .segments
+            .iter()
+            .map(|s| s.address as usize + s.data.len())
+            .max()
+            .unwrap_or(start);
+        let mut bytes = vec![0xFF; end - start];
+        for segment in &self.image.segments {
+            let at = segment.address as usize - start;
+            bytes[at..at + segment.data.len()].copy_from_slice(&segment.data);
+        }
+        (start as u16, bytes)
+    }
+}
+
+pub struct Assembler {
+    cpu: Cpu,
+    // Symbols that are known before the source (it can define them again):
+    predefined: HashMap<String, i64>,
+}
+
+impl Assembler {
+    pub fn new(cpu: Cpu) -> Assembler {
+        Assembler {
+            cpu,
+            predefined: HashMap::new(),
+        }
+    }
+
+    /// Defines a symbol for the sources that are assembled,
+    /// like a label of a program that is already loaded
+    pub fn define(&mut self, name: &str, value: i64) {
+        self.predefined.insert(name.to_string(), value);
+    }
+
+    /// Assembles the source text (includes are relative to
+    /// the working directory)
+    pub fn assemble(&self, text: &str) -> Result<Assembly, AssemblerError> {
+        self.assemble_at(text, 0)
+    }
+
+    /// Assembles the source text starting at `origin` (instead of 0)
+    pub fn assemble_at(&self, text: &str, origin: u16) -> Result<Assembly, AssemblerError> {
+        self.run("", text, Path::new(""), origin)
+    }
+
+    /// Assembles the source text at `origin` and loads it into a new
+    /// chip, the program counter is at `origin` (or the start of `end`)
+    pub fn chip(&self, text: &str, origin: u16) -> Result<Chip, AssemblerError> {
+        let assembly = self.assemble_at(text, origin)?;
+        let mut c = Chip::new();
+        c.load_image(&assembly.image).map_err(|e| AssemblerError {
+            file: String::new(),
+            line: 0,
+            message: e.to_string(),
+        })?;
+        c.startup(assembly.image.start.unwrap_or(origin));
+        Ok(c)
+    }
+
+    /// Assembles a source file (includes are relative to its directory)
+    pub fn assemble_file(&self, file_path: String) -> Result<Assembly, AssemblerError> {
+        let text = std::fs::read(&file_path).map_err(|e| AssemblerError {
+            file: file_path.clone(),
+            line: 0,
+            message: e.to_string(),
+        })?;
+        let directory = Path::new(&file_path).parent().unwrap_or(Path::new(""));
+        self.run(&file_path, &String::from_utf8_lossy(&text), directory, 0)
+    }
+
+    fn run(
+        &self,
+        name: &str,
+        text: &str,
+        directory: &Path,
+        origin: u16,
+    ) -> Result<Assembly, AssemblerError> {
+        let lines: Vec<String> = text.lines().map(|l| l.to_string()).collect();
+        let mut pass = Pass::new(self.cpu, directory, origin, &self.predefined);
+        pass.source(name, &lines, 0)?;
+        pass.finish()?;
+
+        pass.start_second();
+        pass.source(name, &lines, 0)?;
+        pass.finish()?;
+
+        let symbols = pass
+            .symbols
+            .iter()
+            .map(|(name, symbol)| (name.clone(), symbol.value))
+            .collect();
+        Ok(Assembly {
+            image: pass.image,
+            labels: pass.labels,
+            symbols,
+        })
+    }
+}
+
+/// Assembles source text for the 6502
+pub fn assemble(text: &str) -> Result<Assembly, AssemblerError> {
+    Assembler::new(Cpu::Mos6502).assemble(text)
+}
+
+/// Assembles source text for the 6502 into a new chip that is ready
+/// to run at `origin`
+pub fn chip(text: &str, origin: u16) -> Result<Chip, AssemblerError> {
+    Assembler::new(Cpu::Mos6502).chip(text, origin)
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+enum Segment {
+    Code,
+    Data,
+    Bss,
+}
+
+struct Symbol {
+    value: i64,
+    // Defined with `=`:
+    variable: bool,
+    // The pass it was last defined in:
+    pass: u8,
+}
+
+struct Condition {
+    active: bool,
+    // Is the `if` itself in an active part:
+    outer: bool,
+    seen_else: bool,
+}
+
+/// The operand as it is written
+enum Syntax<'a> {
+    None,
+    Accumulator,
+    Immediate(&'a str),
+    Direct(&'a str),
+    Indexed(&'a str, char),
+    XIndirect(&'a str),
+    IndirectY(&'a str),
+    Indirect(&'a str),
+    Pair(&'a str, &'a str),
+}
+
+/// The state of a pass
+struct Pass<'a> {
+    cpu: Cpu,
+    directory: PathBuf,
+    pass: u8,
+    opcodes: HashMap<(&'static str, Mode), u8>,
+    symbols: HashMap<String, Symbol>,
+    predefined: &'a HashMap<String, i64>,
+    macros: HashMap<String, Vec<String>>,
+    // A macro that is being defined:
+    recording: Option<(String, Vec<String>)>,
+    conditions: Vec<Condition>,
+    segment: Segment,
+    pcs: HashMap<Segment, u32>,
+    // Where every segment starts:
+    origin: u32,
+    // The number of the last macro expansion (for \?):
+    expansion: usize,
+    // For every instruction if it was assembled absolute in the first pass:
+    wide: Vec<bool>,
+    instruction: usize,
+    ended: bool,
+    // Where the current line is:
+    file: String,
+    line: usize,
+    image: Image,
+    labels: SymbolTable,
+}
+
+impl<'a> Pass<'a> {
+    fn new(
+        cpu: Cpu,
+        directory: &Path,
+        origin: u16,
+        predefined: &'a HashMap<String, i64>,
+    ) -> Pass<'a> {
+        let mut opcodes = HashMap::new();
+        for byte in 0..=255 {
+            let op = opcode(cpu, byte);
+            if op.documented {
+                opcodes.insert((op.mnemonic, op.mode), byte);
+            }
+        }
+        Pass {
+            cpu,
+            directory: directory.to_path_buf(),
+            pass: 1,
+            opcodes,
+            symbols: HashMap::new(),
+            predefined,
+            macros: HashMap::new(),
+            recording: None,
+            conditions: Vec::new(),
+            segment: Segment::Code,
+            pcs: HashMap::new(),
+            origin: origin as u32,
+            expansion: 0,
+            wide: Vec::new(),
+            instruction: 0,
+            ended: false,
+            file: String::new(),
+            line: 0,
+            image: Image::default(),
+            labels: SymbolTable::new(),
+        }
+    }
+
+    /// Resets everything but the symbols for the second pass
+    fn start_second(&mut self) {
+        self.pass = 2;
+        self.macros.clear();
+        self.segment = Segment::Code;
+        self.pcs.clear();
+        self.expansion = 0;
+        self.instruction = 0;
+        self.ended = false;
+    }
+
+    /// Checks that every `if` and `macro` was closed
+    fn finish(&self) -> Result<(), AssemblerError> {
+        if self.recording.is_some() {
+            return Err(self.error("`macro` without `endm`".to_string()));
+        }
+        if !self.conditions.is_empty() {
+            return Err(self.error("`if` without `endif`".to_string()));
+        }
+        Ok(())
+    }
+
+    fn error(&self, message: String) -> AssemblerError {
+        AssemblerError {
+            file: self.file.clone(),
+            line: self.line,
+            message,
+        }
+    }
+
+    fn pc(&self) -> u32 {
+        *self.pcs.get(&self.segment).unwrap_or(&self.origin)
+    }
+
+    fn set_pc(&mut self, pc: u32) {
+        self.pcs.insert(self.segment, pc);
+    }
+
+    fn active(&self) -> bool {
+        self.conditions.last().map(|c| c.active).unwrap_or(true)
+    }
+
+    /// Assembles the lines of a file
+    fn source(&mut self, name: &str, lines: &[String], depth: usize) -> Result<(), AssemblerError> {
+        let (file, line) = (self.file.clone(), self.line);
+        self.file = name.to_string();
+        for (i, text) in lines.iter().enumerate() {
+            if self.ended {
+                break;
+            }
+            self.line = i + 1;
+            self.statement(text, depth)?;
+        }
+        self.file = file;
+        self.line = line;
+        Ok(())
+    }
+
+    /// Assembles a line
+    fn statement(&mut self, text: &str, depth: usize) -> Result<(), AssemblerError> {
+        let (label, op, operand) = split_line(text);
+        let op_lower = op.to_ascii_lowercase();
+        let directive = op_lower.trim_start_matches('.');
+
+        if let Some((name, body)) = &mut self.recording {
+            if directive == "endm" {
+                let name = name.clone();
+                let body = std::mem::take(body);
+                self.recording = None;
+                self.macros.insert(name, body);
+            } else {
+                body.push(text.to_string());
+            }
+            return Ok(());
+        }
+
+        match directive {
+            "if" => {
+                let outer = self.active();
+                let active = outer && self.known(operand, "if")? != 0;
+                self.conditions.push(Condition {
+                    active,
+                    outer,
+                    seen_else: false,
+                });
+                return Ok(());
+            }
+            "else" => {
+                let condition = match self.conditions.last_mut() {
+                    Some(condition) if !condition.seen_else => condition,
+                    _ => return Err(self.error("`else` without `if`".to_string())),
+                };
+                condition.active = condition.outer && !condition.active;
+                condition.seen_else = true;
+                return Ok(());
+            }
+            "endif" => {
+                if self.conditions.pop().is_none() {
+                    return Err(self.error("`endif` without `if`".to_string()));
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+        if !self.active() {
+            return Ok(());
+        }
+
+        match directive {
+            "macro" => {
+                if label.is_empty() {
+                    return Err(self.error("a macro needs a name".to_string()));
+                }
+                self.recording = Some((label.to_ascii_lowercase(), Vec::new()));
+                return Ok(());
+            }
+            "endm" => return Err(self.error("`endm` without `macro`".to_string())),
+            "equ" | "=" => {
+                if label.is_empty() {
+                    return Err(self.error(format!("`{}` needs a name", op)));
+                }
+                let value = self.evaluate(operand)?;
+                return self.define(label, value, directive == "=");
+            }
+            _ => {}
+        }
+
+        if !label.is_empty() {
+            let pc = self.pc();
+            self.define(label, Some(pc as i64), false)?;
+            if self.pass == 2 {
+                self.labels.insert(label, pc as u16);
+            }
+        }
+
+        match directive {
+            "" => Ok(()),
+            "org" => {
+                let pc = self.known(operand, "org")?;
+                self.check_address(pc, 0)?;
+                self.set_pc(pc as u32);
+                Ok(())
+            }
+            "db" | "byte" | "fcb" => self.bytes(operand),
+            "dw" | "word" | "fdb" => self.words(operand),
+            "ds" | "res" => {
+                let length = self.known(operand, "ds")?;
+                self.check_address(self.pc() as i64, length)?;
+                self.emit(&vec![0; length as usize])
+            }
+            "align" => {
+                let to = match operand {
+                    "" => 2,
+                    _ => self.known(operand, "align")?,
+                };
+                if to <= 0 {
+                    return Err(self.error(format!("can not align to {}", to)));
+                }
+                let pc = self.pc() as i64;
+                let length = (to - pc % to) % to;
+                self.check_address(pc, length)?;
+                self.set_pc((pc + length) as u32);
+                Ok(())
+            }
+            "code" => {
+                self.segment = Segment::Code;
+                Ok(())
+            }
+            "data" => {
+                self.segment = Segment::Data;
+                Ok(())
+            }
+            "bss" => {
+                self.segment = Segment::Bss;
+                Ok(())
+            }
+            "end" => {
+                if !operand.is_empty() {
+                    let start = self.evaluate(operand)?;
+                    if let (2, Some(start)) = (self.pass, start) {
+                        self.image.start = Some(start as u16);
+                    }
+                }
+                self.ended = true;
+                Ok(())
+            }
+            "include" => self.include(operand, depth),
+            "noopt" | "opt" | "list" | "nolist" | "page" | "title" | "subttl" => Ok(()),
+            _ => match self.macros.get(directive) {
+                Some(body) => {
+                    let body = body.clone();
+                    self.expand(directive, &body, operand, depth)
+                }
+                None => self.instruction(op, operand),
+            },
+        }
+    }
+
+    /// Defines a symbol
+    fn define(
+        &mut self,
+        name: &str,
+        value: Option<i64>,
+        variable: bool,
+    ) -> Result<(), AssemblerError> {
+        let value = match value {
+            Some(value) => value,
+            // it stays unknown in the first pass
+            None if self.pass == 1 => return Ok(()),
+            None => return Err(self.error(format!("`{}` can not be evaluated", name))),
+        };
+        if let Some(symbol) = self.symbols.get(name) {
+            if !variable && (symbol.variable || symbol.pass == self.pass) {
+                return Err(self.error(format!("`{}` is already defined", name)));
+            }
+            if !variable && symbol.value != value {
+                return Err(self.error(format!(
+                    "`{}` is ${:04X} in the second pass but was ${:04X} in the first",
+                    name, value, symbol.value
+                )));
+            }
+        }
+        self.symbols.insert(
+            name.to_string(),
+            Symbol {
+                value,
+                variable,
+                pass: self.pass,
+            },
+        );
+        Ok(())
+    }
+
+    /// Evaluates an expression, it is `None` if it uses symbols
+    /// that are not defined (only in the first pass)
+    fn evaluate(&self, text: &str) -> Result<Option<i64>, AssemblerError> {
+        let missing = RefCell::new(None);
+        let lookup = |name: &str| match self
+            .symbols
+            .get(name)
+            .map(|symbol| symbol.value)
+            .or_else(|| self.predefined.get(name).copied())
+        {
+            Some(value) => Lookup::Value(value),
+            None => {
+                missing.replace(Some(name.to_string()));
+                Lookup::Unknown
+            }
+        };
+        let value = evaluate(text, self.pc() as u16, &lookup).map_err(|e| self.error(e))?;
+        match (value, missing.into_inner()) {
+            (None, Some(name)) if self.pass == 2 => {
+                Err(self.error(format!("`{}` is not defined", name)))
+            }
+            (value, _) => Ok(value),
+        }
+    }
+
+    /// Evaluates an expression that has to be known in the first pass
+    fn known(&self, text: &str, what: &str) -> Result<i64, AssemblerError> {
+        match self.evaluate(text)? {
+            Some(value) => Ok(value),
+            None => Err(self.error(format!(
+                "the value of `{}` has to be known before it is used",
+                what
+            ))),
+        }
+    }
+
+    /// Checks that `length` bytes fit at the address
+    fn check_address(&self, address: i64, length: i64) -> Result<(), AssemblerError> {
+        if address < 0 || length < 0 || address + length > 0x10000 {
+            return Err(self.error(format!(
+                "{} bytes at {} do not fit into the memory",
+                length, address
+            )));
+        }
+        Ok(())
+    }
+
+    /// Writes the bytes at the program counter (only in the second pass)
+    fn emit(&mut self, bytes: &[u8]) -> Result<(), AssemblerError> {
+        let pc = self.pc();
+        self.check_address(pc as i64, bytes.len() as i64)?;
+        if self.pass == 2 && self.segment != Segment::Bss {
+            self.image.push(pc as u16, bytes);
+        }
+        self.set_pc(pc + bytes.len() as u32);
+        Ok(())
+    }
+
+    fn bytes(&mut self, operand: &str) -> Result<(), AssemblerError> {
+        let mut bytes = Vec::new();
+        for item in split_top_level(operand) {
+            let quoted = item.len() >= 2
+                && (item.starts_with('"') && item.ends_with('"')
+                    || item.starts_with('\'') && item.ends_with('\'') && item.len() != 3);
+            if quoted {
+                bytes.extend_from_slice(&item.as_bytes()[1..item.len() - 1]);
+                continue;
+            }
+            let value = self.evaluate(item)?.unwrap_or(0);
+            bytes.push(self.byte(value)?);
+        }
+        self.emit(&bytes)
+    }
+
+    fn words(&mut self, operand: &str) -> Result<(), AssemblerError> {
+        let mut bytes = Vec::new();
+        for item in split_top_level(operand) {
+            let value = self.evaluate(item)?.unwrap_or(0);
+            if !(-0x8000..=0xFFFF).contains(&value) {
+                return Err(self.error(format!("{} does not fit into a word", value)));
+            }
+            bytes.extend_from_slice(&(value as u16).to_le_bytes());
+        }
+        self.emit(&bytes)
+    }
+
+    /// A value that has to fit into a byte
+    fn byte(&self, value: i64) -> Result<u8, AssemblerError> {
+        if !(-0x80..=0xFF).contains(&value) {
+            return Err(self.error(format!("{} does not fit into a byte", value)));
+        }
+        Ok(value as u8)
+    }
+
+    fn include(&mut self, operand: &str, depth: usize) -> Result<(), AssemblerError> {
+        if depth >= MAX_DEPTH {
+            return Err(self.error("too many nested includes".to_string()));
+        }
+        let name = operand.trim_matches(|c| c == '"' || c == '\'');
+        let path = self.directory.join(name);
+        let text = std::fs::read(&path)
+            .map_err(|e| self.error(format!("can not include `{}`: {}", name, e)))?;
+        let lines: Vec<String> = String::from_utf8_lossy(&text)
+            .lines()
+            .map(|l| l.to_string())
+            .collect();
+        self.source(&path.to_string_lossy(), &lines, depth + 1)
+    }
+
+    /// Assembles the lines of a macro with the parameters filled in
+    fn expand(
+        &mut self,
+        name: &str,
+        body: &[String],
+        operand: &str,
+        depth: usize,
+    ) -> Result<(), AssemblerError> {
+        if depth >= MAX_DEPTH {
+            return Err(self.error(format!("macro `{}` is nested too deep", name)));
+        }
+        let parameters = split_top_level(operand);
+        self.expansion += 1;
+        let unique = format!("_{:04}", self.expansion);
+        for line in body {
+            let mut text = String::new();
+            let mut chars = line.chars().peekable();
+            while let Some(ch) = chars.next() {
+                match (ch, chars.peek()) {
+                    ('\\', Some('?')) => {
+                        chars.next();
+                        text.push_str(&unique);
+                    }
+                    ('\\', Some(digit)) if digit.is_ascii_digit() => {
+                        let n = digit.to_digit(10).unwrap_or(0) as usize;
+                        chars.next();
+                        if n == 0 {
+                            text.push_str(&parameters.len().to_string());
+                        } else if let Some(parameter) = parameters.get(n - 1) {
+                            text.push_str(parameter);
+                        }
+                    }
+                    _ => text.push(ch),
+                }
+            }
+            self.statement(&text, depth + 1).map_err(|mut e| {
+                if !e.message.contains("(in macro") {
+                    e.message = format!("{} (in macro `{}`)", e.message, name);
+                }
+                e
+            })?;
+        }
+        Ok(())
+    }
+
+    fn instruction(&mut self, op: &str, operand: &str) -> Result<(), AssemblerError> {
+        let mnemonic = op.to_ascii_uppercase();
+        let has = |mode: Mode| self.opcodes.contains_key(&(mnemonic.as_str(), mode));
+        if !self.opcodes.keys().any(|(m, _)| *m == mnemonic) {
+            return Err(self.error(format!("unknown instruction `{}`", op)));
+        }
+
+        // The modes that fit the syntax: (zeropage mode, absolute mode)
+        let syntax = parse_operand(operand);
+        let has_operand = !matches!(syntax, Syntax::None | Syntax::Accumulator);
+        let (text, narrow, wide) = match syntax {
+            Syntax::None if has(Mode::Implied) => ("", None, Mode::Implied),
+            Syntax::None | Syntax::Accumulator => ("", None, Mode::Accumulator),
+            Syntax::Immediate(text) => (text, None, Mode::Immediate),
+            Syntax::Direct(text) if has(Mode::Relative) => (text, None, Mode::Relative),
+            Syntax::Direct(text) => (text, Some(Mode::Zeropage), Mode::Absolute),
+            Syntax::Indexed(text, 'X') => (text, Some(Mode::ZeropageX), Mode::AbsoluteX),
+            Syntax::Indexed(text, _) => (text, Some(Mode::ZeropageY), Mode::AbsoluteY),
+            Syntax::XIndirect(text) => (text, Some(Mode::XIndirect), Mode::AbsoluteXIndirect),
+            Syntax::IndirectY(text) => (text, Some(Mode::IndirectY), Mode::IndirectY),
+            Syntax::Indirect(text) => (text, Some(Mode::ZeropageIndirect), Mode::Indirect),
+            Syntax::Pair(zeropage, target) => {
+                return self.zeropage_relative(&mnemonic, zeropage, target)
+            }
+        };
+        let narrow = narrow.filter(|mode| has(*mode));
+        let wide = Some(wide).filter(|mode| has(*mode));
+
+        // implied and accumulator have no operand
+        let value = if has_operand {
+            self.evaluate(text)?
+        } else {
+            Some(0)
+        };
+        // the size is decided in the first pass and kept in the second
+        let use_wide = if self.pass == 1 {
+            let fits = matches!(value, Some(0..=0xFF));
+            let use_wide = !fits || narrow.is_none();
+            self.wide.push(use_wide);
+            use_wide
+        } else {
+            let use_wide = self.wide.get(self.instruction).copied().unwrap_or(true);
+            self.instruction += 1;
+            use_wide
+        };
+        let mode = match (use_wide, narrow, wide) {
+            (true, _, Some(wide)) | (false, None, Some(wide)) => wide,
+            (_, Some(narrow), _) => narrow,
+            _ => {
+                return Err(self.error(format!(
+                    "`{}` can not be used with the operand `{}`",
+                    mnemonic, operand
+                )))
+            }
+        };
+        let opcode = self.opcodes[&(mnemonic.as_str(), mode)];
+
+        if self.pass == 1 {
+            return self.emit(&vec![0; mode.length()]);
+        }
+        let value = value.unwrap_or(0);
+        let mut bytes = vec![opcode];
+        match mode.length() {
+            1 => {}
+            _ if mode == Mode::Relative => {
+                bytes.push(self.branch(value, 2)?);
+            }
+            2 => bytes.push(self.byte_operand(mode, value)?),
+            _ => {
+                if !(0..=0xFFFF).contains(&value) {
+                    return Err(self.error(format!("{} is not an address", value)));
+                }
+                bytes.extend_from_slice(&(value as u16).to_le_bytes());
+            }
+        }
+        self.emit(&bytes)
+    }
+
+    /// The operand of an instruction with two bytes
+    fn byte_operand(&self, mode: Mode, value: i64) -> Result<u8, AssemblerError> {
+        if mode == Mode::Immediate {
+            return self.byte(value);
+        }
+        if !(0..=0xFF).contains(&value) {
+            return Err(self.error(format!("{} is not a zeropage address", value)));
+        }
+        Ok(value as u8)
+    }
+
+    /// The offset of a branch from an instruction with `length` bytes
+    fn branch(&self, target: i64, length: i64) -> Result<u8, AssemblerError> {
+        let offset = target - (self.pc() as i64 + length);
+        if !(-128..=127).contains(&offset) {
+            return Err(self.error(format!(
+                "the branch target is {} bytes away (-128 to 127)",
+                offset
+            )));
+        }
+        Ok(offset as u8)
+    }
+
+    /// BBR and BBS of the 65C02
+    fn zeropage_relative(
+        &mut self,
+        mnemonic: &str,
+        zeropage: &str,
+        target: &str,
+    ) -> Result<(), AssemblerError> {
+        let opcode = match self.opcodes.get(&(mnemonic, Mode::ZeropageRelative)) {
+            Some(opcode) => *opcode,
+            None => {
+                return Err(self.error(format!(
+                    "`{}` can not be used with two operands on the {:?}",
+                    mnemonic, self.cpu
+                )))
+            }
+        };
+        let zeropage = self.evaluate(zeropage)?;
+        let target = self.evaluate(target)?;
+        if self.pass == 1 {
+            return self.emit(&[0; 3]);
+        }
+        let bytes = [
+            opcode,
+            self.byte_operand(Mode::Zeropage, zeropage.unwrap_or(0))?,
+            self.branch(target.unwrap_or(0), 3)?,
+        ];
+        self.emit(&bytes)
+    }
+}
+
+/// Splits a line into label, operation and operand (without the comment)
+fn split_line(text: &str) -> (&str, &str, &str) {
+    let text = strip_comment(text).trim_end();
+    let (label, rest) = if text.starts_with(|c: char| !c.is_whitespace()) {
+        let end = text
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(text.len());
+        // an instruction that is not indented
+        if Mnemonic::parse(&text[..end]).is_some() && !text[end..].trim_start().starts_with('=') {
+            ("", text)
+        } else {
+            (text[..end].trim_end_matches(':'), &text[end..])
+        }
+    } else {
+        ("", text)
+    };
+    let rest = rest.trim_start();
+    // `name=value` and `name =value`
+    if let Some(value) = rest.strip_prefix('=') {
+        return (label, "=", value.trim());
+    }
+    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    (label, &rest[..end], rest[end..].trim())
+}
+
+/// Removes a `;` comment (but not one in quotes)
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    for (i, ch) in text.char_indices() {
+        match (ch, quote) {
+            ('"', None) => quote = Some('"'),
+            // a character constant is only three characters long
+            ('\'', None) if text[i..].chars().nth(2) == Some('\'') => quote = Some('\''),
+            (c, Some(q)) if c == q => quote = None,
+            (';', None) => return &text[..i],
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Splits at the commas that are not in parentheses or quotes
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+    for (i, ch) in text.char_indices() {
+        match (ch, quote) {
+            ('"', None) => quote = Some('"'),
+            ('\'', None) if text[i..].chars().nth(2) == Some('\'') => quote = Some('\''),
+            (c, Some(q)) if c == q => quote = None,
+            ('(', None) => depth += 1,
+            (')', None) => depth -= 1,
+            (',', None) if depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = text[start..].trim();
+    if !last.is_empty() || !parts.is_empty() {
+        parts.push(last);
+    }
+    parts
+}
+
+fn parse_operand(text: &str) -> Syntax<'_> {
+    if text.is_empty() {
+        return Syntax::None;
+    }
+    if text.eq_ignore_ascii_case("a") {
+        return Syntax::Accumulator;
+    }
+    if let Some(value) = text.strip_prefix('#') {
+        return Syntax::Immediate(value.trim());
+    }
+    if text.starts_with('(') {
+        if let Some(close) = matching_parenthesis(text) {
+            let inner = &text[1..close];
+            let rest = text[close + 1..].trim();
+            let parts = split_top_level(inner);
+            if rest.is_empty() {
+                return match parts.as_slice() {
+                    [value, index] if index.eq_ignore_ascii_case("x") => Syntax::XIndirect(value),
+                    _ => Syntax::Indirect(inner.trim()),
+                };
+            }
+            if let Some(index) = rest.strip_prefix(',') {
+                if index.trim().eq_ignore_ascii_case("y") && parts.len() == 1 {
+                    return Syntax::IndirectY(inner.trim());
+                }
+            }
+        }
+    }
+    match split_top_level(text).as_slice() {
+        [value, index] if index.eq_ignore_ascii_case("x") => Syntax::Indexed(value, 'X'),
+        [value, index] if index.eq_ignore_ascii_case("y") => Syntax::Indexed(value, 'Y'),
+        [first, second] => Syntax::Pair(first, second),
+        _ => Syntax::Direct(text),
+    }
+}
+
+/// The index of the `)` that closes the `(` at the start
+fn matching_parenthesis(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, ch) in text.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
@@ -0,0 +1,14 @@
+// The debug adapter on stdin and stdout
+//
+// Editors start it as the program of a debug adapter, everything
+// else comes with the `launch` request.
+
+use sixfiveohtwo::dap::{self, Server};
+
+fn main() {
+    let mut server = Server::new();
+    if let Err(e) = dap::serve(&mut server, std::io::stdin(), std::io::stdout()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,167 @@
+// The monitor on the command line
+//
+// monitor [--tui] [--script <file>] [<image> [address]]
+//
+// The image is loaded like with `load`, the script runs before the
+// prompt shows up (and the monitor ends if it quits).
+//
+// With `--tui` the full-screen debugger is used instead of the prompt.
+// It puts the terminal into raw mode with `stty` (and back at the end).
+
+use std::io::{self, BufRead, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+use sixfiveohtwo::monitor::{Monitor, GDB_ADDRESS};
+use sixfiveohtwo::tui::{self, Tui};
+
+/// How many instructions run between looking for a key
+const SLICE: u64 = 10_000;
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let mut monitor = Monitor::new();
+
+    let full_screen = match args.iter().position(|a| a == "--tui") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let script = match args.iter().position(|a| a == "--script") {
+        Some(i) if i + 1 < args.len() => {
+            let file = args.remove(i + 1);
+            args.remove(i);
+            Some(file)
+        }
+        Some(_) => {
+            eprintln!("--script needs a file");
+            std::process::exit(2);
+        }
+        None => None,
+    };
+
+    if !args.is_empty() {
+        run(&mut monitor, &format!("load {}", args.join(" ")));
+    }
+    if let Some(file) = script {
+        run(&mut monitor, &format!("source {}", file));
+    }
+
+    if full_screen {
+        run_tui(monitor);
+        return;
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    while !monitor.quit() {
+        print!("({:04x}) ", monitor.chip.pc);
+        io::stdout().flush().ok();
+        match lines.next() {
+            Some(Ok(line)) => run(&mut monitor, &line),
+            _ => break,
+        }
+    }
+}
+
+fn run(monitor: &mut Monitor, line: &str) {
+    // `gdb` blocks until the debugger detaches
+    let mut words = line.split_whitespace();
+    if words.next() == Some("gdb") {
+        let address = words.next().unwrap_or(GDB_ADDRESS);
+        eprintln!("waiting for a debugger on {}", address);
+    }
+    match monitor.command(line) {
+        Ok(text) => print!("{}", text),
+        Err(e) => eprintln!("{}", e),
+    }
+}
+
+fn run_tui(monitor: Monitor) {
+    let terminal = match Terminal::raw() {
+        Some(terminal) => terminal,
+        None => {
+            eprintln!("--tui needs a terminal (and stty)");
+            std::process::exit(1);
+        }
+    };
+
+    // stdin is read by a thread, so a running program can be
+    // stopped by any key
+    let (sender, keys) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buffer = [0u8; 64];
+        while let Ok(count) = stdin.read(&mut buffer) {
+            if count == 0 || sender.send(buffer[..count].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut tui = Tui::new(monitor);
+    let mut redraw = true;
+    while !tui.quit() {
+        if redraw {
+            print!("{}", tui.render());
+            io::stdout().flush().ok();
+        }
+        if tui.running() {
+            tui.run(SLICE);
+            redraw = !tui.running();
+            match keys.try_recv() {
+                // even keys that are not keys of the debugger (like ^C)
+                Ok(_) => tui.pause(),
+                Err(TryRecvError::Empty) => continue,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            let bytes = match keys.recv() {
+                Ok(bytes) => bytes,
+                Err(_) => break,
+            };
+            for key in tui::parse_keys(&bytes) {
+                tui.key(key);
+            }
+        }
+        redraw = true;
+    }
+    drop(terminal);
+}
+
+/// The terminal in raw mode on the alternate screen,
+/// it is restored when this is dropped (also after a panic)
+struct Terminal {
+    // The settings of stty before
+    settings: String,
+}
+
+impl Terminal {
+    fn raw() -> Option<Terminal> {
+        // stty reads the settings of the terminal at stdin
+        let output = Command::new("stty")
+            .arg("-g")
+            .stdin(Stdio::inherit())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let settings = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Command::new("stty").args(["raw", "-echo"]).status().ok();
+        // the alternate screen and no cursor
+        print!("\x1b[?1049h\x1b[?25l");
+        Some(Terminal { settings })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        io::stdout().flush().ok();
+        Command::new("stty").arg(&self.settings).status().ok();
+    }
+}
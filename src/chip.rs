@@ -1,7 +1,16 @@
 // Imports for reading a file
+use std::collections::HashMap;
 use std::fs::File;
-use std::io;
 use std::io::prelude::*;
+use std::ops::RangeInclusive;
+
+use crate::instruction::{Instruction, Mnemonic, Operand};
+use crate::loader::{
+    binary, check_range, ihex, ines, o65, patch, prg, sim65, srec, Image, LoadError,
+};
+use crate::opcodes;
+use crate::power_on::{fill_memory, PowerOn, Rng};
+use crate::snapshot;
 
 // max number of an u16
 pub const MEMORY: usize = 65536;
@@ -17,19 +26,12 @@ pub const I: u8 = 0x04; // [0000 0100] interupt disable
 pub const Z: u8 = 0x02; // [0000 0010] zero
 pub const C: u8 = 0x01; // [0000 0001] carry
 
+/// The variants of the CPU: the NMOS 6502 and the CMOS 65C02
+/// (the chip itself executes the 6502 instructions)
 #[derive(Debug, PartialEq, Copy, Clone)]
-enum AddressMode {
-    Accumulator,
-    Absolute,
-    AbsoluteX,
-    AbsoluteY,
-    Immediate,
-    Indirect,
-    XIndirect,
-    IndirectY,
-    Zeropage,
-    ZeropageX,
-    ZeropageY,
+pub enum Cpu {
+    Mos6502,
+    Wdc65C02,
 }
 
 pub struct Chip {
@@ -54,19 +56,49 @@ pub struct Chip {
     // $FFFC, $FFFD ... RES (Reset) vector, 16-bit (LB, HB)
     // $FFFE, $FFFF ... IRQ (Interrupt Request) vector, 16-bit (LB, HB)
     pub memory: [u8; MEMORY],
+    // The cycles of the instructions and interrupts executed so far:
+    pub cycles: u64,
+    // Interrupt requests that are taken before the next instruction:
+    pub irq: bool,
+    pub nmi: bool,
+}
+
+impl Default for Chip {
+    fn default() -> Chip {
+        Chip::new()
+    }
 }
 
 impl Chip {
     pub fn new() -> Chip {
-        Chip {
+        Chip::power_on(&PowerOn::default())
+    }
+
+    /// Creates a chip in the state described by the config.
+    ///
+    /// The same config (and seed) always gives the same chip.
+    pub fn power_on(config: &PowerOn) -> Chip {
+        let mut rng = Rng::new(config.seed);
+        let mut c = Chip {
             acc: 0,
             rx: 0,
             ry: 0,
             f: 0,
             sp: 0xFF,
-            pc: 0x200,
+            pc: config.pc,
             memory: [0; MEMORY],
+            cycles: 0,
+            irq: false,
+            nmi: false,
+        };
+        fill_memory(&mut c.memory, config.fill, &mut rng);
+        if config.random_registers {
+            c.acc = rng.next_byte();
+            c.rx = rng.next_byte();
+            c.ry = rng.next_byte();
+            c.f = rng.next_byte();
         }
+        c
     }
 
     // ======================================
@@ -77,27 +109,262 @@ impl Chip {
         self.pc = address;
     }
 
+    /// Loads the program to $0200.
+    ///
+    /// Panics if the program does not fit into the memory,
+    /// use `load_bytes` to get an error instead.
     pub fn load_program(&mut self, prog: Vec<u8>) {
-        for i in 0..prog.len() {
-            self.memory[0x200 + i] = prog[i];
+        if let Err(e) = self.load_bytes(0x200, &prog) {
+            panic!("can not load the program: {}", e);
+        }
+    }
+
+    /// Writes the bytes to the memory starting at the address
+    pub fn load_bytes(&mut self, address: u16, bytes: &[u8]) -> Result<(), LoadError> {
+        check_range(address as u32, bytes.len())?;
+        let start = address as usize;
+        self.memory[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Loads a whole raw binary file to the given address
+    /// (the functional test is loaded with `0x000A`).
+    pub fn load_exe(&mut self, file_path: String, zeropage_start: usize) -> Result<(), LoadError> {
+        if zeropage_start > 0xFFFF {
+            return Err(LoadError::OutOfRange {
+                address: zeropage_start as u32,
+                length: 0,
+                line: None,
+            });
         }
+        self.load_binary(file_path, zeropage_start as u16, 0, None)?;
+        Ok(())
     }
 
-    pub fn load_exe(&mut self, file_path: String, zeropage_start: usize) -> io::Result<()> {
+    /// Loads a raw binary file to the given address and returns
+    /// the number of bytes that were loaded.
+    ///
+    /// Only the part from `offset` on is loaded, and if a `length`
+    /// is given only that many bytes.
+    pub fn load_binary(
+        &mut self,
+        file_path: String,
+        address: u16,
+        offset: usize,
+        length: Option<usize>,
+    ) -> Result<usize, LoadError> {
         let mut f = File::open(file_path)?;
         let mut buffer = Vec::new();
-
         f.read_to_end(&mut buffer)?;
-        // println!("buffer.len() = {}", buffer.len());
-        // println!("{:?}", buffer);
-        // println!("self.memory.len() = {}", self.memory.len());
-        for i in zeropage_start..MEMORY {
-            // println!("{:>08b}", self.memory[i]);
-            self.memory[i] = buffer[i - zeropage_start];
+
+        self.place_binary(&buffer, address, offset, length)
+    }
+
+    /// Loads a raw binary file with an IPS or BPS patch applied to it
+    /// and returns the number of bytes that were loaded
+    pub fn load_binary_patched(
+        &mut self,
+        file_path: String,
+        patch_path: String,
+        address: u16,
+    ) -> Result<usize, LoadError> {
+        let bytes = patch::apply(&std::fs::read(file_path)?, &std::fs::read(patch_path)?)?;
+        self.place_binary(&bytes, address, 0, None)
+    }
+
+    fn place_binary(
+        &mut self,
+        bytes: &[u8],
+        address: u16,
+        offset: usize,
+        length: Option<usize>,
+    ) -> Result<usize, LoadError> {
+        let image = binary::parse(bytes, address, offset, length)?;
+        self.load_image(&image)?;
+        Ok(image.segments.iter().map(|s| s.data.len()).sum())
+    }
+
+    /// Applies an IPS or BPS patch to the `length` bytes of the
+    /// memory at the address (the patch sees them as its file).
+    ///
+    /// Nothing gets written if the patch fails or its result
+    /// does not fit into the memory.
+    pub fn apply_patch(
+        &mut self,
+        patch_path: String,
+        address: u16,
+        length: usize,
+    ) -> Result<(), LoadError> {
+        check_range(address as u32, length)?;
+        let start = address as usize;
+        let source = &self.memory[start..start + length];
+        let patched = patch::apply(source, &std::fs::read(patch_path)?)?;
+        self.load_bytes(address, &patched)
+    }
+
+    /// Places all the segments of an image in the memory.
+    ///
+    /// Nothing gets written if any segment does not fit.
+    pub fn load_image(&mut self, image: &Image) -> Result<(), LoadError> {
+        for segment in &image.segments {
+            check_range(segment.address as u32, segment.data.len())?;
+        }
+        for segment in &image.segments {
+            let start = segment.address as usize;
+            self.memory[start..start + segment.data.len()].copy_from_slice(&segment.data);
+        }
+        Ok(())
+    }
+
+    /// Loads an Intel HEX file.
+    ///
+    /// If `set_pc` is true and the file has a start address record,
+    /// the program counter is set to it.
+    pub fn load_ihex(&mut self, file_path: String, set_pc: bool) -> Result<(), LoadError> {
+        let text = std::fs::read_to_string(file_path)?;
+        let image = ihex::parse(&text)?;
+        self.load_image(&image)?;
+        if let (true, Some(start)) = (set_pc, image.start) {
+            self.pc = start;
+        }
+        Ok(())
+    }
+
+    /// Loads a Motorola S-record file (S19, S28 or S37).
+    ///
+    /// If `set_pc` is true and the file has a S9/S8/S7 record,
+    /// the program counter is set to its start address.
+    pub fn load_srec(&mut self, file_path: String, set_pc: bool) -> Result<(), LoadError> {
+        let text = std::fs::read_to_string(file_path)?;
+        let image = srec::parse(&text)?;
+        self.load_image(&image)?;
+        if let (true, Some(start)) = (set_pc, image.start) {
+            self.pc = start;
+        }
+        Ok(())
+    }
+
+    /// Loads a Commodore PRG file to the address in its header
+    /// and returns the addresses it occupies.
+    ///
+    /// If `sys_start` is true the program counter is set to the
+    /// address of the `SYS` in its BASIC line (like typing RUN on a C64).
+    pub fn load_prg(
+        &mut self,
+        file_path: String,
+        sys_start: bool,
+    ) -> Result<RangeInclusive<u16>, LoadError> {
+        let image = prg::parse(&std::fs::read(file_path)?)?;
+        let start = if sys_start {
+            match prg::sys_address(&image) {
+                Some(address) => Some(address),
+                None => {
+                    return Err(LoadError::Format(
+                        "the program does not start with a BASIC SYS line".to_string(),
+                    ))
+                }
+            }
+        } else {
+            None
+        };
+        self.load_image(&image)?;
+        if let Some(start) = start {
+            self.pc = start;
+        }
+        let segment = &image.segments[0];
+        Ok(segment.address..=segment.address + (segment.data.len() - 1) as u16)
+    }
+
+    /// Loads an iNES (or NES 2.0) ROM with the NROM mapping,
+    /// sets the program counter to its reset vector and
+    /// returns the ROM so the host can use the CHR data.
+    ///
+    /// Test ROMs that run without a PPU start somewhere else,
+    /// nestest.nes for example at `ines::NESTEST_START`.
+    pub fn load_ines(&mut self, file_path: String) -> Result<ines::Rom, LoadError> {
+        self.place_ines(&std::fs::read(file_path)?)
+    }
+
+    /// Loads an iNES ROM with an IPS or BPS patch applied to the
+    /// file (like `load_ines`)
+    pub fn load_ines_patched(
+        &mut self,
+        file_path: String,
+        patch_path: String,
+    ) -> Result<ines::Rom, LoadError> {
+        let bytes = patch::apply(&std::fs::read(file_path)?, &std::fs::read(patch_path)?)?;
+        self.place_ines(&bytes)
+    }
+
+    fn place_ines(&mut self, bytes: &[u8]) -> Result<ines::Rom, LoadError> {
+        let rom = ines::parse(bytes)?;
+        let image = rom.image()?;
+        self.load_image(&image)?;
+        if let Some(start) = image.start {
+            self.pc = start;
+        }
+        Ok(rom)
+    }
+
+    /// Loads an o65 file with its segments moved to the given bases
+    /// (`o65::parse(..)?.bases` are the ones it was assembled for).
+    ///
+    /// The undefined symbols of the file are resolved with `imports`,
+    /// the exported symbols are returned at their new addresses.
+    pub fn load_o65(
+        &mut self,
+        file_path: String,
+        bases: &o65::Bases,
+        imports: &HashMap<String, u16>,
+    ) -> Result<HashMap<String, u16>, LoadError> {
+        let object = o65::parse(&std::fs::read(file_path)?)?;
+        let linked = object.relocate(bases, imports)?;
+        self.load_image(&linked.image)?;
+        Ok(linked.exports)
+    }
+
+    /// Loads a cc65 sim65 binary and starts it like sim65 does:
+    /// the reset vector is set to the reset address of the header
+    /// and the chip is reset.
+    ///
+    /// The header is returned, the paravirtualization hooks need
+    /// the address of the C stack pointer from it.
+    pub fn load_sim65(&mut self, file_path: String) -> Result<sim65::Header, LoadError> {
+        let (header, image) = sim65::parse(&std::fs::read(file_path)?)?;
+        if header.cpu != Cpu::Mos6502 {
+            return Err(LoadError::Format(
+                "only programs for the 6502 can be run (not 65C02)".to_string(),
+            ));
         }
+        self.load_image(&image)?;
+        let (ll, hh) = self.word_to_bytes(header.reset);
+        self.memory[0xFFFC] = ll;
+        self.memory[0xFFFD] = hh;
+        self.reset();
+        Ok(header)
+    }
+
+    /// Does what the RES line does: the program counter is loaded
+    /// from the reset vector, interrupts are disabled and the stack
+    /// pointer ends up at $FD.
+    pub fn reset(&mut self) {
+        self.pc = self.read_word(0xFFFC);
+        self.sp = 0xFD;
+        self.f = R | I;
+    }
+
+    /// Writes a snapshot of the chip (registers, memory, cycles and
+    /// pending interrupts) to a file
+    pub fn save_state(&self, file_path: String) -> Result<(), LoadError> {
+        std::fs::write(file_path, snapshot::save(self))?;
         Ok(())
     }
 
+    /// Restores the chip from a snapshot file
+    pub fn load_state(&mut self, file_path: String) -> Result<(), LoadError> {
+        snapshot::restore(self, &std::fs::read(file_path)?)
+    }
+
     // =====================
     // Helper functions
     // =====================
@@ -113,8 +380,7 @@ impl Chip {
     /// and then increments the stack pointer
     fn pop_stack(&mut self) -> u8 {
         (self.sp, _) = self.sp.overflowing_add(1);
-        let data = self.memory[0x0100 + self.sp as usize];
-        data
+        self.memory[0x0100 + self.sp as usize]
     }
 
     /// Reads a byte from the given address
@@ -141,17 +407,10 @@ impl Chip {
         self.bytes_to_word(b1, b2)
     }
 
-    /// Fetches a word from the programm counter
-    fn fetch_word(&mut self) -> u16 {
-        let ll = self.fetch_byte();
-        let hh = self.fetch_byte();
-        self.bytes_to_word(ll, hh)
-    }
-
     /// takes a u16 (word) number and returns
     /// two u8 (byte) numbers back
     fn word_to_bytes(&self, word: u16) -> (u8, u8) {
-        (word as u8 & 0xFF, (word >> 8) as u8)
+        (word as u8, (word >> 8) as u8)
     }
 
     /// takes two u8 (byte) numbers and returns a
@@ -206,249 +465,227 @@ impl Chip {
             if offset > 0x80 {
                 // This code here gets the positive form a into negative
                 // parsed i8 as an u8
-                self.pc -= (offset as i8 * -1i8) as u16;
+                self.pc -= -(offset as i8) as u16;
             } else if offset == 0x80 {
                 self.pc -= 128;
             }
         }
     }
 
-    // Returns the address depending on the given AddressMode.
-    fn get_address(&mut self, addr: AddressMode) -> u16 {
-        match addr {
-            AddressMode::Immediate => {
-                // Need to increment the pc
-                // Else it would not register that we have
-                // "read" the Immediate Address
-                self.pc += 1;
-                return self.pc - 1;
-            }
-            AddressMode::Absolute => {
-                let address = self.fetch_word();
-                return address;
-            }
-            AddressMode::Zeropage => {
-                let ll = self.fetch_byte();
-                let address = ll as u16;
-                return address;
-            }
-            AddressMode::AbsoluteX => {
-                let address = self.fetch_word();
+    // Returns the address of the operand.
+    fn get_address(&mut self, op: Operand) -> u16 {
+        match op {
+            Operand::Absolute(address) => address,
+            Operand::Zeropage(ll) => ll as u16,
+            Operand::AbsoluteX(address) => {
                 let x = self.rx;
-                return address + x as u16;
+                address + x as u16
             }
-            AddressMode::AbsoluteY => {
-                let address = self.fetch_word();
+            Operand::AbsoluteY(address) => {
                 let y = self.ry;
-                return address + y as u16;
+                address + y as u16
             }
-            AddressMode::ZeropageX => {
-                let ll = self.fetch_byte();
+            Operand::ZeropageX(ll) => {
                 let x = self.rx;
                 let (address, _) = ll.overflowing_add(x);
-                return address as u16;
+                address as u16
             }
-            AddressMode::ZeropageY => {
-                let ll = self.fetch_byte();
+            Operand::ZeropageY(ll) => {
                 let y = self.ry;
                 let (address, _) = ll.overflowing_add(y);
-                return address as u16;
-            }
-            AddressMode::Indirect => {
-                let address = self.fetch_word();
-                let address2 = self.read_word(address);
-                return address2;
+                address as u16
             }
-            AddressMode::XIndirect => {
-                let ll = self.fetch_byte();
+            Operand::Indirect(address) => self.read_word(address),
+            Operand::XIndirect(ll) => {
                 let x = self.rx;
                 let (address, _) = ll.overflowing_add(x);
-                return self.read_word(address as u16);
+                self.read_word(address as u16)
             }
-            AddressMode::IndirectY => {
-                let ll = self.fetch_byte();
+            Operand::IndirectY(ll) => {
                 let y = self.ry;
                 let address = ll as u16;
-                let address2 = self.read_word(address) + y as u16;
-                return address2;
+                self.read_word(address) + y as u16
             }
             _ => {
                 // This here needs to return nothing ...
                 // because the ...
-                // AddressMode::Accumulator is not handled ...
+                // Operand::Accumulator is not handled ...
                 // here and can also not be handled ...
                 // because it is kind of special ...
-                return 0;
+                0
+            }
+        }
+    }
+
+    // Returns the value of the operand, an immediate
+    // value or the byte at the address of the operand.
+    fn read_operand(&mut self, op: Operand) -> u8 {
+        match op {
+            Operand::Immediate(value) => value,
+            _ => {
+                let address = self.get_address(op);
+                self.read_byte(address)
             }
         }
     }
 
     /// This here is called execute_cycle but strictly speaking
-    /// it only executes an OPCODE
+    /// it only executes an OPCODE (or takes a pending interrupt)
     pub fn execute_cycle(&mut self) {
+        if self.nmi {
+            self.nmi = false;
+            self.interrupt(0xFFFA);
+            return;
+        }
+        if self.irq && self.f & I == 0 {
+            self.irq = false;
+            self.interrupt(0xFFFE);
+            return;
+        }
         let opcode: u8 = self.fetch_byte();
         self.process_opcode(opcode);
     }
 
     /// Processes an opcode and calls the correct function for the opcode
     fn process_opcode(&mut self, opcode: u8) {
-        // opcode
-        let op_1 = (opcode & 0xF0) >> 4;
-        let op_2 = opcode & 0x0F;
-
-        match (op_1, op_2) {
-            (0x0, 0x0) => self.brk(),
-            (0x0, 0x1) => self.ora(AddressMode::XIndirect),
-            (0x0, 0x5) => self.ora(AddressMode::Zeropage),
-            (0x0, 0x6) => self.asl(AddressMode::Zeropage),
-            (0x0, 0x8) => self.php(),
-            (0x0, 0x9) => self.ora(AddressMode::Immediate),
-            (0x0, 0xA) => self.asl(AddressMode::Accumulator),
-            (0x0, 0xD) => self.ora(AddressMode::Absolute),
-            (0x0, 0xE) => self.asl(AddressMode::Absolute),
-            (0x1, 0x0) => self.bpl(),
-            (0x1, 0x1) => self.ora(AddressMode::IndirectY),
-            (0x1, 0x5) => self.ora(AddressMode::ZeropageX),
-            (0x1, 0x6) => self.asl(AddressMode::ZeropageX),
-            (0x1, 0x8) => self.clc(),
-            (0x1, 0x9) => self.ora(AddressMode::AbsoluteY),
-            (0x1, 0xD) => self.ora(AddressMode::AbsoluteX),
-            (0x1, 0xE) => self.asl(AddressMode::AbsoluteX),
-            (0x2, 0x0) => self.jsr(),
-            (0x2, 0x4) => self.bit(AddressMode::Zeropage),
-            (0x2, 0x1) => self.and(AddressMode::XIndirect),
-            (0x2, 0x5) => self.and(AddressMode::Zeropage),
-            (0x2, 0x6) => self.rol(AddressMode::Zeropage),
-            (0x2, 0x8) => self.plp(),
-            (0x2, 0x9) => self.and(AddressMode::Immediate),
-            (0x2, 0xA) => self.rol(AddressMode::Accumulator),
-            (0x2, 0xC) => self.bit(AddressMode::Absolute),
-            (0x2, 0xD) => self.and(AddressMode::Absolute),
-            (0x2, 0xE) => self.rol(AddressMode::Absolute),
-            (0x3, 0x0) => self.bmi(),
-            (0x3, 0x1) => self.and(AddressMode::IndirectY),
-            (0x3, 0x5) => self.and(AddressMode::ZeropageX),
-            (0x3, 0x6) => self.rol(AddressMode::ZeropageX),
-            (0x3, 0x8) => self.sec(),
-            (0x3, 0x9) => self.and(AddressMode::AbsoluteY),
-            (0x3, 0xD) => self.and(AddressMode::AbsoluteX),
-            (0x3, 0xE) => self.rol(AddressMode::AbsoluteX),
-            (0x4, 0x0) => self.rti(),
-            (0x4, 0x1) => self.eor(AddressMode::XIndirect),
-            (0x4, 0x5) => self.eor(AddressMode::Zeropage),
-            (0x4, 0x6) => self.lsr(AddressMode::Zeropage),
-            (0x4, 0x8) => self.pha(),
-            (0x4, 0x9) => self.eor(AddressMode::Immediate),
-            (0x4, 0xA) => self.lsr(AddressMode::Accumulator),
-            (0x4, 0xC) => self.jmp(AddressMode::Absolute),
-            (0x4, 0xE) => self.lsr(AddressMode::Absolute),
-            (0x4, 0xD) => self.eor(AddressMode::Absolute),
-            (0x5, 0x0) => self.bvc(),
-            (0x5, 0x1) => self.eor(AddressMode::IndirectY),
-            (0x5, 0x5) => self.eor(AddressMode::ZeropageX),
-            (0x5, 0x6) => self.lsr(AddressMode::ZeropageX),
-            (0x5, 0x8) => self.cli(),
-            (0x5, 0x9) => self.eor(AddressMode::AbsoluteY),
-            (0x5, 0xD) => self.eor(AddressMode::AbsoluteX),
-            (0x5, 0xE) => self.lsr(AddressMode::AbsoluteX),
-            (0x6, 0x0) => self.rts(),
-            (0x6, 0x1) => self.adc(AddressMode::XIndirect),
-            (0x6, 0x5) => self.adc(AddressMode::Zeropage),
-            (0x6, 0x6) => self.ror(AddressMode::Zeropage),
-            (0x6, 0x8) => self.pla(),
-            (0x6, 0x9) => self.adc(AddressMode::Immediate),
-            (0x6, 0xC) => self.jmp(AddressMode::Indirect),
-            (0x6, 0xA) => self.ror(AddressMode::Accumulator),
-            (0x6, 0xD) => self.adc(AddressMode::Absolute),
-            (0x6, 0xE) => self.ror(AddressMode::Absolute),
-            (0x7, 0x0) => self.bvs(),
-            (0x7, 0x1) => self.adc(AddressMode::IndirectY),
-            (0x7, 0x5) => self.adc(AddressMode::ZeropageX),
-            (0x7, 0x6) => self.ror(AddressMode::ZeropageX),
-            (0x7, 0x8) => self.sei(),
-            (0x7, 0x9) => self.adc(AddressMode::AbsoluteY),
-            (0x7, 0xD) => self.adc(AddressMode::AbsoluteX),
-            (0x7, 0xE) => self.ror(AddressMode::AbsoluteX),
-            (0x8, 0x1) => self.sta(AddressMode::XIndirect),
-            (0x8, 0x4) => self.sty(AddressMode::Zeropage),
-            (0x8, 0x5) => self.sta(AddressMode::Zeropage),
-            (0x8, 0x6) => self.stx(AddressMode::Zeropage),
-            (0x8, 0x8) => self.dey(),
-            (0x8, 0xC) => self.sty(AddressMode::Absolute),
-            (0x8, 0xA) => self.txa(),
-            (0x8, 0xD) => self.sta(AddressMode::Absolute),
-            (0x8, 0xE) => self.stx(AddressMode::Absolute),
-            (0x9, 0x0) => self.bcc(),
-            (0x9, 0x1) => self.sta(AddressMode::IndirectY),
-            (0x9, 0x4) => self.sty(AddressMode::ZeropageX),
-            (0x9, 0x5) => self.sta(AddressMode::ZeropageX),
-            (0x9, 0x6) => self.stx(AddressMode::ZeropageY),
-            (0x9, 0x8) => self.tya(),
-            (0x9, 0x9) => self.sta(AddressMode::AbsoluteY),
-            (0x9, 0xA) => self.txs(),
-            (0x9, 0xD) => self.sta(AddressMode::AbsoluteX),
-            (0xA, 0x0) => self.ldy(AddressMode::Immediate),
-            (0xA, 0x1) => self.lda(AddressMode::XIndirect),
-            (0xA, 0x2) => self.ldx(AddressMode::Immediate),
-            (0xA, 0x4) => self.ldy(AddressMode::Zeropage),
-            (0xA, 0x5) => self.lda(AddressMode::Zeropage),
-            (0xA, 0x6) => self.ldx(AddressMode::Zeropage),
-            (0xA, 0x8) => self.tay(),
-            (0xA, 0x9) => self.lda(AddressMode::Immediate),
-            (0xA, 0xA) => self.tax(),
-            (0xA, 0xC) => self.ldy(AddressMode::Absolute),
-            (0xA, 0xD) => self.lda(AddressMode::Absolute),
-            (0xA, 0xE) => self.ldx(AddressMode::Absolute),
-            (0xB, 0x0) => self.bcs(),
-            (0xB, 0x1) => self.lda(AddressMode::IndirectY),
-            (0xB, 0x4) => self.ldy(AddressMode::ZeropageX),
-            (0xB, 0x5) => self.lda(AddressMode::ZeropageX),
-            (0xB, 0x6) => self.ldx(AddressMode::ZeropageY),
-            (0xB, 0x8) => self.clv(),
-            (0xB, 0x9) => self.lda(AddressMode::AbsoluteY),
-            (0xB, 0xA) => self.tsx(),
-            (0xB, 0xC) => self.ldy(AddressMode::AbsoluteX),
-            (0xB, 0xD) => self.lda(AddressMode::AbsoluteX),
-            (0xB, 0xE) => self.ldx(AddressMode::AbsoluteY),
-            (0xC, 0x0) => self.cpy(AddressMode::Immediate),
-            (0xC, 0x1) => self.cmp(AddressMode::XIndirect),
-            (0xC, 0x4) => self.cpy(AddressMode::Zeropage),
-            (0xC, 0x5) => self.cmp(AddressMode::Zeropage),
-            (0xC, 0x6) => self.dec(AddressMode::Zeropage),
-            (0xC, 0x8) => self.iny(),
-            (0xC, 0x9) => self.cmp(AddressMode::Immediate),
-            (0xC, 0xC) => self.cpy(AddressMode::Absolute),
-            (0xC, 0xD) => self.cmp(AddressMode::Absolute),
-            (0xC, 0xA) => self.dex(),
-            (0xC, 0xE) => self.dec(AddressMode::Absolute),
-            (0xD, 0x0) => self.bne(),
-            (0xD, 0x1) => self.cmp(AddressMode::IndirectY),
-            (0xD, 0x5) => self.cmp(AddressMode::ZeropageX),
-            (0xD, 0x6) => self.dec(AddressMode::ZeropageX),
-            (0xD, 0x8) => self.cld(),
-            (0xD, 0x9) => self.cmp(AddressMode::AbsoluteY),
-            (0xD, 0xD) => self.cmp(AddressMode::AbsoluteX),
-            (0xD, 0xE) => self.dec(AddressMode::AbsoluteX),
-            (0xE, 0x0) => self.cpx(AddressMode::Immediate),
-            (0xE, 0x1) => self.sbc(AddressMode::XIndirect),
-            (0xE, 0x4) => self.cpx(AddressMode::Zeropage),
-            (0xE, 0x5) => self.sbc(AddressMode::Zeropage),
-            (0xE, 0x6) => self.inc(AddressMode::Zeropage),
-            (0xE, 0x8) => self.inx(),
-            (0xE, 0x9) => self.sbc(AddressMode::Immediate),
-            (0xE, 0xA) => self.nop(),
-            (0xE, 0xC) => self.cpx(AddressMode::Absolute),
-            (0xE, 0xD) => self.sbc(AddressMode::Absolute),
-            (0xE, 0xE) => self.inc(AddressMode::Absolute),
-            (0xF, 0x0) => self.beq(),
-            (0xF, 0x1) => self.sbc(AddressMode::IndirectY),
-            (0xF, 0x5) => self.sbc(AddressMode::ZeropageX),
-            (0xF, 0x8) => self.sed(),
-            (0xF, 0x9) => self.sbc(AddressMode::AbsoluteY),
-            (0xF, 0xD) => self.sbc(AddressMode::AbsoluteX),
-            (0xF, 0x6) => self.inc(AddressMode::ZeropageX),
-            (0xF, 0xE) => self.inc(AddressMode::AbsoluteX),
-            _ => {}
+        let metadata = opcodes::metadata(opcode);
+        self.cycles += metadata.cycles as u64;
+        // the undocumented opcodes do nothing
+        let mnemonic = match metadata.instruction {
+            Some(mnemonic) => mnemonic,
+            None => return,
+        };
+        let bytes = [
+            self.memory[self.pc as usize],
+            self.memory[self.pc.wrapping_add(1) as usize],
+        ];
+        if let Some(operand) = Operand::from_bytes(metadata.mode, &bytes) {
+            self.pc = self.pc.wrapping_add(metadata.length as u16 - 1);
+            let next = self.pc;
+            if metadata.page_penalty > 0 && self.crosses_page(operand) {
+                self.cycles += metadata.page_penalty as u64;
+            }
+            self.dispatch(mnemonic, operand);
+            // a branch that is taken, and another cycle to another page
+            if let (Operand::Relative(_), true) = (operand, self.pc != next) {
+                self.cycles += 1;
+                if self.pc & 0xFF00 != next & 0xFF00 {
+                    self.cycles += 1;
+                }
+            }
+        }
+    }
+
+    /// If an indexed operand ends up on another page than its base
+    fn crosses_page(&mut self, op: Operand) -> bool {
+        let (base, index) = match op {
+            Operand::AbsoluteX(address) => (address, self.rx),
+            Operand::AbsoluteY(address) => (address, self.ry),
+            Operand::IndirectY(address) => (self.read_word(address as u16), self.ry),
+            _ => return false,
+        };
+        base & 0xFF00 != base.wrapping_add(index as u16) & 0xFF00
+    }
+
+    /// Takes an IRQ or NMI: pushes the program counter and the
+    /// flags (without B) and jumps through the vector
+    fn interrupt(&mut self, vector: u16) {
+        let (ll, hh) = self.word_to_bytes(self.pc);
+        self.push_stack(hh);
+        self.push_stack(ll);
+        self.push_stack((self.f & !B) | R);
+        self.set_flag(I);
+        self.pc = self.read_word(vector);
+        self.cycles += 7;
+    }
+
+    /// Executes an instruction, the program counter has to point
+    /// behind it (like after fetching it from the memory).
+    ///
+    /// The instructions that only the 65C02 has do nothing.
+    pub fn execute(&mut self, instruction: &Instruction) {
+        if opcodes::find(instruction.mnemonic, instruction.operand.mode()).is_some() {
+            self.dispatch(instruction.mnemonic, instruction.operand);
+        }
+    }
+
+    fn dispatch(&mut self, mnemonic: Mnemonic, op: Operand) {
+        let offset = |op: Operand| match op {
+            Operand::Relative(offset) => offset as u8,
+            _ => 0,
+        };
+        match mnemonic {
+            Mnemonic::Adc => self.adc(op),
+            Mnemonic::And => self.and(op),
+            Mnemonic::Asl => self.asl(op),
+            Mnemonic::Bcc => self.bcc(offset(op)),
+            Mnemonic::Bcs => self.bcs(offset(op)),
+            Mnemonic::Beq => self.beq(offset(op)),
+            Mnemonic::Bit => self.bit(op),
+            Mnemonic::Bmi => self.bmi(offset(op)),
+            Mnemonic::Bne => self.bne(offset(op)),
+            Mnemonic::Bpl => self.bpl(offset(op)),
+            Mnemonic::Brk => self.brk(),
+            Mnemonic::Bvc => self.bvc(offset(op)),
+            Mnemonic::Bvs => self.bvs(offset(op)),
+            Mnemonic::Clc => self.clc(),
+            Mnemonic::Cld => self.cld(),
+            Mnemonic::Cli => self.cli(),
+            Mnemonic::Clv => self.clv(),
+            Mnemonic::Cmp => self.cmp(op),
+            Mnemonic::Cpx => self.cpx(op),
+            Mnemonic::Cpy => self.cpy(op),
+            Mnemonic::Dec => self.dec(op),
+            Mnemonic::Dex => self.dex(),
+            Mnemonic::Dey => self.dey(),
+            Mnemonic::Eor => self.eor(op),
+            Mnemonic::Inc => self.inc(op),
+            Mnemonic::Inx => self.inx(),
+            Mnemonic::Iny => self.iny(),
+            Mnemonic::Jmp => self.jmp(op),
+            Mnemonic::Jsr => self.jsr(op),
+            Mnemonic::Lda => self.lda(op),
+            Mnemonic::Ldx => self.ldx(op),
+            Mnemonic::Ldy => self.ldy(op),
+            Mnemonic::Lsr => self.lsr(op),
+            Mnemonic::Nop => self.nop(),
+            Mnemonic::Ora => self.ora(op),
+            Mnemonic::Pha => self.pha(),
+            Mnemonic::Php => self.php(),
+            Mnemonic::Pla => self.pla(),
+            Mnemonic::Plp => self.plp(),
+            Mnemonic::Rol => self.rol(op),
+            Mnemonic::Ror => self.ror(op),
+            Mnemonic::Rti => self.rti(),
+            Mnemonic::Rts => self.rts(),
+            Mnemonic::Sbc => self.sbc(op),
+            Mnemonic::Sec => self.sec(),
+            Mnemonic::Sed => self.sed(),
+            Mnemonic::Sei => self.sei(),
+            Mnemonic::Sta => self.sta(op),
+            Mnemonic::Stx => self.stx(op),
+            Mnemonic::Sty => self.sty(op),
+            Mnemonic::Tax => self.tax(),
+            Mnemonic::Tay => self.tay(),
+            Mnemonic::Tsx => self.tsx(),
+            Mnemonic::Txa => self.txa(),
+            Mnemonic::Txs => self.txs(),
+            Mnemonic::Tya => self.tya(),
+            // 65C02
+            Mnemonic::Bra
+            | Mnemonic::Phx
+            | Mnemonic::Phy
+            | Mnemonic::Plx
+            | Mnemonic::Ply
+            | Mnemonic::Stp
+            | Mnemonic::Stz
+            | Mnemonic::Trb
+            | Mnemonic::Tsb
+            | Mnemonic::Wai
+            | Mnemonic::Bbr(_)
+            | Mnemonic::Bbs(_)
+            | Mnemonic::Rmb(_)
+            | Mnemonic::Smb(_) => {}
         }
     }
 
@@ -457,41 +694,38 @@ impl Chip {
     // ======================
 
     // load accumulator
-    fn lda(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        self.acc = self.read_byte(address);
+    fn lda(&mut self, op: Operand) {
+        self.acc = self.read_operand(op);
         self.set_zero_neg_flags(self.acc);
     }
 
     // load X
-    fn ldx(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        self.rx = self.read_byte(address);
+    fn ldx(&mut self, op: Operand) {
+        self.rx = self.read_operand(op);
         self.set_zero_neg_flags(self.rx);
     }
 
     // load Y
-    fn ldy(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        self.ry = self.read_byte(address);
+    fn ldy(&mut self, op: Operand) {
+        self.ry = self.read_operand(op);
         self.set_zero_neg_flags(self.ry);
     }
 
     // store accumulator
-    fn sta(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
+    fn sta(&mut self, op: Operand) {
+        let address = self.get_address(op);
         self.write_byte(self.acc, address);
     }
 
     // store X
-    fn stx(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
+    fn stx(&mut self, op: Operand) {
+        let address = self.get_address(op);
         self.write_byte(self.rx, address);
     }
 
     // store Y
-    fn sty(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
+    fn sty(&mut self, op: Operand) {
+        let address = self.get_address(op);
         self.write_byte(self.ry, address);
     }
 
@@ -561,8 +795,8 @@ impl Chip {
     // ======================
 
     // decrement
-    fn dec(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
+    fn dec(&mut self, op: Operand) {
+        let address = self.get_address(op);
         let byte = self.read_byte(address);
         let (res, _) = byte.overflowing_sub(1);
         self.write_byte(res, address);
@@ -582,8 +816,8 @@ impl Chip {
     }
 
     // increment
-    fn inc(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
+    fn inc(&mut self, op: Operand) {
+        let address = self.get_address(op);
         let byte = self.read_byte(address);
         let (res, _) = byte.overflowing_add(1);
         self.write_byte(res, address);
@@ -607,9 +841,8 @@ impl Chip {
     // ======================
 
     // add with carry
-    fn adc(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        let byte = self.read_byte(address);
+    fn adc(&mut self, op: Operand) {
+        let byte = self.read_operand(op);
         let carry = if self.f & C == C { 1 } else { 0 };
         let m_7 = if self.acc & 0x80 == 0x80 { 1 } else { 0 };
         let n_7 = if byte & 0x80 == 0x80 { 1 } else { 0 };
@@ -623,7 +856,7 @@ impl Chip {
         } else {
             self.clear_flag(V);
         }
-        if of == true {
+        if of {
             self.set_flag(C)
         } else {
             self.clear_flag(C)
@@ -632,9 +865,8 @@ impl Chip {
     }
 
     // subtract with carry
-    fn sbc(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        let byte = self.read_byte(address);
+    fn sbc(&mut self, op: Operand) {
+        let byte = self.read_operand(op);
         let carry = if self.f & C == C { 1 } else { 0 };
         let m_7 = if self.acc & 0x80 == 0x80 { 1 } else { 0 };
         let n_7 = if byte & 0x80 == 0x80 { 1 } else { 0 };
@@ -648,7 +880,7 @@ impl Chip {
         } else {
             self.clear_flag(V);
         }
-        if of == true {
+        if of {
             self.set_flag(C)
         } else {
             self.clear_flag(C)
@@ -661,25 +893,22 @@ impl Chip {
     // ======================
 
     // and (with accumulator)
-    fn and(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        let and = self.read_byte(address);
+    fn and(&mut self, op: Operand) {
+        let and = self.read_operand(op);
         self.acc &= and;
         self.set_zero_neg_flags(self.acc);
     }
 
     // exclusive or (with accumulator)
-    fn eor(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        let eor = self.read_byte(address);
+    fn eor(&mut self, op: Operand) {
+        let eor = self.read_operand(op);
         self.acc ^= eor;
         self.set_zero_neg_flags(self.acc);
     }
 
     // or with accumulator
-    fn ora(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        let or = self.read_byte(address);
+    fn ora(&mut self, op: Operand) {
+        let or = self.read_operand(op);
         self.acc |= or;
         self.set_zero_neg_flags(self.acc);
     }
@@ -689,11 +918,11 @@ impl Chip {
     // ======================
 
     // arithmetic shift left
-    fn asl(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
+    fn asl(&mut self, op: Operand) {
+        let address = self.get_address(op);
         let byte = self.read_byte(address);
-        match addr {
-            AddressMode::Accumulator => {
+        match op {
+            Operand::Accumulator => {
                 if self.acc >> 7 == 1 {
                     self.set_flag(C);
                 } else {
@@ -716,11 +945,11 @@ impl Chip {
     }
 
     // logical shift right
-    fn lsr(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
+    fn lsr(&mut self, op: Operand) {
+        let address = self.get_address(op);
         let byte = self.read_byte(address);
-        match addr {
-            AddressMode::Accumulator => {
+        match op {
+            Operand::Accumulator => {
                 if self.acc & 0x01 == 1 {
                     self.set_flag(C);
                 } else {
@@ -743,11 +972,11 @@ impl Chip {
     }
 
     // rotate left
-    fn rol(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
+    fn rol(&mut self, op: Operand) {
+        let address = self.get_address(op);
         let byte = self.read_byte(address);
-        match addr {
-            AddressMode::Accumulator => {
+        match op {
+            Operand::Accumulator => {
                 let oc = if self.f & C == C {
                     0b00000001
                 } else {
@@ -780,11 +1009,11 @@ impl Chip {
     }
 
     // rotate right
-    fn ror(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
+    fn ror(&mut self, op: Operand) {
+        let address = self.get_address(op);
         let byte = self.read_byte(address);
-        match addr {
-            AddressMode::Accumulator => {
+        match op {
+            Operand::Accumulator => {
                 let oc = if self.f & C == C {
                     0b10000000
                 } else {
@@ -861,9 +1090,8 @@ impl Chip {
 
     // compare (with accumulator)
     // SOLUTION: ... clear the damn values ...
-    fn cmp(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        let byte = self.read_byte(address);
+    fn cmp(&mut self, op: Operand) {
+        let byte = self.read_operand(op);
         let (res, _) = self.acc.overflowing_sub(byte);
         if self.acc >= byte {
             self.set_flag(C);
@@ -883,9 +1111,8 @@ impl Chip {
     }
 
     // compare with X
-    fn cpx(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        let byte = self.read_byte(address);
+    fn cpx(&mut self, op: Operand) {
+        let byte = self.read_operand(op);
         let (res, _) = self.rx.overflowing_sub(byte);
         if self.rx >= byte {
             self.set_flag(C);
@@ -905,9 +1132,8 @@ impl Chip {
     }
 
     // compare with Y
-    fn cpy(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        let byte = self.read_byte(address);
+    fn cpy(&mut self, op: Operand) {
+        let byte = self.read_operand(op);
         let (res, _) = self.ry.overflowing_sub(byte);
         if self.ry >= byte {
             self.set_flag(C);
@@ -931,64 +1157,56 @@ impl Chip {
     // ======================
 
     // branch on carry clear
-    fn bcc(&mut self) {
-        let offset = self.fetch_byte();
+    fn bcc(&mut self, offset: u8) {
         if self.f & C != C {
             self.branch(offset);
         }
     }
 
     // branch on carry set
-    fn bcs(&mut self) {
-        let offset = self.fetch_byte();
+    fn bcs(&mut self, offset: u8) {
         if self.f & C == C {
             self.branch(offset);
         }
     }
 
     // branch on equal (zero set)
-    fn beq(&mut self) {
-        let offset = self.fetch_byte();
+    fn beq(&mut self, offset: u8) {
         if self.f & Z == Z {
             self.branch(offset);
         }
     }
 
     // branch on minus (negative set)
-    fn bmi(&mut self) {
-        let offset = self.fetch_byte();
+    fn bmi(&mut self, offset: u8) {
         if self.f & N == N {
             self.branch(offset);
         }
     }
 
     // branch on not equal (zero clear)
-    fn bne(&mut self) {
-        let offset = self.fetch_byte();
+    fn bne(&mut self, offset: u8) {
         if self.f & Z != Z {
             self.branch(offset);
         }
     }
 
     // branch on plus (negative clear)
-    fn bpl(&mut self) {
-        let offset = self.fetch_byte();
+    fn bpl(&mut self, offset: u8) {
         if self.f & N != N {
             self.branch(offset);
         }
     }
 
     // branch on overflow clear
-    fn bvc(&mut self) {
-        let offset = self.fetch_byte();
+    fn bvc(&mut self, offset: u8) {
         if self.f & V != V {
             self.branch(offset);
         }
     }
 
     // branch on overflow set
-    fn bvs(&mut self) {
-        let offset = self.fetch_byte();
+    fn bvs(&mut self, offset: u8) {
         if self.f & V == V {
             self.branch(offset);
         }
@@ -999,13 +1217,13 @@ impl Chip {
     // ======================
 
     // jump
-    fn jmp(&mut self, addr: AddressMode) {
-        self.pc = self.get_address(addr);
+    fn jmp(&mut self, op: Operand) {
+        self.pc = self.get_address(op);
     }
 
     // jump subroutine
-    fn jsr(&mut self) {
-        let subaddr = self.fetch_word();
+    fn jsr(&mut self, op: Operand) {
+        let subaddr = self.get_address(op);
         // The write_word function is more or less the same as the
         // Used push_stack functions...
         // I just prefer the push stack a little bit more
@@ -1061,9 +1279,8 @@ impl Chip {
     // ======================
 
     // bit test
-    fn bit(&mut self, addr: AddressMode) {
-        let address = self.get_address(addr);
-        let byte = self.read_byte(address);
+    fn bit(&mut self, op: Operand) {
+        let byte = self.read_operand(op);
         if (self.acc & byte) == 0x0 {
             self.set_flag(Z);
         } else {
@@ -0,0 +1,804 @@
+// A debug adapter for editors that speak the Debug Adapter Protocol
+//
+// The adapter talks over stdin and stdout, every message is JSON
+// after a header with its length:
+//
+// Content-Length: 61\r\n
+// \r\n
+// {"seq":1,"type":"request","command":"threads","arguments":{}}
+//
+// The arguments of `launch`:
+//
+// program      the image, loaded like with the `load` command of the monitor
+// address      where a raw binary goes ($0200 if it is not given)
+// pc           where the execution starts (otherwise what the image says)
+// listing      an as65 listing, for the source lines and the labels
+// source       the source file of the listing (otherwise the one in its header)
+// debugInfo    an ld65 debug info file, for the source lines and the labels
+// labels       a label file (VICE or `label = $1234`)
+// stopOnEntry  stop before the first instruction
+//
+// The requests: initialize, launch, setBreakpoints, setFunctionBreakpoints
+// (labels), configurationDone, threads, stackTrace, scopes, variables,
+// continue, next, stepIn, stepOut, pause, evaluate, readMemory and
+// disconnect. Stepping goes from source line to source line (or by
+// instruction with the `instruction` granularity, or without source).
+//
+// There is no real call stack, so the stack trace comes from the JSRs:
+// every JSR (and BRK) adds a frame, every RTS (and RTI) removes the last.
+//
+// A trap (`jmp *` or a branch to itself) stops the chip like an
+// exception.
+//
+// Reference: https://microsoft.github.io/debug-adapter-protocol/specification
+
+pub mod json;
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, TryRecvError};
+
+use crate::debug_info::DebugInfo;
+use crate::listing::Listing;
+use crate::monitor::Monitor;
+use crate::symbols::parse_value;
+pub use json::Json;
+
+/// How many instructions run before the adapter looks for new requests
+const SLICE: usize = 10_000;
+/// How many frames are kept (older ones are dropped)
+const MAX_FRAMES: usize = 256;
+
+// The references of the scopes
+const REGISTERS: i64 = 1;
+const FLAGS: i64 = 2;
+const ZEROPAGE: i64 = 3;
+const STACK: i64 = 4;
+
+/// A JSR (or BRK) that has not returned yet
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Call {
+    // The address of the JSR:
+    site: u16,
+    target: u16,
+}
+
+/// How the chip runs until it stops again
+#[derive(Debug, PartialEq, Clone)]
+enum Run {
+    Continue,
+    // Until the source line changes (with `None`: one instruction)
+    StepIn(Option<(String, u32)>),
+    // Like `StepIn`, but subroutines run until they return
+    Next(Option<(String, u32)>, usize),
+    // Until the subroutine returns
+    StepOut(usize),
+}
+
+pub struct Server {
+    pub monitor: Monitor,
+    listing: Option<Listing>,
+    listing_source: Option<String>,
+    debug_info: Option<DebugInfo>,
+    debug_info_dir: PathBuf,
+    // The breakpoints of every source (and of the functions under "")
+    breakpoints: BTreeMap<String, Vec<u16>>,
+    // The ids and lines of the breakpoints of every source, they are
+    // resolved again when the program is launched
+    lines: BTreeMap<String, Vec<(i64, i64)>>,
+    next_breakpoint: i64,
+    calls: Vec<Call>,
+    run: Option<Run>,
+    stop_on_entry: bool,
+    launched: bool,
+    configured: bool,
+    finished: bool,
+    seq: i64,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Server::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server {
+            monitor: Monitor::new(),
+            listing: None,
+            listing_source: None,
+            debug_info: None,
+            debug_info_dir: PathBuf::new(),
+            breakpoints: BTreeMap::new(),
+            lines: BTreeMap::new(),
+            next_breakpoint: 1,
+            calls: Vec::new(),
+            run: None,
+            stop_on_entry: false,
+            launched: false,
+            configured: false,
+            finished: false,
+            seq: 0,
+        }
+    }
+
+    /// If the chip is running
+    pub fn running(&self) -> bool {
+        self.run.is_some()
+    }
+
+    /// If the client disconnected
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Handles a request and returns the messages for the client
+    pub fn handle(&mut self, request: &Json) -> Vec<Json> {
+        let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+        let empty = Json::Object(Vec::new());
+        let arguments = request.get("arguments").unwrap_or(&empty);
+        let mut messages = Vec::new();
+        let result = match command {
+            "initialize" => Ok(Json::object(vec![
+                ("supportsConfigurationDoneRequest", true.into()),
+                ("supportsFunctionBreakpoints", true.into()),
+                ("supportsEvaluateForHovers", true.into()),
+                ("supportsReadMemoryRequest", true.into()),
+                ("supportsSteppingGranularity", true.into()),
+            ])),
+            "launch" => self.launch(arguments),
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "setFunctionBreakpoints" => self.set_function_breakpoints(arguments),
+            "setExceptionBreakpoints" => Ok(Json::Null),
+            "configurationDone" => {
+                self.configured = true;
+                Ok(Json::Null)
+            }
+            "threads" => Ok(Json::object(vec![(
+                "threads",
+                vec![Json::object(vec![
+                    ("id", 1i64.into()),
+                    ("name", "6502".into()),
+                ])]
+                .into(),
+            )])),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(self.scopes()),
+            "variables" => self.variables(arguments),
+            "continue" => {
+                self.run = Some(Run::Continue);
+                Ok(Json::object(vec![("allThreadsContinued", true.into())]))
+            }
+            "next" | "stepIn" | "stepOut" => {
+                let by_instruction =
+                    arguments.get("granularity").and_then(Json::as_str) == Some("instruction");
+                let start = match by_instruction {
+                    true => None,
+                    false => self.location(self.monitor.chip.pc),
+                };
+                self.run = Some(match command {
+                    "next" => Run::Next(start, self.calls.len()),
+                    "stepIn" => Run::StepIn(start),
+                    _ => Run::StepOut(self.calls.len()),
+                });
+                Ok(Json::Null)
+            }
+            "pause" => {
+                if self.run.take().is_some() {
+                    messages.push(self.stopped("pause", None));
+                }
+                Ok(Json::Null)
+            }
+            "evaluate" => self.evaluate(arguments),
+            "readMemory" => self.read_memory(arguments),
+            "disconnect" | "terminate" => {
+                self.run = None;
+                self.finished = true;
+                Ok(Json::Null)
+            }
+            _ => Err(format!("`{}` is not supported", command)),
+        };
+        let launched = command == "launch" && result.is_ok();
+        messages.insert(0, self.response(request, result));
+        // breakpoints set before the launch had no source lines yet
+        if launched {
+            let paths: Vec<String> = self.lines.keys().cloned().collect();
+            for path in paths {
+                for breakpoint in self.resolve(&path) {
+                    let body = Json::object(vec![
+                        ("reason", "changed".into()),
+                        ("breakpoint", breakpoint),
+                    ]);
+                    messages.push(self.event("breakpoint", body));
+                }
+            }
+        }
+        if command == "initialize" {
+            messages.push(self.event("initialized", Json::Null));
+        }
+        // the program starts once it is launched and configured
+        if (command == "launch" || command == "configurationDone")
+            && self.launched
+            && self.configured
+        {
+            self.configured = false;
+            if self.stop_on_entry {
+                messages.push(self.stopped("entry", None));
+            } else {
+                self.run = Some(Run::Continue);
+            }
+        }
+        messages
+    }
+
+    /// Runs up to `count` instructions, the messages are a
+    /// `stopped` event if the chip stopped
+    pub fn run(&mut self, count: usize) -> Vec<Json> {
+        for _ in 0..count {
+            let run = match &self.run {
+                Some(run) => run.clone(),
+                None => break,
+            };
+            let pc = self.monitor.chip.pc;
+            self.track_calls();
+            self.monitor.chip.execute_cycle();
+            let new_pc = self.monitor.chip.pc;
+
+            let reason = if new_pc == pc {
+                Some(("exception", Some(format!("trapped at ${:04X}", pc))))
+            } else if self.breakpoints.values().any(|b| b.contains(&new_pc)) {
+                Some(("breakpoint", None))
+            } else {
+                let stepped = match run {
+                    Run::Continue => false,
+                    Run::StepIn(start) => self.left_line(&start),
+                    Run::Next(start, depth) => self.calls.len() <= depth && self.left_line(&start),
+                    Run::StepOut(depth) => self.calls.len() < depth,
+                };
+                if stepped {
+                    Some(("step", None))
+                } else {
+                    None
+                }
+            };
+            if let Some((reason, text)) = reason {
+                self.run = None;
+                return vec![self.stopped(reason, text)];
+            }
+        }
+        Vec::new()
+    }
+
+    // =====================
+    // Requests
+    // =====================
+
+    fn launch(&mut self, arguments: &Json) -> Result<Json, String> {
+        let text = |name: &str| arguments.get(name).and_then(Json::as_str);
+        if let Some(file) = text("listing") {
+            let listing =
+                Listing::load(file.to_string()).map_err(|e| format!("{}: {}", file, e))?;
+            self.monitor.symbols.merge(listing.labels());
+            // the source file is next to the listing
+            self.listing_source = text("source")
+                .map(|s| s.to_string())
+                .or_else(|| listing.source.as_ref().map(|s| beside(file, s)));
+            self.listing = Some(listing);
+        }
+        if let Some(file) = text("debugInfo") {
+            let info = DebugInfo::load(file.to_string()).map_err(|e| format!("{}: {}", file, e))?;
+            self.monitor.symbols.merge(&info.symbol_table());
+            self.debug_info_dir = Path::new(file)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .to_path_buf();
+            self.debug_info = Some(info);
+        }
+        if let Some(file) = text("labels") {
+            self.monitor.load_labels(file)?;
+        }
+        let program = text("program").ok_or("`program` is missing")?;
+        let address = match text("address") {
+            Some(address) => Some(self.number(address)?),
+            None => None,
+        };
+        self.monitor.load(program, address)?;
+        if let Some(pc) = text("pc") {
+            self.monitor.chip.pc = self.number(pc)?;
+        }
+        self.stop_on_entry = arguments
+            .get("stopOnEntry")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        self.calls.clear();
+        self.launched = true;
+        Ok(Json::Null)
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Json) -> Result<Json, String> {
+        let path = arguments
+            .get("source")
+            .and_then(|s| s.get("path"))
+            .and_then(Json::as_str)
+            .ok_or("the source has no path")?
+            .to_string();
+        let lines: Vec<(i64, i64)> = arguments
+            .get("breakpoints")
+            .and_then(Json::as_array)
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|b| b.get("line").and_then(Json::as_i64))
+            .map(|line| {
+                self.next_breakpoint += 1;
+                (self.next_breakpoint - 1, line)
+            })
+            .collect();
+        self.lines.insert(path.clone(), lines);
+        let breakpoints = self.resolve(&path);
+        Ok(Json::object(vec![("breakpoints", breakpoints.into())]))
+    }
+
+    /// Finds the addresses of the breakpoints of a source
+    fn resolve(&mut self, path: &str) -> Vec<Json> {
+        let lines = self.lines.get(path).cloned().unwrap_or_default();
+        let mut addresses = Vec::new();
+        let mut breakpoints = Vec::new();
+        for (id, line) in lines {
+            let address = self.addresses_of(path, line as u32).first().copied();
+            let mut breakpoint = vec![
+                ("id", id.into()),
+                ("verified", address.is_some().into()),
+                ("line", line.into()),
+            ];
+            match address {
+                Some(address) => {
+                    addresses.push(address);
+                    breakpoint.push(("instructionReference", format!("0x{:04X}", address).into()));
+                }
+                None => breakpoint.push(("message", "there is no code on this line".into())),
+            }
+            breakpoints.push(Json::object(breakpoint));
+        }
+        self.breakpoints.insert(path.to_string(), addresses);
+        breakpoints
+    }
+
+    fn set_function_breakpoints(&mut self, arguments: &Json) -> Result<Json, String> {
+        let mut addresses = Vec::new();
+        let mut breakpoints = Vec::new();
+        for breakpoint in arguments
+            .get("breakpoints")
+            .and_then(Json::as_array)
+            .unwrap_or(&[])
+        {
+            let name = breakpoint.get("name").and_then(Json::as_str).unwrap_or("");
+            let address = self.value(name);
+            if let Some(address) = address {
+                addresses.push(address);
+            }
+            breakpoints.push(Json::object(vec![("verified", address.is_some().into())]));
+        }
+        self.breakpoints.insert(String::new(), addresses);
+        Ok(Json::object(vec![("breakpoints", breakpoints.into())]))
+    }
+
+    fn stack_trace(&self) -> Json {
+        let mut addresses = vec![self.monitor.chip.pc];
+        addresses.extend(self.calls.iter().rev().map(|call| call.site));
+        let frames: Vec<Json> = addresses
+            .iter()
+            .enumerate()
+            .map(|(i, address)| {
+                // the subroutine the frame is in is the target of the call before it
+                let name = match self.calls.len().checked_sub(i + 1) {
+                    Some(call) => self.monitor.symbols.format_address(self.calls[call].target),
+                    None => match self.monitor.symbols.nearest(*address) {
+                        Some((name, _)) => name.to_string(),
+                        None => format!("${:04X}", address),
+                    },
+                };
+                let mut frame = vec![
+                    ("id", i.into()),
+                    ("name", name.into()),
+                    ("line", 0i64.into()),
+                    ("column", 1i64.into()),
+                    (
+                        "instructionPointerReference",
+                        format!("0x{:04X}", address).into(),
+                    ),
+                ];
+                if let Some((path, line)) = self.location(*address) {
+                    frame[2] = ("line", (line as i64).into());
+                    frame.push(("source", source(&path)));
+                }
+                Json::object(frame)
+            })
+            .collect();
+        Json::object(vec![
+            ("totalFrames", frames.len().into()),
+            ("stackFrames", frames.into()),
+        ])
+    }
+
+    fn scopes(&self) -> Json {
+        let scope = |name: &str, reference: i64| {
+            Json::object(vec![
+                ("name", name.into()),
+                ("variablesReference", reference.into()),
+                ("expensive", false.into()),
+            ])
+        };
+        Json::object(vec![(
+            "scopes",
+            vec![
+                scope("Registers", REGISTERS),
+                scope("Flags", FLAGS),
+                scope("Zero page", ZEROPAGE),
+                scope("Stack", STACK),
+            ]
+            .into(),
+        )])
+    }
+
+    fn variables(&self, arguments: &Json) -> Result<Json, String> {
+        let c = &self.monitor.chip;
+        let variable = |name: String, value: String| {
+            Json::object(vec![
+                ("name", name.into()),
+                ("value", value.into()),
+                ("variablesReference", 0i64.into()),
+            ])
+        };
+        let reference = arguments.get("variablesReference").and_then(Json::as_i64);
+        let variables: Vec<Json> = match reference {
+            Some(REGISTERS) => vec![
+                variable("A".to_string(), format!("${:02X}", c.acc)),
+                variable("X".to_string(), format!("${:02X}", c.rx)),
+                variable("Y".to_string(), format!("${:02X}", c.ry)),
+                variable("SP".to_string(), format!("${:02X}", c.sp)),
+                variable("PC".to_string(), format!("${:04X}", c.pc)),
+                variable("P".to_string(), format!("${:02X} ({:08b})", c.f, c.f)),
+            ],
+            Some(FLAGS) => "NV-BDIZC"
+                .chars()
+                .enumerate()
+                .filter(|(_, name)| *name != '-')
+                .map(|(i, name)| {
+                    let set = c.f & (0x80 >> i) != 0;
+                    variable(name.to_string(), (set as u8).to_string())
+                })
+                .collect(),
+            Some(ZEROPAGE) => (0..16)
+                .map(|row| {
+                    let bytes: Vec<String> = c.memory[row * 16..row * 16 + 16]
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect();
+                    variable(format!("${:02X}", row * 16), bytes.join(" "))
+                })
+                .collect(),
+            Some(STACK) => (c.sp as usize + 1..=0xFF)
+                .rev()
+                .map(|offset| {
+                    let address = 0x0100 + offset;
+                    variable(
+                        format!("${:04X}", address),
+                        format!("${:02X}", c.memory[address]),
+                    )
+                })
+                .collect(),
+            _ => return Err("unknown variables reference".to_string()),
+        };
+        Ok(Json::object(vec![("variables", variables.into())]))
+    }
+
+    /// A register, a label or a number
+    fn evaluate(&self, arguments: &Json) -> Result<Json, String> {
+        let expression = arguments
+            .get("expression")
+            .and_then(Json::as_str)
+            .unwrap_or("")
+            .trim();
+        let c = &self.monitor.chip;
+        let byte = match expression.to_ascii_lowercase().as_str() {
+            "a" => Some(c.acc),
+            "x" => Some(c.rx),
+            "y" => Some(c.ry),
+            "sp" => Some(c.sp),
+            "p" => Some(c.f),
+            _ => None,
+        };
+        let result = match (byte, self.value(expression)) {
+            (Some(byte), _) => vec![("result", format!("${:02X}", byte).into())],
+            (None, Some(address)) => vec![
+                (
+                    "result",
+                    format!("${:04X} (${:02X})", address, c.memory[address as usize]).into(),
+                ),
+                ("memoryReference", format!("0x{:04X}", address).into()),
+            ],
+            _ => {
+                return Err(format!(
+                    "`{}` is not a register, label or number",
+                    expression
+                ))
+            }
+        };
+        let mut body = result;
+        body.push(("variablesReference", 0i64.into()));
+        Ok(Json::object(body))
+    }
+
+    fn read_memory(&self, arguments: &Json) -> Result<Json, String> {
+        let reference = arguments
+            .get("memoryReference")
+            .and_then(Json::as_str)
+            .unwrap_or("");
+        let start =
+            self.value(reference)
+                .ok_or_else(|| format!("`{}` is not an address", reference))? as i64;
+        let start = start + arguments.get("offset").and_then(Json::as_i64).unwrap_or(0);
+        let count = arguments.get("count").and_then(Json::as_i64).unwrap_or(0);
+        let memory = &self.monitor.chip.memory;
+        let first = start.clamp(0, memory.len() as i64) as usize;
+        let end = (start + count).clamp(0, memory.len() as i64) as usize;
+        let end = end.max(first);
+        Ok(Json::object(vec![
+            ("address", format!("0x{:04X}", first).into()),
+            ("data", base64(&memory[first..end]).into()),
+            (
+                "unreadableBytes",
+                (count - (end - first) as i64).max(0).into(),
+            ),
+        ]))
+    }
+
+    // =====================
+    // Helper functions
+    // =====================
+
+    fn response(&mut self, request: &Json, result: Result<Json, String>) -> Json {
+        self.seq += 1;
+        let mut fields = vec![
+            ("seq", self.seq.into()),
+            ("type", "response".into()),
+            (
+                "request_seq",
+                request.get("seq").cloned().unwrap_or(Json::Null),
+            ),
+            ("success", result.is_ok().into()),
+            (
+                "command",
+                request.get("command").cloned().unwrap_or(Json::Null),
+            ),
+        ];
+        match result {
+            Ok(Json::Null) => {}
+            Ok(body) => fields.push(("body", body)),
+            Err(message) => fields.push(("message", message.into())),
+        }
+        Json::object(fields)
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> Json {
+        self.seq += 1;
+        let mut fields = vec![
+            ("seq", self.seq.into()),
+            ("type", "event".into()),
+            ("event", event.into()),
+        ];
+        if body != Json::Null {
+            fields.push(("body", body));
+        }
+        Json::object(fields)
+    }
+
+    fn stopped(&mut self, reason: &str, text: Option<String>) -> Json {
+        let mut body = vec![
+            ("reason", reason.into()),
+            ("threadId", 1i64.into()),
+            ("allThreadsStopped", true.into()),
+        ];
+        if let Some(text) = text {
+            body.push(("text", text.clone().into()));
+            body.push(("description", text.into()));
+        }
+        self.event("stopped", Json::object(body))
+    }
+
+    /// Keeps the calls up to date before the instruction at PC runs
+    fn track_calls(&mut self) {
+        let c = &self.monitor.chip;
+        let pc = c.pc as usize;
+        let word = |address: usize| {
+            u16::from_le_bytes([c.memory[address], c.memory[(address + 1) & 0xFFFF]])
+        };
+        match c.memory[pc] {
+            // JSR and BRK
+            0x20 => self.calls.push(Call {
+                site: c.pc,
+                target: word((pc + 1) & 0xFFFF),
+            }),
+            0x00 => self.calls.push(Call {
+                site: c.pc,
+                target: word(0xFFFE),
+            }),
+            // RTS and RTI
+            0x60 | 0x40 => {
+                self.calls.pop();
+            }
+            _ => {}
+        }
+        if self.calls.len() > MAX_FRAMES {
+            self.calls.remove(0);
+        }
+    }
+
+    /// If PC is on another source line than `start` (always without a start)
+    fn left_line(&self, start: &Option<(String, u32)>) -> bool {
+        match start {
+            None => true,
+            Some(start) => match self.location(self.monitor.chip.pc) {
+                Some(location) => location != *start,
+                None => false,
+            },
+        }
+    }
+
+    /// The source file and line of the code at the address
+    fn location(&self, address: u16) -> Option<(String, u32)> {
+        if let Some(info) = &self.debug_info {
+            if let Some((file, line)) = info.line_at(address) {
+                let path = self.debug_info_dir.join(file);
+                return Some((path.to_string_lossy().to_string(), line));
+            }
+        }
+        let listing = self.listing.as_ref()?;
+        let line = listing.source_line_at(address)?.source_line?;
+        Some((self.listing_source.clone()?, line as u32))
+    }
+
+    /// The addresses of the code of a source line
+    fn addresses_of(&self, path: &str, line: u32) -> Vec<u16> {
+        if let Some(info) = &self.debug_info {
+            let addresses = info.addresses_of(path, line);
+            if !addresses.is_empty() {
+                return addresses;
+            }
+        }
+        match (&self.listing, &self.listing_source) {
+            (Some(listing), Some(source)) if file_name(source) == file_name(path) => {
+                listing.addresses_of(line as usize)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// A label or a number
+    fn value(&self, text: &str) -> Option<u16> {
+        self.monitor.symbols.get(text).or_else(|| parse_value(text))
+    }
+
+    /// Like `value`, but a missing label is an error
+    fn number(&self, text: &str) -> Result<u16, String> {
+        self.value(text)
+            .ok_or_else(|| format!("`{}` is neither a number nor a label", text))
+    }
+}
+
+/// Reads requests from the input and answers them on the output
+/// until the client disconnects or the input ends
+pub fn serve<R, W>(server: &mut Server, input: R, mut output: W) -> io::Result<()>
+where
+    R: Read + Send + 'static,
+    W: Write,
+{
+    // the requests are read on their own, so a running chip can be paused
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(input);
+        while let Ok(Some(message)) = read_message(&mut reader) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    while !server.finished() {
+        let messages = if server.running() {
+            match receiver.try_recv() {
+                Ok(request) => server.handle(&request),
+                Err(TryRecvError::Empty) => server.run(SLICE),
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match receiver.recv() {
+                Ok(request) => server.handle(&request),
+                Err(_) => break,
+            }
+        };
+        for message in messages {
+            write_message(&mut output, &message)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a message with its header, `None` at the end of the input
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length.unwrap_or(0)];
+    reader.read_exact(&mut body)?;
+    Json::parse(&String::from_utf8_lossy(&body))
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writes a message with its header
+pub fn write_message(writer: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// A source of a frame
+fn source(path: &str) -> Json {
+    Json::object(vec![
+        ("name", file_name(path).into()),
+        ("path", path.into()),
+    ])
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+/// The path of a file in the same directory as another file
+fn beside(file: &str, name: &str) -> String {
+    match Path::new(file).parent() {
+        Some(dir) => dir.join(name).to_string_lossy().to_string(),
+        None => name.to_string(),
+    }
+}
+
+/// Base64 with padding (what `readMemory` answers)
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
@@ -0,0 +1,354 @@
+// ld65 debug info files (`ld65 --dbgfile`)
+//
+// Every line is a record: a keyword, a tab and a list of
+// `key=value` attributes separated by commas.
+//
+// file  ... source files
+// seg   ... segments with their start address
+// span  ... a range of bytes in a segment
+// line  ... a line of a source file and the spans it produced
+// sym   ... symbols with their value
+//
+// Everything else (modules, scopes, C symbols, ...) is skipped.
+//
+// Reference: https://cc65.github.io/doc/debugging.html
+
+use std::collections::HashMap;
+
+use crate::loader::LoadError;
+use crate::symbols::SymbolTable;
+
+/// A source file
+#[derive(Debug, PartialEq, Clone)]
+pub struct SourceFile {
+    pub id: usize,
+    pub name: String,
+}
+
+/// A segment of the linked program
+#[derive(Debug, PartialEq, Clone)]
+pub struct DebugSegment {
+    pub id: usize,
+    pub name: String,
+    pub start: u16,
+    pub size: u32,
+}
+
+/// A symbol with its value
+#[derive(Debug, PartialEq, Clone)]
+pub struct DebugSymbol {
+    pub name: String,
+    pub value: u16,
+    // A label (and not an equate):
+    pub label: bool,
+}
+
+/// The addresses a line of source produced
+#[derive(Debug, PartialEq, Clone)]
+pub struct LineInfo {
+    // Index into `DebugInfo::files`:
+    pub file: usize,
+    pub line: u32,
+    // The line is part of a macro expansion:
+    pub macro_line: bool,
+    pub start: u16,
+    pub size: u32,
+}
+
+/// Everything read from a debug info file
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct DebugInfo {
+    pub files: Vec<SourceFile>,
+    pub segments: Vec<DebugSegment>,
+    pub symbols: Vec<DebugSymbol>,
+    pub lines: Vec<LineInfo>,
+}
+
+/// The attributes of a record
+struct Record<'a> {
+    line: usize,
+    attributes: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Record<'a> {
+    fn parse(text: &'a str, line: usize) -> Result<Record<'a>, LoadError> {
+        let mut attributes = HashMap::new();
+        let mut in_string = false;
+        let mut start = 0;
+        let mut parts = Vec::new();
+        for (i, ch) in text.char_indices() {
+            match ch {
+                '"' => in_string = !in_string,
+                ',' if !in_string => {
+                    parts.push(&text[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        parts.push(&text[start..]);
+        for part in parts {
+            let part = part.trim();
+            if part.is_empty() {
+                continue;
+            }
+            match part.split_once('=') {
+                Some((key, value)) => {
+                    attributes.insert(key, value);
+                }
+                None => {
+                    return Err(LoadError::Syntax {
+                        line,
+                        message: format!("attribute `{}` has no value", part),
+                    })
+                }
+            }
+        }
+        Ok(Record { line, attributes })
+    }
+
+    fn get(&self, key: &str) -> Option<&'a str> {
+        self.attributes.get(key).copied()
+    }
+
+    fn required(&self, key: &str) -> Result<&'a str, LoadError> {
+        self.get(key).ok_or_else(|| LoadError::Syntax {
+            line: self.line,
+            message: format!("missing attribute `{}`", key),
+        })
+    }
+
+    fn number(&self, key: &str) -> Result<u32, LoadError> {
+        let text = self.required(key)?;
+        parse_number(text).ok_or_else(|| LoadError::Syntax {
+            line: self.line,
+            message: format!("`{}` is not a number", text),
+        })
+    }
+
+    fn string(&self, key: &str) -> Result<String, LoadError> {
+        let text = self.required(key)?;
+        Ok(text.trim_matches('"').replace("\\\"", "\""))
+    }
+
+    /// A `+` separated list of ids
+    fn ids(&self, key: &str) -> Result<Vec<u32>, LoadError> {
+        match self.get(key) {
+            Some(text) => text
+                .split('+')
+                .map(|id| {
+                    parse_number(id).ok_or_else(|| LoadError::Syntax {
+                        line: self.line,
+                        message: format!("`{}` is not a number", id),
+                    })
+                })
+                .collect(),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+fn parse_number(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Parses the text of a debug info file
+pub fn parse(text: &str) -> Result<DebugInfo, LoadError> {
+    let mut info = DebugInfo::default();
+    // segment id -> start address
+    let mut segment_starts = HashMap::new();
+    // span id -> (segment id, start, size)
+    let mut spans = HashMap::new();
+    // (file id, line, type, span ids, line of the record)
+    let mut lines = Vec::new();
+    // file id -> index into info.files
+    let mut file_index = HashMap::new();
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+        let (keyword, rest) = raw.split_once(char::is_whitespace).unwrap_or((raw, ""));
+        let record = Record::parse(rest, line)?;
+        match keyword {
+            "version" => {
+                let major = record.number("major")?;
+                if major != 2 {
+                    return Err(LoadError::Syntax {
+                        line,
+                        message: format!("debug info version {} is not supported", major),
+                    });
+                }
+            }
+            "file" => {
+                let id = record.number("id")? as usize;
+                file_index.insert(id, info.files.len());
+                info.files.push(SourceFile {
+                    id,
+                    name: record.string("name")?,
+                });
+            }
+            "seg" => {
+                let id = record.number("id")? as usize;
+                let start = record.number("start")?;
+                segment_starts.insert(id, start);
+                info.segments.push(DebugSegment {
+                    id,
+                    name: record.string("name")?,
+                    start: start as u16,
+                    size: record.number("size")?,
+                });
+            }
+            "span" => {
+                spans.insert(
+                    record.number("id")?,
+                    (
+                        record.number("seg")? as usize,
+                        record.number("start")?,
+                        record.number("size")?,
+                    ),
+                );
+            }
+            "line" => {
+                let kind = match record.get("type") {
+                    Some(_) => record.number("type")?,
+                    None => 0,
+                };
+                lines.push((
+                    record.number("file")? as usize,
+                    record.number("line")?,
+                    kind,
+                    record.ids("span")?,
+                    line,
+                ));
+            }
+            "sym" => {
+                // imports have no value
+                if record.get("val").is_none() {
+                    continue;
+                }
+                info.symbols.push(DebugSymbol {
+                    name: record.string("name")?,
+                    value: record.number("val")? as u16,
+                    label: record.get("type") == Some("lab"),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    for (file, line_number, kind, span_ids, line) in lines {
+        let file = match file_index.get(&file) {
+            Some(file) => *file,
+            None => {
+                return Err(LoadError::Syntax {
+                    line,
+                    message: format!("unknown file id {}", file),
+                })
+            }
+        };
+        for span in span_ids {
+            let (segment, start, size) = match spans.get(&span) {
+                Some(span) => *span,
+                None => {
+                    return Err(LoadError::Syntax {
+                        line,
+                        message: format!("unknown span id {}", span),
+                    })
+                }
+            };
+            let segment_start = match segment_starts.get(&segment) {
+                Some(start) => *start,
+                None => {
+                    return Err(LoadError::Syntax {
+                        line,
+                        message: format!("unknown segment id {}", segment),
+                    })
+                }
+            };
+            info.lines.push(LineInfo {
+                file,
+                line: line_number,
+                // type 2 are lines of a macro
+                macro_line: kind == 2,
+                start: (segment_start + start) as u16,
+                size,
+            });
+        }
+    }
+
+    Ok(info)
+}
+
+impl DebugInfo {
+    /// Loads a debug info file
+    pub fn load(file_path: String) -> Result<DebugInfo, LoadError> {
+        parse(&std::fs::read_to_string(file_path)?)
+    }
+
+    /// All symbols as a symbol table (labels come first
+    /// so they are preferred over equates with the same value)
+    pub fn symbol_table(&self) -> SymbolTable {
+        let mut table = SymbolTable::new();
+        for symbol in self.symbols.iter().filter(|s| s.label) {
+            table.insert(&symbol.name, symbol.value);
+        }
+        for symbol in self.symbols.iter().filter(|s| !s.label) {
+            table.insert(&symbol.name, symbol.value);
+        }
+        table
+    }
+
+    /// The value of a symbol
+    pub fn symbol(&self, name: &str) -> Option<u16> {
+        self.symbols
+            .iter()
+            .find(|s| s.name == name)
+            .map(|s| s.value)
+    }
+
+    /// The name of the label at exactly this address
+    pub fn label_at(&self, address: u16) -> Option<&str> {
+        self.symbols
+            .iter()
+            .find(|s| s.label && s.value == address)
+            .map(|s| s.name.as_str())
+    }
+
+    /// The source file and line that produced the byte at the address.
+    ///
+    /// Lines of the source are preferred over lines inside of macros,
+    /// and of those the one with the smallest span.
+    pub fn line_at(&self, address: u16) -> Option<(&str, u32)> {
+        self.lines
+            .iter()
+            .filter(|l| (l.start as u32..l.start as u32 + l.size).contains(&(address as u32)))
+            .min_by_key(|l| (l.macro_line, l.size))
+            .map(|l| (self.files[l.file].name.as_str(), l.line))
+    }
+
+    /// The addresses of the code a source line produced
+    /// (the file name can be given without its directory).
+    pub fn addresses_of(&self, file: &str, line: u32) -> Vec<u16> {
+        let mut addresses: Vec<u16> = self
+            .lines
+            .iter()
+            .filter(|l| l.line == line && same_file(&self.files[l.file].name, file))
+            .map(|l| l.start)
+            .collect();
+        addresses.sort();
+        addresses.dedup();
+        addresses
+    }
+}
+
+/// Compares file names, ignoring their directories
+fn same_file(name: &str, wanted: &str) -> bool {
+    let base = |n: &str| n.rsplit(['/', '\\']).next().unwrap_or(n).to_string();
+    name == wanted || base(name) == base(wanted)
+}
@@ -0,0 +1,422 @@
+// A disassembler for the 6502 and the 65C02
+//
+// Every opcode is looked up in a table of its mnemonic and addressing
+// mode. The undocumented opcodes of the NMOS 6502 use the names of the
+// masswerk reference (SLO, LAX, DCP, JAM, ...), the unused opcodes of
+// the 65C02 are NOPs of different lengths.
+//
+// Operands are written like the assemblers do:
+//
+// #$10  $10  $10,X  $10,Y  $1234  $1234,X  $1234,Y
+// ($1234)  ($10,X)  ($10),Y  ($10)  ($1234,X)  $10,$1234
+//
+// Branches show their target and not the offset. With a symbol table
+// addresses are shown as labels where there is one.
+//
+// Reference: https://www.masswerk.at/6502/6502_instruction_set.html
+//            http://www.6502.org/tutorials/65c02opcodes.html
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+pub use crate::chip::Cpu;
+use crate::chip::MEMORY;
+use crate::instruction::Operand;
+use crate::symbols::SymbolTable;
+
+/// The addressing modes of both CPUs
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Mode {
+    Implied,
+    Accumulator,
+    Immediate,
+    Zeropage,
+    ZeropageX,
+    ZeropageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    XIndirect,
+    IndirectY,
+    Relative,
+    // 65C02 only:
+    ZeropageIndirect,
+    AbsoluteXIndirect,
+    ZeropageRelative,
+}
+
+impl Mode {
+    /// The length of an instruction with this mode (with the opcode)
+    pub fn length(&self) -> usize {
+        match self {
+            Mode::Implied | Mode::Accumulator => 1,
+            Mode::Immediate
+            | Mode::Zeropage
+            | Mode::ZeropageX
+            | Mode::ZeropageY
+            | Mode::XIndirect
+            | Mode::IndirectY
+            | Mode::Relative
+            | Mode::ZeropageIndirect => 2,
+            Mode::Absolute
+            | Mode::AbsoluteX
+            | Mode::AbsoluteY
+            | Mode::Indirect
+            | Mode::AbsoluteXIndirect
+            | Mode::ZeropageRelative => 3,
+        }
+    }
+}
+
+/// What an opcode is
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Opcode {
+    pub mnemonic: &'static str,
+    pub mode: Mode,
+    pub documented: bool,
+}
+
+/// Looks up an opcode
+pub fn opcode(cpu: Cpu, byte: u8) -> Opcode {
+    match cpu {
+        Cpu::Mos6502 => NMOS[byte as usize],
+        Cpu::Wdc65C02 => CMOS[byte as usize],
+    }
+}
+
+/// A disassembled instruction
+#[derive(Debug, PartialEq, Clone)]
+pub struct Disassembled {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub mnemonic: &'static str,
+    pub mode: Mode,
+    // The operand as text (empty for implied instructions):
+    pub operand: String,
+    pub documented: bool,
+}
+
+impl Disassembled {
+    /// The number of bytes of the instruction
+    pub fn length(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// The instruction without address and bytes (`LDA #$01`)
+    pub fn text(&self) -> String {
+        if self.operand.is_empty() {
+            self.mnemonic.to_string()
+        } else {
+            format!("{} {}", self.mnemonic, self.operand)
+        }
+    }
+}
+
+impl fmt::Display for Disassembled {
+    /// `0200  a9 01     LDA #$01`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        write!(
+            f,
+            "{:04x}  {:<8}  {}",
+            self.address,
+            bytes.join(" "),
+            self.text()
+        )
+    }
+}
+
+pub struct Disassembler<'a> {
+    cpu: Cpu,
+    symbols: Option<&'a SymbolTable>,
+}
+
+impl<'a> Disassembler<'a> {
+    pub fn new(cpu: Cpu) -> Disassembler<'a> {
+        Disassembler { cpu, symbols: None }
+    }
+
+    /// A disassembler that shows the addresses as labels of the table
+    pub fn with_symbols(cpu: Cpu, symbols: &'a SymbolTable) -> Disassembler<'a> {
+        Disassembler {
+            cpu,
+            symbols: Some(symbols),
+        }
+    }
+
+    /// Disassembles the instruction at the address
+    /// (the operand wraps around at the end of the memory)
+    pub fn disassemble(&self, memory: &[u8; MEMORY], address: u16) -> Disassembled {
+        let op = opcode(self.cpu, memory[address as usize]);
+        let bytes: Vec<u8> = (0..op.mode.length() as u16)
+            .map(|i| memory[address.wrapping_add(i) as usize])
+            .collect();
+        Disassembled {
+            address,
+            operand: self.operand(op.mode, address, &bytes),
+            bytes,
+            mnemonic: op.mnemonic,
+            mode: op.mode,
+            documented: op.documented,
+        }
+    }
+
+    /// Disassembles all instructions that start in the range
+    pub fn range(&self, memory: &[u8; MEMORY], range: RangeInclusive<u16>) -> Vec<Disassembled> {
+        let mut instructions = Vec::new();
+        let mut address = *range.start() as usize;
+        while address <= *range.end() as usize {
+            let instruction = self.disassemble(memory, address as u16);
+            address += instruction.length();
+            instructions.push(instruction);
+        }
+        instructions
+    }
+
+    /// A listing of the range, one instruction per line
+    /// and with a `label:` line before every labeled address
+    pub fn listing(&self, memory: &[u8; MEMORY], range: RangeInclusive<u16>) -> String {
+        let mut text = String::new();
+        for instruction in self.range(memory, range) {
+            if let Some(symbols) = self.symbols {
+                for label in symbols.labels_at(instruction.address) {
+                    text.push_str(&format!("{}:\n", label));
+                }
+            }
+            text.push_str(&format!("{}\n", instruction));
+        }
+        text
+    }
+
+    fn operand(&self, mode: Mode, address: u16, bytes: &[u8]) -> String {
+        let branch = |offset: i8, length: u16| {
+            let target = address.wrapping_add(length).wrapping_add(offset as u16);
+            self.absolute(target)
+        };
+        let operand = match Operand::from_bytes(mode, &bytes[1..]) {
+            Some(operand) => operand,
+            None => return String::new(),
+        };
+        match operand {
+            Operand::Implied => String::new(),
+            Operand::Accumulator => "A".to_string(),
+            Operand::Immediate(byte) => format!("#${:02X}", byte),
+            Operand::Zeropage(byte) => self.zeropage(byte),
+            Operand::ZeropageX(byte) => format!("{},X", self.zeropage(byte)),
+            Operand::ZeropageY(byte) => format!("{},Y", self.zeropage(byte)),
+            Operand::Absolute(word) => self.absolute(word),
+            Operand::AbsoluteX(word) => format!("{},X", self.absolute(word)),
+            Operand::AbsoluteY(word) => format!("{},Y", self.absolute(word)),
+            Operand::Indirect(word) => format!("({})", self.absolute(word)),
+            Operand::XIndirect(byte) => format!("({},X)", self.zeropage(byte)),
+            Operand::IndirectY(byte) => format!("({}),Y", self.zeropage(byte)),
+            Operand::Relative(offset) => branch(offset, 2),
+            Operand::ZeropageIndirect(byte) => format!("({})", self.zeropage(byte)),
+            Operand::AbsoluteXIndirect(word) => format!("({},X)", self.absolute(word)),
+            Operand::ZeropageRelative(byte, offset) => {
+                format!("{},{}", self.zeropage(byte), branch(offset, 3))
+            }
+        }
+    }
+
+    /// A zeropage address, only exact labels are used
+    fn zeropage(&self, value: u8) -> String {
+        match self.symbols.and_then(|s| s.label_at(value as u16)) {
+            Some(label) => label.to_string(),
+            None => format!("${:02X}", value),
+        }
+    }
+
+    /// An address as `label`, `label+offset` or `$1234`
+    fn absolute(&self, value: u16) -> String {
+        match self.symbols {
+            Some(symbols) => symbols.format_address(value),
+            None => format!("${:04X}", value),
+        }
+    }
+}
+
+const fn op(mnemonic: &'static str, mode: Mode) -> Opcode {
+    Opcode {
+        mnemonic,
+        mode,
+        documented: true,
+    }
+}
+
+const fn ill(mnemonic: &'static str, mode: Mode) -> Opcode {
+    Opcode {
+        mnemonic,
+        mode,
+        documented: false,
+    }
+}
+
+#[rustfmt::skip]
+const NMOS: [Opcode; 256] = [
+    // $00
+    op("BRK", Mode::Implied), op("ORA", Mode::XIndirect), ill("JAM", Mode::Implied), ill("SLO", Mode::XIndirect),
+    ill("NOP", Mode::Zeropage), op("ORA", Mode::Zeropage), op("ASL", Mode::Zeropage), ill("SLO", Mode::Zeropage),
+    op("PHP", Mode::Implied), op("ORA", Mode::Immediate), op("ASL", Mode::Accumulator), ill("ANC", Mode::Immediate),
+    ill("NOP", Mode::Absolute), op("ORA", Mode::Absolute), op("ASL", Mode::Absolute), ill("SLO", Mode::Absolute),
+    // $10
+    op("BPL", Mode::Relative), op("ORA", Mode::IndirectY), ill("JAM", Mode::Implied), ill("SLO", Mode::IndirectY),
+    ill("NOP", Mode::ZeropageX), op("ORA", Mode::ZeropageX), op("ASL", Mode::ZeropageX), ill("SLO", Mode::ZeropageX),
+    op("CLC", Mode::Implied), op("ORA", Mode::AbsoluteY), ill("NOP", Mode::Implied), ill("SLO", Mode::AbsoluteY),
+    ill("NOP", Mode::AbsoluteX), op("ORA", Mode::AbsoluteX), op("ASL", Mode::AbsoluteX), ill("SLO", Mode::AbsoluteX),
+    // $20
+    op("JSR", Mode::Absolute), op("AND", Mode::XIndirect), ill("JAM", Mode::Implied), ill("RLA", Mode::XIndirect),
+    op("BIT", Mode::Zeropage), op("AND", Mode::Zeropage), op("ROL", Mode::Zeropage), ill("RLA", Mode::Zeropage),
+    op("PLP", Mode::Implied), op("AND", Mode::Immediate), op("ROL", Mode::Accumulator), ill("ANC", Mode::Immediate),
+    op("BIT", Mode::Absolute), op("AND", Mode::Absolute), op("ROL", Mode::Absolute), ill("RLA", Mode::Absolute),
+    // $30
+    op("BMI", Mode::Relative), op("AND", Mode::IndirectY), ill("JAM", Mode::Implied), ill("RLA", Mode::IndirectY),
+    ill("NOP", Mode::ZeropageX), op("AND", Mode::ZeropageX), op("ROL", Mode::ZeropageX), ill("RLA", Mode::ZeropageX),
+    op("SEC", Mode::Implied), op("AND", Mode::AbsoluteY), ill("NOP", Mode::Implied), ill("RLA", Mode::AbsoluteY),
+    ill("NOP", Mode::AbsoluteX), op("AND", Mode::AbsoluteX), op("ROL", Mode::AbsoluteX), ill("RLA", Mode::AbsoluteX),
+    // $40
+    op("RTI", Mode::Implied), op("EOR", Mode::XIndirect), ill("JAM", Mode::Implied), ill("SRE", Mode::XIndirect),
+    ill("NOP", Mode::Zeropage), op("EOR", Mode::Zeropage), op("LSR", Mode::Zeropage), ill("SRE", Mode::Zeropage),
+    op("PHA", Mode::Implied), op("EOR", Mode::Immediate), op("LSR", Mode::Accumulator), ill("ALR", Mode::Immediate),
+    op("JMP", Mode::Absolute), op("EOR", Mode::Absolute), op("LSR", Mode::Absolute), ill("SRE", Mode::Absolute),
+    // $50
+    op("BVC", Mode::Relative), op("EOR", Mode::IndirectY), ill("JAM", Mode::Implied), ill("SRE", Mode::IndirectY),
+    ill("NOP", Mode::ZeropageX), op("EOR", Mode::ZeropageX), op("LSR", Mode::ZeropageX), ill("SRE", Mode::ZeropageX),
+    op("CLI", Mode::Implied), op("EOR", Mode::AbsoluteY), ill("NOP", Mode::Implied), ill("SRE", Mode::AbsoluteY),
+    ill("NOP", Mode::AbsoluteX), op("EOR", Mode::AbsoluteX), op("LSR", Mode::AbsoluteX), ill("SRE", Mode::AbsoluteX),
+    // $60
+    op("RTS", Mode::Implied), op("ADC", Mode::XIndirect), ill("JAM", Mode::Implied), ill("RRA", Mode::XIndirect),
+    ill("NOP", Mode::Zeropage), op("ADC", Mode::Zeropage), op("ROR", Mode::Zeropage), ill("RRA", Mode::Zeropage),
+    op("PLA", Mode::Implied), op("ADC", Mode::Immediate), op("ROR", Mode::Accumulator), ill("ARR", Mode::Immediate),
+    op("JMP", Mode::Indirect), op("ADC", Mode::Absolute), op("ROR", Mode::Absolute), ill("RRA", Mode::Absolute),
+    // $70
+    op("BVS", Mode::Relative), op("ADC", Mode::IndirectY), ill("JAM", Mode::Implied), ill("RRA", Mode::IndirectY),
+    ill("NOP", Mode::ZeropageX), op("ADC", Mode::ZeropageX), op("ROR", Mode::ZeropageX), ill("RRA", Mode::ZeropageX),
+    op("SEI", Mode::Implied), op("ADC", Mode::AbsoluteY), ill("NOP", Mode::Implied), ill("RRA", Mode::AbsoluteY),
+    ill("NOP", Mode::AbsoluteX), op("ADC", Mode::AbsoluteX), op("ROR", Mode::AbsoluteX), ill("RRA", Mode::AbsoluteX),
+    // $80
+    ill("NOP", Mode::Immediate), op("STA", Mode::XIndirect), ill("NOP", Mode::Immediate), ill("SAX", Mode::XIndirect),
+    op("STY", Mode::Zeropage), op("STA", Mode::Zeropage), op("STX", Mode::Zeropage), ill("SAX", Mode::Zeropage),
+    op("DEY", Mode::Implied), ill("NOP", Mode::Immediate), op("TXA", Mode::Implied), ill("ANE", Mode::Immediate),
+    op("STY", Mode::Absolute), op("STA", Mode::Absolute), op("STX", Mode::Absolute), ill("SAX", Mode::Absolute),
+    // $90
+    op("BCC", Mode::Relative), op("STA", Mode::IndirectY), ill("JAM", Mode::Implied), ill("SHA", Mode::IndirectY),
+    op("STY", Mode::ZeropageX), op("STA", Mode::ZeropageX), op("STX", Mode::ZeropageY), ill("SAX", Mode::ZeropageY),
+    op("TYA", Mode::Implied), op("STA", Mode::AbsoluteY), op("TXS", Mode::Implied), ill("TAS", Mode::AbsoluteY),
+    ill("SHY", Mode::AbsoluteX), op("STA", Mode::AbsoluteX), ill("SHX", Mode::AbsoluteY), ill("SHA", Mode::AbsoluteY),
+    // $A0
+    op("LDY", Mode::Immediate), op("LDA", Mode::XIndirect), op("LDX", Mode::Immediate), ill("LAX", Mode::XIndirect),
+    op("LDY", Mode::Zeropage), op("LDA", Mode::Zeropage), op("LDX", Mode::Zeropage), ill("LAX", Mode::Zeropage),
+    op("TAY", Mode::Implied), op("LDA", Mode::Immediate), op("TAX", Mode::Implied), ill("LXA", Mode::Immediate),
+    op("LDY", Mode::Absolute), op("LDA", Mode::Absolute), op("LDX", Mode::Absolute), ill("LAX", Mode::Absolute),
+    // $B0
+    op("BCS", Mode::Relative), op("LDA", Mode::IndirectY), ill("JAM", Mode::Implied), ill("LAX", Mode::IndirectY),
+    op("LDY", Mode::ZeropageX), op("LDA", Mode::ZeropageX), op("LDX", Mode::ZeropageY), ill("LAX", Mode::ZeropageY),
+    op("CLV", Mode::Implied), op("LDA", Mode::AbsoluteY), op("TSX", Mode::Implied), ill("LAS", Mode::AbsoluteY),
+    op("LDY", Mode::AbsoluteX), op("LDA", Mode::AbsoluteX), op("LDX", Mode::AbsoluteY), ill("LAX", Mode::AbsoluteY),
+    // $C0
+    op("CPY", Mode::Immediate), op("CMP", Mode::XIndirect), ill("NOP", Mode::Immediate), ill("DCP", Mode::XIndirect),
+    op("CPY", Mode::Zeropage), op("CMP", Mode::Zeropage), op("DEC", Mode::Zeropage), ill("DCP", Mode::Zeropage),
+    op("INY", Mode::Implied), op("CMP", Mode::Immediate), op("DEX", Mode::Implied), ill("SBX", Mode::Immediate),
+    op("CPY", Mode::Absolute), op("CMP", Mode::Absolute), op("DEC", Mode::Absolute), ill("DCP", Mode::Absolute),
+    // $D0
+    op("BNE", Mode::Relative), op("CMP", Mode::IndirectY), ill("JAM", Mode::Implied), ill("DCP", Mode::IndirectY),
+    ill("NOP", Mode::ZeropageX), op("CMP", Mode::ZeropageX), op("DEC", Mode::ZeropageX), ill("DCP", Mode::ZeropageX),
+    op("CLD", Mode::Implied), op("CMP", Mode::AbsoluteY), ill("NOP", Mode::Implied), ill("DCP", Mode::AbsoluteY),
+    ill("NOP", Mode::AbsoluteX), op("CMP", Mode::AbsoluteX), op("DEC", Mode::AbsoluteX), ill("DCP", Mode::AbsoluteX),
+    // $E0
+    op("CPX", Mode::Immediate), op("SBC", Mode::XIndirect), ill("NOP", Mode::Immediate), ill("ISC", Mode::XIndirect),
+    op("CPX", Mode::Zeropage), op("SBC", Mode::Zeropage), op("INC", Mode::Zeropage), ill("ISC", Mode::Zeropage),
+    op("INX", Mode::Implied), op("SBC", Mode::Immediate), op("NOP", Mode::Implied), ill("SBC", Mode::Immediate),
+    op("CPX", Mode::Absolute), op("SBC", Mode::Absolute), op("INC", Mode::Absolute), ill("ISC", Mode::Absolute),
+    // $F0
+    op("BEQ", Mode::Relative), op("SBC", Mode::IndirectY), ill("JAM", Mode::Implied), ill("ISC", Mode::IndirectY),
+    ill("NOP", Mode::ZeropageX), op("SBC", Mode::ZeropageX), op("INC", Mode::ZeropageX), ill("ISC", Mode::ZeropageX),
+    op("SED", Mode::Implied), op("SBC", Mode::AbsoluteY), ill("NOP", Mode::Implied), ill("ISC", Mode::AbsoluteY),
+    ill("NOP", Mode::AbsoluteX), op("SBC", Mode::AbsoluteX), op("INC", Mode::AbsoluteX), ill("ISC", Mode::AbsoluteX),
+];
+
+#[rustfmt::skip]
+const CMOS: [Opcode; 256] = [
+    // $00
+    op("BRK", Mode::Implied), op("ORA", Mode::XIndirect), ill("NOP", Mode::Immediate), ill("NOP", Mode::Implied),
+    op("TSB", Mode::Zeropage), op("ORA", Mode::Zeropage), op("ASL", Mode::Zeropage), op("RMB0", Mode::Zeropage),
+    op("PHP", Mode::Implied), op("ORA", Mode::Immediate), op("ASL", Mode::Accumulator), ill("NOP", Mode::Implied),
+    op("TSB", Mode::Absolute), op("ORA", Mode::Absolute), op("ASL", Mode::Absolute), op("BBR0", Mode::ZeropageRelative),
+    // $10
+    op("BPL", Mode::Relative), op("ORA", Mode::IndirectY), op("ORA", Mode::ZeropageIndirect), ill("NOP", Mode::Implied),
+    op("TRB", Mode::Zeropage), op("ORA", Mode::ZeropageX), op("ASL", Mode::ZeropageX), op("RMB1", Mode::Zeropage),
+    op("CLC", Mode::Implied), op("ORA", Mode::AbsoluteY), op("INC", Mode::Accumulator), ill("NOP", Mode::Implied),
+    op("TRB", Mode::Absolute), op("ORA", Mode::AbsoluteX), op("ASL", Mode::AbsoluteX), op("BBR1", Mode::ZeropageRelative),
+    // $20
+    op("JSR", Mode::Absolute), op("AND", Mode::XIndirect), ill("NOP", Mode::Immediate), ill("NOP", Mode::Implied),
+    op("BIT", Mode::Zeropage), op("AND", Mode::Zeropage), op("ROL", Mode::Zeropage), op("RMB2", Mode::Zeropage),
+    op("PLP", Mode::Implied), op("AND", Mode::Immediate), op("ROL", Mode::Accumulator), ill("NOP", Mode::Implied),
+    op("BIT", Mode::Absolute), op("AND", Mode::Absolute), op("ROL", Mode::Absolute), op("BBR2", Mode::ZeropageRelative),
+    // $30
+    op("BMI", Mode::Relative), op("AND", Mode::IndirectY), op("AND", Mode::ZeropageIndirect), ill("NOP", Mode::Implied),
+    op("BIT", Mode::ZeropageX), op("AND", Mode::ZeropageX), op("ROL", Mode::ZeropageX), op("RMB3", Mode::Zeropage),
+    op("SEC", Mode::Implied), op("AND", Mode::AbsoluteY), op("DEC", Mode::Accumulator), ill("NOP", Mode::Implied),
+    op("BIT", Mode::AbsoluteX), op("AND", Mode::AbsoluteX), op("ROL", Mode::AbsoluteX), op("BBR3", Mode::ZeropageRelative),
+    // $40
+    op("RTI", Mode::Implied), op("EOR", Mode::XIndirect), ill("NOP", Mode::Immediate), ill("NOP", Mode::Implied),
+    ill("NOP", Mode::Zeropage), op("EOR", Mode::Zeropage), op("LSR", Mode::Zeropage), op("RMB4", Mode::Zeropage),
+    op("PHA", Mode::Implied), op("EOR", Mode::Immediate), op("LSR", Mode::Accumulator), ill("NOP", Mode::Implied),
+    op("JMP", Mode::Absolute), op("EOR", Mode::Absolute), op("LSR", Mode::Absolute), op("BBR4", Mode::ZeropageRelative),
+    // $50
+    op("BVC", Mode::Relative), op("EOR", Mode::IndirectY), op("EOR", Mode::ZeropageIndirect), ill("NOP", Mode::Implied),
+    ill("NOP", Mode::ZeropageX), op("EOR", Mode::ZeropageX), op("LSR", Mode::ZeropageX), op("RMB5", Mode::Zeropage),
+    op("CLI", Mode::Implied), op("EOR", Mode::AbsoluteY), op("PHY", Mode::Implied), ill("NOP", Mode::Implied),
+    ill("NOP", Mode::Absolute), op("EOR", Mode::AbsoluteX), op("LSR", Mode::AbsoluteX), op("BBR5", Mode::ZeropageRelative),
+    // $60
+    op("RTS", Mode::Implied), op("ADC", Mode::XIndirect), ill("NOP", Mode::Immediate), ill("NOP", Mode::Implied),
+    op("STZ", Mode::Zeropage), op("ADC", Mode::Zeropage), op("ROR", Mode::Zeropage), op("RMB6", Mode::Zeropage),
+    op("PLA", Mode::Implied), op("ADC", Mode::Immediate), op("ROR", Mode::Accumulator), ill("NOP", Mode::Implied),
+    op("JMP", Mode::Indirect), op("ADC", Mode::Absolute), op("ROR", Mode::Absolute), op("BBR6", Mode::ZeropageRelative),
+    // $70
+    op("BVS", Mode::Relative), op("ADC", Mode::IndirectY), op("ADC", Mode::ZeropageIndirect), ill("NOP", Mode::Implied),
+    op("STZ", Mode::ZeropageX), op("ADC", Mode::ZeropageX), op("ROR", Mode::ZeropageX), op("RMB7", Mode::Zeropage),
+    op("SEI", Mode::Implied), op("ADC", Mode::AbsoluteY), op("PLY", Mode::Implied), ill("NOP", Mode::Implied),
+    op("JMP", Mode::AbsoluteXIndirect), op("ADC", Mode::AbsoluteX), op("ROR", Mode::AbsoluteX), op("BBR7", Mode::ZeropageRelative),
+    // $80
+    op("BRA", Mode::Relative), op("STA", Mode::XIndirect), ill("NOP", Mode::Immediate), ill("NOP", Mode::Implied),
+    op("STY", Mode::Zeropage), op("STA", Mode::Zeropage), op("STX", Mode::Zeropage), op("SMB0", Mode::Zeropage),
+    op("DEY", Mode::Implied), op("BIT", Mode::Immediate), op("TXA", Mode::Implied), ill("NOP", Mode::Implied),
+    op("STY", Mode::Absolute), op("STA", Mode::Absolute), op("STX", Mode::Absolute), op("BBS0", Mode::ZeropageRelative),
+    // $90
+    op("BCC", Mode::Relative), op("STA", Mode::IndirectY), op("STA", Mode::ZeropageIndirect), ill("NOP", Mode::Implied),
+    op("STY", Mode::ZeropageX), op("STA", Mode::ZeropageX), op("STX", Mode::ZeropageY), op("SMB1", Mode::Zeropage),
+    op("TYA", Mode::Implied), op("STA", Mode::AbsoluteY), op("TXS", Mode::Implied), ill("NOP", Mode::Implied),
+    op("STZ", Mode::Absolute), op("STA", Mode::AbsoluteX), op("STZ", Mode::AbsoluteX), op("BBS1", Mode::ZeropageRelative),
+    // $A0
+    op("LDY", Mode::Immediate), op("LDA", Mode::XIndirect), op("LDX", Mode::Immediate), ill("NOP", Mode::Implied),
+    op("LDY", Mode::Zeropage), op("LDA", Mode::Zeropage), op("LDX", Mode::Zeropage), op("SMB2", Mode::Zeropage),
+    op("TAY", Mode::Implied), op("LDA", Mode::Immediate), op("TAX", Mode::Implied), ill("NOP", Mode::Implied),
+    op("LDY", Mode::Absolute), op("LDA", Mode::Absolute), op("LDX", Mode::Absolute), op("BBS2", Mode::ZeropageRelative),
+    // $B0
+    op("BCS", Mode::Relative), op("LDA", Mode::IndirectY), op("LDA", Mode::ZeropageIndirect), ill("NOP", Mode::Implied),
+    op("LDY", Mode::ZeropageX), op("LDA", Mode::ZeropageX), op("LDX", Mode::ZeropageY), op("SMB3", Mode::Zeropage),
+    op("CLV", Mode::Implied), op("LDA", Mode::AbsoluteY), op("TSX", Mode::Implied), ill("NOP", Mode::Implied),
+    op("LDY", Mode::AbsoluteX), op("LDA", Mode::AbsoluteX), op("LDX", Mode::AbsoluteY), op("BBS3", Mode::ZeropageRelative),
+    // $C0
+    op("CPY", Mode::Immediate), op("CMP", Mode::XIndirect), ill("NOP", Mode::Immediate), ill("NOP", Mode::Implied),
+    op("CPY", Mode::Zeropage), op("CMP", Mode::Zeropage), op("DEC", Mode::Zeropage), op("SMB4", Mode::Zeropage),
+    op("INY", Mode::Implied), op("CMP", Mode::Immediate), op("DEX", Mode::Implied), op("WAI", Mode::Implied),
+    op("CPY", Mode::Absolute), op("CMP", Mode::Absolute), op("DEC", Mode::Absolute), op("BBS4", Mode::ZeropageRelative),
+    // $D0
+    op("BNE", Mode::Relative), op("CMP", Mode::IndirectY), op("CMP", Mode::ZeropageIndirect), ill("NOP", Mode::Implied),
+    ill("NOP", Mode::ZeropageX), op("CMP", Mode::ZeropageX), op("DEC", Mode::ZeropageX), op("SMB5", Mode::Zeropage),
+    op("CLD", Mode::Implied), op("CMP", Mode::AbsoluteY), op("PHX", Mode::Implied), op("STP", Mode::Implied),
+    ill("NOP", Mode::Absolute), op("CMP", Mode::AbsoluteX), op("DEC", Mode::AbsoluteX), op("BBS5", Mode::ZeropageRelative),
+    // $E0
+    op("CPX", Mode::Immediate), op("SBC", Mode::XIndirect), ill("NOP", Mode::Immediate), ill("NOP", Mode::Implied),
+    op("CPX", Mode::Zeropage), op("SBC", Mode::Zeropage), op("INC", Mode::Zeropage), op("SMB6", Mode::Zeropage),
+    op("INX", Mode::Implied), op("SBC", Mode::Immediate), op("NOP", Mode::Implied), ill("NOP", Mode::Implied),
+    op("CPX", Mode::Absolute), op("SBC", Mode::Absolute), op("INC", Mode::Absolute), op("BBS6", Mode::ZeropageRelative),
+    // $F0
+    op("BEQ", Mode::Relative), op("SBC", Mode::IndirectY), op("SBC", Mode::ZeropageIndirect), ill("NOP", Mode::Implied),
+    ill("NOP", Mode::ZeropageX), op("SBC", Mode::ZeropageX), op("INC", Mode::ZeropageX), op("SMB7", Mode::Zeropage),
+    op("SED", Mode::Implied), op("SBC", Mode::AbsoluteY), op("PLX", Mode::Implied), ill("NOP", Mode::Implied),
+    ill("NOP", Mode::Absolute), op("SBC", Mode::AbsoluteX), op("INC", Mode::AbsoluteX), op("BBS7", Mode::ZeropageRelative),
+];
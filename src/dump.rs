@@ -0,0 +1,140 @@
+// Exporting ranges of the memory
+//
+// raw     ... the bytes as they are
+// ihex    ... Intel HEX with 16 bytes per record (readable by `Chip::load_ihex`)
+// hexdump ... 16 bytes per line with their characters in ASCII or PETSCII:
+//
+// 0200  a9 01 69 01 69 01 00 00  00 00 00 00 00 00 00 00  |..i.i...........|
+
+use std::ops::RangeInclusive;
+
+use crate::chip::Chip;
+use crate::symbols::parse_value;
+
+/// How the characters of a hexdump are shown
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Charset {
+    Ascii,
+    // The shifted (lower case) character set of the Commodore machines:
+    Petscii,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Format {
+    Raw,
+    Ihex,
+    Hexdump(Charset),
+}
+
+impl Format {
+    /// `raw`, `ihex`, `hexdump` (ASCII) or `petscii`
+    pub fn parse(text: &str) -> Option<Format> {
+        match text.to_ascii_lowercase().as_str() {
+            "raw" | "bin" => Some(Format::Raw),
+            "ihex" | "hex" => Some(Format::Ihex),
+            "hexdump" | "ascii" => Some(Format::Hexdump(Charset::Ascii)),
+            "petscii" => Some(Format::Hexdump(Charset::Petscii)),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a range like `$0200-$02FF` or `$0200+256`
+/// (the numbers are written like in label files)
+pub fn parse_range(text: &str) -> Option<RangeInclusive<u16>> {
+    if let Some((start, end)) = text.split_once('-') {
+        let (start, end) = (parse_value(start.trim())?, parse_value(end.trim())?);
+        if start > end {
+            return None;
+        }
+        Some(start..=end)
+    } else {
+        let (start, length) = text.split_once('+')?;
+        let (start, length) = (parse_value(start.trim())?, parse_value(length.trim())?);
+        if length == 0 {
+            return None;
+        }
+        Some(start..=start.checked_add(length - 1)?)
+    }
+}
+
+/// The bytes of the range
+pub fn raw(c: &Chip, range: RangeInclusive<u16>) -> Vec<u8> {
+    c.memory[*range.start() as usize..=*range.end() as usize].to_vec()
+}
+
+/// The range as Intel HEX records
+pub fn ihex(c: &Chip, range: RangeInclusive<u16>) -> String {
+    let mut text = String::new();
+    let start = *range.start() as usize;
+    for (i, chunk) in raw(c, range).chunks(16).enumerate() {
+        let address = (start + i * 16) as u16;
+        let [hh, ll] = address.to_be_bytes();
+        let mut record = [chunk.len() as u8, hh, ll, 0x00].to_vec();
+        record.extend_from_slice(chunk);
+        let sum = record.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        record.push(sum.wrapping_neg());
+
+        text.push(':');
+        for byte in record {
+            text.push_str(&format!("{:02X}", byte));
+        }
+        text.push('\n');
+    }
+    text.push_str(":00000001FF\n");
+    text
+}
+
+/// The range as a hexdump, one line for every 16 bytes
+pub fn hexdump(c: &Chip, range: RangeInclusive<u16>, charset: Charset) -> String {
+    let mut text = String::new();
+    let start = *range.start() as usize;
+    for (i, chunk) in raw(c, range).chunks(16).enumerate() {
+        text.push_str(&format!("{:04x} ", start + i * 16));
+        for j in 0..16 {
+            if j == 8 {
+                text.push(' ');
+            }
+            match chunk.get(j) {
+                Some(byte) => text.push_str(&format!(" {:02x}", byte)),
+                None => text.push_str("   "),
+            }
+        }
+        text.push_str("  |");
+        for byte in chunk {
+            text.push(match charset {
+                Charset::Ascii => ascii(*byte),
+                Charset::Petscii => petscii(*byte),
+            });
+        }
+        text.push_str("|\n");
+    }
+    text
+}
+
+/// The range in the format
+pub fn export(c: &Chip, range: RangeInclusive<u16>, format: Format) -> Vec<u8> {
+    match format {
+        Format::Raw => raw(c, range),
+        Format::Ihex => ihex(c, range).into_bytes(),
+        Format::Hexdump(charset) => hexdump(c, range, charset).into_bytes(),
+    }
+}
+
+fn ascii(byte: u8) -> char {
+    match byte {
+        0x20..=0x7E => byte as char,
+        _ => '.',
+    }
+}
+
+fn petscii(byte: u8) -> char {
+    match byte {
+        0x41..=0x5A => byte.to_ascii_lowercase() as char,
+        0x61..=0x7A | 0xC1..=0xDA => (byte & 0x1F | 0x40) as char,
+        // £, ↑ and ←
+        0x5C | 0x5E | 0x5F => '.',
+        0x20..=0x5D => byte as char,
+        _ => '.',
+    }
+}
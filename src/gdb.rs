@@ -0,0 +1,600 @@
+// A stub for the GDB remote serial protocol
+//
+// A debugger front-end (gdb, or anything else that speaks the protocol)
+// connects over TCP and controls the chip with packets like `$m200,4#2d`.
+// The stub answers:
+//
+// ?            why the chip stopped (always a SIGTRAP)
+// g, G         read and write all registers
+// p, P         read and write one register
+// m, M         read and write memory
+// s, c         step and continue (with an optional address)
+// Z0/z0, Z1/z1 set and delete software and hardware breakpoints
+// Z2-Z4/z2-z4  set and delete write, read and access watchpoints
+// D, k         detach and kill (both end the session)
+// qSupported, qAttached, qXfer:features:read (the register layout),
+// QStartNoAckMode and the thread queries of a single thread
+//
+// Everything else gets an empty answer, which means "not supported".
+// A ^C (0x03) while the chip runs stops it.
+//
+// The registers are (with their numbers, the values are little endian):
+//
+// 0 a   8 bits
+// 1 x   8 bits
+// 2 y   8 bits
+// 3 p   8 bits (NV-BDIZC)
+// 4 sp  8 bits (the stack is at $0100 + sp)
+// 5 pc  16 bits
+//
+// `g` answers all of them in this order, `14020300ff0002` is A=$14,
+// X=$02, Y=$03, P=$00, SP=$FF and PC=$0200.
+//
+// Reference: https://sourceware.org/gdb/current/onlinedocs/gdb.html/Remote-Protocol.html
+
+use std::collections::{BTreeSet, VecDeque};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::chip::{Chip, MEMORY};
+use crate::instruction::Operand;
+use crate::opcodes;
+
+/// The register layout that `qXfer:features:read:target.xml` sends
+pub const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.sixfiveohtwo.mos6502">
+    <reg name="a" bitsize="8" regnum="0" type="uint8"/>
+    <reg name="x" bitsize="8" regnum="1" type="uint8"/>
+    <reg name="y" bitsize="8" regnum="2" type="uint8"/>
+    <reg name="p" bitsize="8" regnum="3" type="uint8"/>
+    <reg name="sp" bitsize="8" regnum="4" type="uint8"/>
+    <reg name="pc" bitsize="16" regnum="5" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+/// How many instructions run between the checks for a ^C
+const INTERRUPT_CHECK: usize = 4096;
+/// The longest packet that is read (`PacketSize` in qSupported),
+/// longer ones are answered with a `-`
+const PACKET_SIZE: usize = 0x4000;
+
+// The signals in the stop replies
+const SIGINT: u8 = 2;
+const SIGTRAP: u8 = 5;
+
+/// What a watchpoint waits for
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Watch {
+    Write,
+    Read,
+    Access,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+struct Watchpoint {
+    kind: Watch,
+    address: usize,
+    // Never 0, and the watched bytes end inside of the memory:
+    length: usize,
+}
+
+impl Watchpoint {
+    fn covers(&self, address: u16) -> bool {
+        (self.address..self.address + self.length).contains(&(address as usize))
+    }
+}
+
+pub struct Stub<'a> {
+    chip: &'a mut Chip,
+    breakpoints: BTreeSet<u16>,
+    watchpoints: Vec<Watchpoint>,
+    no_ack: bool,
+    attached: bool,
+}
+
+impl<'a> Stub<'a> {
+    pub fn new(chip: &'a mut Chip) -> Stub<'a> {
+        Stub {
+            chip,
+            breakpoints: BTreeSet::new(),
+            watchpoints: Vec::new(),
+            no_ack: false,
+            attached: true,
+        }
+    }
+
+    /// If the debugger has not detached (or killed) yet
+    pub fn attached(&self) -> bool {
+        self.attached
+    }
+
+    /// Answers a packet (without `$` and the checksum), `None` if there
+    /// is no answer. `interrupted` is asked while the chip runs.
+    pub fn handle(
+        &mut self,
+        packet: &str,
+        interrupted: &mut dyn FnMut() -> bool,
+    ) -> Option<String> {
+        let command = packet.get(..1).unwrap_or("");
+        let rest = packet.get(1..).unwrap_or("");
+        let reply = match command {
+            "?" => stop(SIGTRAP),
+            "g" => self.read_registers(),
+            "G" => self.write_registers(rest),
+            "p" => match parse_hex(rest).and_then(|n| self.register(n)) {
+                Some(value) => value,
+                None => "E01".to_string(),
+            },
+            "P" => self.write_register(rest),
+            "m" => self.read_memory(rest),
+            "M" => self.write_memory(rest),
+            "s" | "c" => {
+                if !rest.is_empty() {
+                    match parse_hex(rest) {
+                        Some(address) if address <= 0xFFFF => self.chip.pc = address as u16,
+                        _ => return Some("E01".to_string()),
+                    }
+                }
+                if command == "s" {
+                    self.resume(1, interrupted)
+                } else {
+                    self.resume(usize::MAX, interrupted)
+                }
+            }
+            "Z" | "z" => self.point(command == "Z", rest),
+            "D" => {
+                self.attached = false;
+                "OK".to_string()
+            }
+            "k" => {
+                self.attached = false;
+                return None;
+            }
+            "H" => "OK".to_string(),
+            "T" => "OK".to_string(),
+            _ => self.query(packet),
+        };
+        Some(reply)
+    }
+
+    /// Answers the packets of a connection until the debugger detaches
+    /// or the connection ends
+    pub fn serve(&mut self, stream: TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut connection = Connection {
+            stream,
+            pending: VecDeque::new(),
+        };
+        while self.attached {
+            let packet = match connection.read_packet()? {
+                Some(Packet::Data(packet)) => packet,
+                // a ^C while the chip is stopped
+                Some(Packet::Interrupt) => {
+                    connection.write(&frame(&stop(SIGINT)))?;
+                    continue;
+                }
+                Some(Packet::Invalid) => {
+                    if !self.no_ack {
+                        connection.write("-")?;
+                    }
+                    continue;
+                }
+                None => break,
+            };
+            if !self.no_ack {
+                connection.write("+")?;
+            }
+            let mut interrupted = || connection.interrupt_pending();
+            if let Some(reply) = self.handle(&packet, &mut interrupted) {
+                connection.write(&frame(&reply))?;
+            }
+        }
+        Ok(())
+    }
+
+    // =====================
+    // Packets
+    // =====================
+
+    fn read_registers(&self) -> String {
+        (0..6).filter_map(|n| self.register(n)).collect()
+    }
+
+    fn write_registers(&mut self, text: &str) -> String {
+        let bytes = match from_hex(text) {
+            Some(bytes) if bytes.len() == 7 => bytes,
+            _ => return "E01".to_string(),
+        };
+        let c = &mut self.chip;
+        c.acc = bytes[0];
+        c.rx = bytes[1];
+        c.ry = bytes[2];
+        c.f = bytes[3];
+        c.sp = bytes[4];
+        c.pc = u16::from_le_bytes([bytes[5], bytes[6]]);
+        "OK".to_string()
+    }
+
+    /// The register as hex (little endian)
+    fn register(&self, n: usize) -> Option<String> {
+        let c = &self.chip;
+        Some(match n {
+            0 => to_hex(&[c.acc]),
+            1 => to_hex(&[c.rx]),
+            2 => to_hex(&[c.ry]),
+            3 => to_hex(&[c.f]),
+            4 => to_hex(&[c.sp]),
+            5 => to_hex(&c.pc.to_le_bytes()),
+            _ => return None,
+        })
+    }
+
+    fn write_register(&mut self, text: &str) -> String {
+        let (n, value) = match text.split_once('=') {
+            Some((n, value)) => (parse_hex(n), from_hex(value)),
+            None => return "E01".to_string(),
+        };
+        let c = &mut self.chip;
+        match (n, value.as_deref()) {
+            (Some(0), Some([byte])) => c.acc = *byte,
+            (Some(1), Some([byte])) => c.rx = *byte,
+            (Some(2), Some([byte])) => c.ry = *byte,
+            (Some(3), Some([byte])) => c.f = *byte,
+            (Some(4), Some([byte])) => c.sp = *byte,
+            (Some(5), Some([low, high])) => c.pc = u16::from_le_bytes([*low, *high]),
+            _ => return "E01".to_string(),
+        }
+        "OK".to_string()
+    }
+
+    fn read_memory(&self, text: &str) -> String {
+        match address_and_length(text) {
+            Some((address, length)) => to_hex(&self.chip.memory[address..address + length]),
+            None => "E01".to_string(),
+        }
+    }
+
+    fn write_memory(&mut self, text: &str) -> String {
+        let (range, data) = match text.split_once(':') {
+            Some(parts) => parts,
+            None => return "E01".to_string(),
+        };
+        match (address_and_length(range), from_hex(data)) {
+            (Some((address, length)), Some(bytes)) if bytes.len() == length => {
+                self.chip.memory[address..address + length].copy_from_slice(&bytes);
+                "OK".to_string()
+            }
+            _ => "E01".to_string(),
+        }
+    }
+
+    /// `Z<type>,<address>,<kind>` and `z...`
+    fn point(&mut self, insert: bool, text: &str) -> String {
+        let (kind, rest) = match text.split_once(',') {
+            Some(fields) => fields,
+            None => return "E01".to_string(),
+        };
+        // the length of a breakpoint is the size of the instruction
+        let (address, length) = match address_and_length_unchecked(rest) {
+            Some((address, _)) if matches!(kind, "0" | "1") && address < MEMORY => (address, 1),
+            _ => match address_and_length(rest) {
+                Some((address, length)) if length > 0 => (address, length),
+                _ => return "E01".to_string(),
+            },
+        };
+        let watch = match kind {
+            "0" | "1" => {
+                if insert {
+                    self.breakpoints.insert(address as u16);
+                } else {
+                    self.breakpoints.remove(&(address as u16));
+                }
+                return "OK".to_string();
+            }
+            "2" => Watch::Write,
+            "3" => Watch::Read,
+            "4" => Watch::Access,
+            _ => return String::new(),
+        };
+        let watchpoint = Watchpoint {
+            kind: watch,
+            address,
+            length,
+        };
+        if insert {
+            self.watchpoints.push(watchpoint);
+        } else {
+            self.watchpoints.retain(|w| *w != watchpoint);
+        }
+        "OK".to_string()
+    }
+
+    fn query(&mut self, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            return format!(
+                "PacketSize={:x};qXfer:features:read+;QStartNoAckMode+",
+                PACKET_SIZE
+            );
+        }
+        if let Some(rest) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            return match address_and_length_unchecked(rest) {
+                Some((offset, length)) => {
+                    let xml = TARGET_XML.as_bytes();
+                    let start = offset.min(xml.len());
+                    let end = (start + length).min(xml.len());
+                    let chunk = String::from_utf8_lossy(&xml[start..end]);
+                    if end == xml.len() {
+                        format!("l{}", chunk)
+                    } else {
+                        format!("m{}", chunk)
+                    }
+                }
+                None => "E01".to_string(),
+            };
+        }
+        match packet {
+            "QStartNoAckMode" => {
+                self.no_ack = true;
+                "OK".to_string()
+            }
+            "qAttached" => "1".to_string(),
+            "qC" => "QC1".to_string(),
+            "qfThreadInfo" => "m1".to_string(),
+            "qsThreadInfo" => "l".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    // =====================
+    // Running
+    // =====================
+
+    /// Runs up to `count` instructions and returns the stop reply
+    fn resume(&mut self, count: usize, interrupted: &mut dyn FnMut() -> bool) -> String {
+        for i in 0..count {
+            if i % INTERRUPT_CHECK == INTERRUPT_CHECK - 1 && interrupted() {
+                return stop(SIGINT);
+            }
+            let pc = self.chip.pc;
+            let hit = self.watchpoint_hit();
+            self.chip.execute_cycle();
+            if let Some((kind, address)) = hit {
+                let name = match kind {
+                    Watch::Write => "watch",
+                    Watch::Read => "rwatch",
+                    Watch::Access => "awatch",
+                };
+                return format!("T{:02x}{}:{:04x};", SIGTRAP, name, address);
+            }
+            // a trap (`jmp *` or a branch to itself) stops too
+            if self.chip.pc == pc || self.breakpoints.contains(&self.chip.pc) {
+                break;
+            }
+        }
+        stop(SIGTRAP)
+    }
+
+    /// The watchpoint that the instruction at PC is going to hit
+    fn watchpoint_hit(&self) -> Option<(Watch, u16)> {
+        if self.watchpoints.is_empty() {
+            return None;
+        }
+        let (address, reads, writes) = data_access(self.chip)?;
+        self.watchpoints
+            .iter()
+            .find(|w| {
+                w.covers(address)
+                    && match w.kind {
+                        Watch::Write => writes,
+                        Watch::Read => reads,
+                        Watch::Access => reads || writes,
+                    }
+            })
+            .map(|w| (w.kind, address))
+    }
+}
+
+/// Waits for a debugger on the address (like `127.0.0.1:1234`)
+/// and lets it control the chip until it detaches
+pub fn listen(chip: &mut Chip, address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    let (stream, _) = listener.accept()?;
+    Stub::new(chip).serve(stream)
+}
+
+/// `$<data>#<checksum>`
+pub fn frame(data: &str) -> String {
+    format!("${}#{:02x}", data, checksum(data.as_bytes()))
+}
+
+/// The sum of the bytes, modulo 256
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
+
+// =====================
+// Helper functions
+// =====================
+
+fn stop(signal: u8) -> String {
+    format!("S{:02x}", signal)
+}
+
+/// The address of the memory that the instruction at PC reads or writes
+/// (the stack and the jumps do not count), if it reads and if it writes
+fn data_access(c: &Chip) -> Option<(u16, bool, bool)> {
+    let metadata = opcodes::metadata(c.memory[c.pc as usize]);
+    if !metadata.documented {
+        return None;
+    }
+    let bytes = [
+        c.memory[c.pc.wrapping_add(1) as usize],
+        c.memory[c.pc.wrapping_add(2) as usize],
+    ];
+    let word = |address: u16| {
+        u16::from_le_bytes([
+            c.memory[address as usize],
+            c.memory[address.wrapping_add(1) as usize],
+        ])
+    };
+    let address = match Operand::from_bytes(metadata.mode, &bytes)? {
+        Operand::Zeropage(ll) => ll as u16,
+        Operand::ZeropageX(ll) => ll.wrapping_add(c.rx) as u16,
+        Operand::ZeropageY(ll) => ll.wrapping_add(c.ry) as u16,
+        Operand::Absolute(address) => address,
+        Operand::AbsoluteX(address) => address.wrapping_add(c.rx as u16),
+        Operand::AbsoluteY(address) => address.wrapping_add(c.ry as u16),
+        Operand::XIndirect(ll) => word(ll.wrapping_add(c.rx) as u16),
+        Operand::IndirectY(ll) => word(ll as u16).wrapping_add(c.ry as u16),
+        _ => return None,
+    };
+    match metadata.mnemonic {
+        "JMP" | "JSR" => None,
+        "STA" | "STX" | "STY" => Some((address, false, true)),
+        "ASL" | "LSR" | "ROL" | "ROR" | "INC" | "DEC" => Some((address, true, true)),
+        _ => Some((address, true, false)),
+    }
+}
+
+enum Packet {
+    Data(String),
+    Interrupt,
+    // The checksum is wrong
+    Invalid,
+}
+
+/// The connection to the debugger, with the bytes that arrived
+/// while looking for a ^C
+struct Connection {
+    stream: TcpStream,
+    pending: VecDeque<u8>,
+}
+
+impl Connection {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.stream.write_all(text.as_bytes())
+    }
+
+    /// The next byte, `None` at the end of the connection
+    fn byte(&mut self) -> io::Result<Option<u8>> {
+        if let Some(byte) = self.pending.pop_front() {
+            return Ok(Some(byte));
+        }
+        let mut byte = [0u8];
+        if self.stream.read(&mut byte)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(byte[0]))
+    }
+
+    /// Reads the next packet, `None` at the end of the connection
+    fn read_packet(&mut self) -> io::Result<Option<Packet>> {
+        // everything before the `$` (acks) is skipped
+        loop {
+            match self.byte()? {
+                None => return Ok(None),
+                Some(b'$') => break,
+                Some(0x03) => return Ok(Some(Packet::Interrupt)),
+                Some(_) => {}
+            }
+        }
+        let mut data = Vec::new();
+        let mut too_long = false;
+        loop {
+            match self.byte()? {
+                None => return Ok(None),
+                Some(b'#') => break,
+                // the rest of a packet that is too long is dropped
+                Some(_) if data.len() == PACKET_SIZE => too_long = true,
+                Some(byte) => data.push(byte),
+            }
+        }
+        let mut sum = [0u8; 2];
+        for digit in &mut sum {
+            match self.byte()? {
+                Some(byte) => *digit = byte,
+                None => return Ok(None),
+            }
+        }
+        let expected = std::str::from_utf8(&sum)
+            .ok()
+            .and_then(|text| u8::from_str_radix(text, 16).ok());
+        if too_long || expected != Some(checksum(&data)) {
+            return Ok(Some(Packet::Invalid));
+        }
+        Ok(Some(Packet::Data(unescape(&data))))
+    }
+
+    /// If a ^C is waiting on the connection (a closed connection counts
+    /// too), the other bytes that are waiting are kept for `byte`
+    fn interrupt_pending(&mut self) -> bool {
+        if self.stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let mut buffer = [0u8; 256];
+        let interrupted = match self.stream.read(&mut buffer) {
+            Ok(0) => true,
+            Ok(count) => {
+                let bytes = &buffer[..count];
+                self.pending.extend(bytes.iter().filter(|b| **b != 0x03));
+                bytes.contains(&0x03)
+            }
+            Err(_) => false,
+        };
+        self.stream.set_nonblocking(false).ok();
+        interrupted
+    }
+}
+
+/// Undoes the `}` escapes (the byte after `}` is XORed with 0x20)
+fn unescape(data: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(data.len());
+    let mut escaped = false;
+    for &byte in data {
+        if escaped {
+            bytes.push(byte ^ 0x20);
+            escaped = false;
+        } else if byte == b'}' {
+            escaped = true;
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+fn parse_hex(text: &str) -> Option<usize> {
+    usize::from_str_radix(text, 16).ok()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// `<address>,<length>`
+fn address_and_length_unchecked(text: &str) -> Option<(usize, usize)> {
+    let (address, length) = text.split_once(',')?;
+    Some((parse_hex(address)?, parse_hex(length)?))
+}
+
+/// `<address>,<length>` that is inside of the memory
+fn address_and_length(text: &str) -> Option<(usize, usize)> {
+    let (address, length) = address_and_length_unchecked(text)?;
+    if address.checked_add(length)? > MEMORY {
+        return None;
+    }
+    Some((address, length))
+}
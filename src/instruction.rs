@@ -0,0 +1,341 @@
+// Instructions with a typed operand
+//
+// An instruction is its mnemonic and the operand of its addressing mode:
+//
+// LDA #$01    Instruction { mnemonic: Mnemonic::Lda, operand: Operand::Immediate(0x01) }
+// STA $10,X   Instruction { mnemonic: Mnemonic::Sta, operand: Operand::ZeropageX(0x10) }
+// BNE *-3     Instruction { mnemonic: Mnemonic::Bne, operand: Operand::Relative(-5) }
+//
+// They are decoded from and encoded to bytes with the opcode tables of
+// the disassembler (only the documented opcodes) and run with
+// `Chip::execute`.
+//
+// Branches are written relative to the address of the instruction
+// (`*`), like as65 does.
+
+use std::fmt;
+
+pub use crate::chip::Cpu;
+use crate::disassembler::opcode;
+pub use crate::disassembler::Mode;
+
+/// The mnemonics of the documented instructions of both CPUs
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Mnemonic {
+    Adc,
+    And,
+    Asl,
+    Bcc,
+    Bcs,
+    Beq,
+    Bit,
+    Bmi,
+    Bne,
+    Bpl,
+    Brk,
+    Bvc,
+    Bvs,
+    Clc,
+    Cld,
+    Cli,
+    Clv,
+    Cmp,
+    Cpx,
+    Cpy,
+    Dec,
+    Dex,
+    Dey,
+    Eor,
+    Inc,
+    Inx,
+    Iny,
+    Jmp,
+    Jsr,
+    Lda,
+    Ldx,
+    Ldy,
+    Lsr,
+    Nop,
+    Ora,
+    Pha,
+    Php,
+    Pla,
+    Plp,
+    Rol,
+    Ror,
+    Rti,
+    Rts,
+    Sbc,
+    Sec,
+    Sed,
+    Sei,
+    Sta,
+    Stx,
+    Sty,
+    Tax,
+    Tay,
+    Tsx,
+    Txa,
+    Txs,
+    Tya,
+    // 65C02:
+    Bra,
+    Phx,
+    Phy,
+    Plx,
+    Ply,
+    Stp,
+    Stz,
+    Trb,
+    Tsb,
+    Wai,
+    // 65C02 with the number of the bit:
+    Bbr(u8),
+    Bbs(u8),
+    Rmb(u8),
+    Smb(u8),
+}
+
+#[rustfmt::skip]
+const NAMES: [(&str, Mnemonic); 66] = [
+    ("ADC", Mnemonic::Adc), ("AND", Mnemonic::And), ("ASL", Mnemonic::Asl), ("BCC", Mnemonic::Bcc),
+    ("BCS", Mnemonic::Bcs), ("BEQ", Mnemonic::Beq), ("BIT", Mnemonic::Bit), ("BMI", Mnemonic::Bmi),
+    ("BNE", Mnemonic::Bne), ("BPL", Mnemonic::Bpl), ("BRK", Mnemonic::Brk), ("BVC", Mnemonic::Bvc),
+    ("BVS", Mnemonic::Bvs), ("CLC", Mnemonic::Clc), ("CLD", Mnemonic::Cld), ("CLI", Mnemonic::Cli),
+    ("CLV", Mnemonic::Clv), ("CMP", Mnemonic::Cmp), ("CPX", Mnemonic::Cpx), ("CPY", Mnemonic::Cpy),
+    ("DEC", Mnemonic::Dec), ("DEX", Mnemonic::Dex), ("DEY", Mnemonic::Dey), ("EOR", Mnemonic::Eor),
+    ("INC", Mnemonic::Inc), ("INX", Mnemonic::Inx), ("INY", Mnemonic::Iny), ("JMP", Mnemonic::Jmp),
+    ("JSR", Mnemonic::Jsr), ("LDA", Mnemonic::Lda), ("LDX", Mnemonic::Ldx), ("LDY", Mnemonic::Ldy),
+    ("LSR", Mnemonic::Lsr), ("NOP", Mnemonic::Nop), ("ORA", Mnemonic::Ora), ("PHA", Mnemonic::Pha),
+    ("PHP", Mnemonic::Php), ("PLA", Mnemonic::Pla), ("PLP", Mnemonic::Plp), ("ROL", Mnemonic::Rol),
+    ("ROR", Mnemonic::Ror), ("RTI", Mnemonic::Rti), ("RTS", Mnemonic::Rts), ("SBC", Mnemonic::Sbc),
+    ("SEC", Mnemonic::Sec), ("SED", Mnemonic::Sed), ("SEI", Mnemonic::Sei), ("STA", Mnemonic::Sta),
+    ("STX", Mnemonic::Stx), ("STY", Mnemonic::Sty), ("TAX", Mnemonic::Tax), ("TAY", Mnemonic::Tay),
+    ("TSX", Mnemonic::Tsx), ("TXA", Mnemonic::Txa), ("TXS", Mnemonic::Txs), ("TYA", Mnemonic::Tya),
+    ("BRA", Mnemonic::Bra), ("PHX", Mnemonic::Phx), ("PHY", Mnemonic::Phy), ("PLX", Mnemonic::Plx),
+    ("PLY", Mnemonic::Ply), ("STP", Mnemonic::Stp), ("STZ", Mnemonic::Stz), ("TRB", Mnemonic::Trb),
+    ("TSB", Mnemonic::Tsb), ("WAI", Mnemonic::Wai),
+];
+
+impl Mnemonic {
+    /// The mnemonic of a documented instruction (in any case),
+    /// `None` for the undocumented ones
+    pub fn parse(text: &str) -> Option<Mnemonic> {
+        let text = text.to_ascii_uppercase();
+        if let Some((_, mnemonic)) = NAMES.iter().find(|(name, _)| *name == text) {
+            return Some(*mnemonic);
+        }
+        if text.len() != 4 {
+            return None;
+        }
+        let bit = text[3..].parse::<u8>().ok().filter(|bit| *bit < 8)?;
+        match &text[..3] {
+            "BBR" => Some(Mnemonic::Bbr(bit)),
+            "BBS" => Some(Mnemonic::Bbs(bit)),
+            "RMB" => Some(Mnemonic::Rmb(bit)),
+            "SMB" => Some(Mnemonic::Smb(bit)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    /// `LDA`, `BBR3`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mnemonic::Bbr(bit) => write!(f, "BBR{}", bit),
+            Mnemonic::Bbs(bit) => write!(f, "BBS{}", bit),
+            Mnemonic::Rmb(bit) => write!(f, "RMB{}", bit),
+            Mnemonic::Smb(bit) => write!(f, "SMB{}", bit),
+            mnemonic => {
+                let (name, _) = NAMES.iter().find(|(_, m)| m == mnemonic).unwrap();
+                write!(f, "{}", name)
+            }
+        }
+    }
+}
+
+/// The operand of an instruction
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Operand {
+    Implied,
+    Accumulator,
+    Immediate(u8),
+    Zeropage(u8),
+    ZeropageX(u8),
+    ZeropageY(u8),
+    Absolute(u16),
+    AbsoluteX(u16),
+    AbsoluteY(u16),
+    Indirect(u16),
+    XIndirect(u8),
+    IndirectY(u8),
+    // The offset from the end of the instruction:
+    Relative(i8),
+    // 65C02:
+    ZeropageIndirect(u8),
+    AbsoluteXIndirect(u16),
+    // The zeropage address and the offset (BBR and BBS):
+    ZeropageRelative(u8, i8),
+}
+
+impl Operand {
+    /// The operand of the mode from the bytes after the opcode
+    /// (`None` if there are not enough bytes)
+    pub fn from_bytes(mode: Mode, bytes: &[u8]) -> Option<Operand> {
+        if bytes.len() < mode.length() - 1 {
+            return None;
+        }
+        let byte = || bytes[0];
+        let word = || u16::from_le_bytes([bytes[0], bytes[1]]);
+        Some(match mode {
+            Mode::Implied => Operand::Implied,
+            Mode::Accumulator => Operand::Accumulator,
+            Mode::Immediate => Operand::Immediate(byte()),
+            Mode::Zeropage => Operand::Zeropage(byte()),
+            Mode::ZeropageX => Operand::ZeropageX(byte()),
+            Mode::ZeropageY => Operand::ZeropageY(byte()),
+            Mode::Absolute => Operand::Absolute(word()),
+            Mode::AbsoluteX => Operand::AbsoluteX(word()),
+            Mode::AbsoluteY => Operand::AbsoluteY(word()),
+            Mode::Indirect => Operand::Indirect(word()),
+            Mode::XIndirect => Operand::XIndirect(byte()),
+            Mode::IndirectY => Operand::IndirectY(byte()),
+            Mode::Relative => Operand::Relative(byte() as i8),
+            Mode::ZeropageIndirect => Operand::ZeropageIndirect(byte()),
+            Mode::AbsoluteXIndirect => Operand::AbsoluteXIndirect(word()),
+            Mode::ZeropageRelative => Operand::ZeropageRelative(bytes[0], bytes[1] as i8),
+        })
+    }
+
+    pub fn mode(&self) -> Mode {
+        match self {
+            Operand::Implied => Mode::Implied,
+            Operand::Accumulator => Mode::Accumulator,
+            Operand::Immediate(_) => Mode::Immediate,
+            Operand::Zeropage(_) => Mode::Zeropage,
+            Operand::ZeropageX(_) => Mode::ZeropageX,
+            Operand::ZeropageY(_) => Mode::ZeropageY,
+            Operand::Absolute(_) => Mode::Absolute,
+            Operand::AbsoluteX(_) => Mode::AbsoluteX,
+            Operand::AbsoluteY(_) => Mode::AbsoluteY,
+            Operand::Indirect(_) => Mode::Indirect,
+            Operand::XIndirect(_) => Mode::XIndirect,
+            Operand::IndirectY(_) => Mode::IndirectY,
+            Operand::Relative(_) => Mode::Relative,
+            Operand::ZeropageIndirect(_) => Mode::ZeropageIndirect,
+            Operand::AbsoluteXIndirect(_) => Mode::AbsoluteXIndirect,
+            Operand::ZeropageRelative(_, _) => Mode::ZeropageRelative,
+        }
+    }
+
+    /// The bytes after the opcode
+    pub fn bytes(&self) -> Vec<u8> {
+        match *self {
+            Operand::Implied | Operand::Accumulator => Vec::new(),
+            Operand::Immediate(byte)
+            | Operand::Zeropage(byte)
+            | Operand::ZeropageX(byte)
+            | Operand::ZeropageY(byte)
+            | Operand::XIndirect(byte)
+            | Operand::IndirectY(byte)
+            | Operand::ZeropageIndirect(byte) => [byte].to_vec(),
+            Operand::Relative(offset) => [offset as u8].to_vec(),
+            Operand::Absolute(word)
+            | Operand::AbsoluteX(word)
+            | Operand::AbsoluteY(word)
+            | Operand::Indirect(word)
+            | Operand::AbsoluteXIndirect(word) => word.to_le_bytes().to_vec(),
+            Operand::ZeropageRelative(byte, offset) => [byte, offset as u8].to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    /// Like the disassembler writes it, but branches are relative (`*+4`)
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operand::Implied => Ok(()),
+            Operand::Accumulator => write!(f, "A"),
+            Operand::Immediate(byte) => write!(f, "#${:02X}", byte),
+            Operand::Zeropage(byte) => write!(f, "${:02X}", byte),
+            Operand::ZeropageX(byte) => write!(f, "${:02X},X", byte),
+            Operand::ZeropageY(byte) => write!(f, "${:02X},Y", byte),
+            Operand::Absolute(word) => write!(f, "${:04X}", word),
+            Operand::AbsoluteX(word) => write!(f, "${:04X},X", word),
+            Operand::AbsoluteY(word) => write!(f, "${:04X},Y", word),
+            Operand::Indirect(word) => write!(f, "(${:04X})", word),
+            Operand::XIndirect(byte) => write!(f, "(${:02X},X)", byte),
+            Operand::IndirectY(byte) => write!(f, "(${:02X}),Y", byte),
+            Operand::Relative(offset) => write!(f, "{}", relative(offset, 2)),
+            Operand::ZeropageIndirect(byte) => write!(f, "(${:02X})", byte),
+            Operand::AbsoluteXIndirect(word) => write!(f, "(${:04X},X)", word),
+            Operand::ZeropageRelative(byte, offset) => {
+                write!(f, "${:02X},{}", byte, relative(offset, 3))
+            }
+        }
+    }
+}
+
+/// A branch target as `*`, `*+n` or `*-n`
+fn relative(offset: i8, length: i16) -> String {
+    let distance = offset as i16 + length;
+    match distance {
+        0 => "*".to_string(),
+        d if d > 0 => format!("*+{}", d),
+        d => format!("*-{}", -d),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Instruction {
+    pub mnemonic: Mnemonic,
+    pub operand: Operand,
+}
+
+impl Instruction {
+    /// Decodes the instruction at the start of the bytes, `None` if the
+    /// opcode is not documented or the bytes end before the operand
+    pub fn decode(cpu: Cpu, bytes: &[u8]) -> Option<Instruction> {
+        let op = opcode(cpu, *bytes.first()?);
+        if !op.documented {
+            return None;
+        }
+        Some(Instruction {
+            mnemonic: Mnemonic::parse(op.mnemonic)?,
+            operand: Operand::from_bytes(op.mode, &bytes[1..])?,
+        })
+    }
+
+    /// The opcode, `None` if the CPU does not have the mnemonic
+    /// with this addressing mode
+    pub fn opcode(&self, cpu: Cpu) -> Option<u8> {
+        let mode = self.operand.mode();
+        (0..=255).find(|byte| {
+            let op = opcode(cpu, *byte);
+            op.documented && op.mode == mode && Mnemonic::parse(op.mnemonic) == Some(self.mnemonic)
+        })
+    }
+
+    /// The opcode followed by the operand
+    pub fn encode(&self, cpu: Cpu) -> Option<Vec<u8>> {
+        let mut bytes = [self.opcode(cpu)?].to_vec();
+        bytes.extend_from_slice(&self.operand.bytes());
+        Some(bytes)
+    }
+
+    /// The number of bytes of the instruction
+    pub fn length(&self) -> usize {
+        self.operand.mode().length()
+    }
+}
+
+impl fmt::Display for Instruction {
+    /// `LDA #$01`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.operand {
+            Operand::Implied => write!(f, "{}", self.mnemonic),
+            operand => write!(f, "{} {}", self.mnemonic, operand),
+        }
+    }
+}
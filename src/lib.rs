@@ -1,17 +1,58 @@
 use chip::Chip;
+use listing::Listing;
+use loader::LoadError;
+use paravirt::Paravirt;
 
+pub mod assembler;
 pub mod chip;
+pub mod dap;
+pub mod debug_info;
+pub mod disassembler;
+pub mod dump;
+pub mod gdb;
+pub mod instruction;
+pub mod listing;
+pub mod loader;
+pub mod monitor;
+pub mod opcodes;
+pub mod paravirt;
+pub mod power_on;
+pub mod program;
+pub mod snapshot;
+pub mod symbols;
+pub mod tui;
 
-pub fn run_testprogramm() {
+/// Runs the functional test until it traps and returns the chip
+/// (where it trapped goes to stderr, stdout is for the dump)
+pub fn run_testprogramm() -> Chip {
     let mut c = Chip::new();
     c.load_exe("bin/6502_functional_test.bin".to_string(), 0x000A)
         .unwrap();
     c.load_program([].to_vec());
     c.pc = 0x400; // 1024
 
+    // the test traps with `jmp *` or a branch to itself
     loop {
+        let pc = c.pc;
         c.execute_cycle();
+        if c.pc == pc {
+            break;
+        }
     }
+
+    match Listing::load("bin/6502_functional_test.lst".to_string()) {
+        Ok(listing) => {
+            match listing.describe(c.pc) {
+                Some(text) => eprintln!("trapped at {}", text),
+                None => eprintln!("trapped at ${:04X}", c.pc),
+            }
+            if let Some(test) = listing.value_before(c.pc, "test_num") {
+                eprintln!("test case {}", test);
+            }
+        }
+        Err(e) => eprintln!("trapped at ${:04X} ({})", c.pc, e),
+    }
+    c
 }
 
 pub fn run() {
@@ -19,3 +60,21 @@ pub fn run() {
 
     c.load_program([0x80].to_vec());
 }
+
+/// Runs a cc65 program built for sim6502 like sim65 does
+/// and returns its exit code and the chip.
+///
+/// `args` are the arguments of the program, starting with its name.
+/// The exit code is `None` if the program did not exit within `max`
+/// instructions.
+pub fn run_sim65(
+    file_path: String,
+    args: Vec<String>,
+    max: u64,
+) -> Result<(Option<u8>, Chip), LoadError> {
+    let mut c = Chip::new();
+    let header = c.load_sim65(file_path)?;
+    let mut pv = Paravirt::new(header.sp_address, args);
+    let code = pv.run(&mut c, max);
+    Ok((code, c))
+}
@@ -0,0 +1,260 @@
+// as65 listing files (`as65 -l -m -w -h0`)
+//
+// Every line of the listing has a fixed layout:
+//
+// 0400 : d8               start   cld
+// 0003 =                  I_flag = 3
+// 0412 : 4c1204          >        jmp *           ;failed anyway
+// |      |               ||
+// |      |               |+- column 24: the source line
+// |      |               +-- column 23: `>` for lines of a macro expansion
+// |      +------------------ the bytes of the line (hex)
+// +------------------------- the address (`:`) or value (`=`) of the line
+//
+// Lines without code start with 24 spaces. Every macro expansion
+// is followed by an empty line that is not part of the source.
+
+use std::collections::BTreeMap;
+
+use crate::loader::LoadError;
+use crate::symbols::SymbolTable;
+
+/// A line of the listing
+#[derive(Debug, PartialEq, Clone)]
+pub struct ListingLine {
+    // Line in the listing file:
+    pub listing_line: usize,
+    // Line in the source file (None for lines of a macro expansion):
+    pub source_line: Option<usize>,
+    // Where the bytes of the line are:
+    pub address: Option<u16>,
+    pub bytes: Vec<u8>,
+    // The value of an `=` or `equ` line:
+    pub value: Option<u16>,
+    pub macro_line: bool,
+    pub text: String,
+}
+
+/// A parsed listing
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Listing {
+    // The source file named in the header:
+    pub source: Option<String>,
+    pub lines: Vec<ListingLine>,
+    // start address -> index into lines
+    by_address: BTreeMap<u16, usize>,
+    labels: SymbolTable,
+}
+
+impl Listing {
+    /// Parses the text of a listing, lines that are not part of
+    /// the listing (headers, summaries) are skipped
+    pub fn parse(text: &str) -> Listing {
+        let mut listing = Listing::default();
+        let mut source_line = 0;
+        let mut after_macro = false;
+
+        for (i, raw) in text.lines().enumerate() {
+            let raw = raw.trim_end_matches('\r');
+            if listing.source.is_none() && raw.starts_with("---") {
+                let name = raw.trim_matches(|c: char| c == '-' || c.is_whitespace());
+                listing.source = Some(name.to_string());
+                continue;
+            }
+            let Prefix {
+                address,
+                value,
+                bytes,
+            } = match parse_prefix(raw) {
+                Some(prefix) => prefix,
+                None => continue,
+            };
+            let macro_line = raw.as_bytes().get(23) == Some(&b'>');
+            let text = raw.get(24..).unwrap_or("").trim_end().to_string();
+
+            // the empty line that ends a macro expansion
+            if after_macro && !macro_line && address.is_none() && text.is_empty() {
+                after_macro = false;
+                continue;
+            }
+            after_macro = macro_line;
+
+            let source = if macro_line {
+                None
+            } else {
+                source_line += 1;
+                Some(source_line)
+            };
+            if let (Some(address), false) = (address, bytes.is_empty()) {
+                listing.by_address.insert(address, listing.lines.len());
+            }
+            listing.lines.push(ListingLine {
+                listing_line: i + 1,
+                source_line: source,
+                address,
+                bytes,
+                value,
+                macro_line,
+                text,
+            });
+        }
+        listing.labels = find_labels(&listing.lines);
+        listing
+    }
+
+    /// Loads a listing file
+    pub fn load(file_path: String) -> Result<Listing, LoadError> {
+        let bytes = std::fs::read(file_path)?;
+        Ok(Listing::parse(&String::from_utf8_lossy(&bytes)))
+    }
+
+    /// The line that produced the byte at the address
+    pub fn line_at(&self, address: u16) -> Option<&ListingLine> {
+        Some(&self.lines[self.index_at(address)?])
+    }
+
+    /// The source line that produced the byte at the address,
+    /// for code of a macro this is the line that used the macro
+    pub fn source_line_at(&self, address: u16) -> Option<&ListingLine> {
+        let index = self.index_at(address)?;
+        self.lines[..=index].iter().rev().find(|l| !l.macro_line)
+    }
+
+    /// The addresses of the code of a source line (and its macro expansion)
+    pub fn addresses_of(&self, source_line: usize) -> Vec<u16> {
+        let start = match self
+            .lines
+            .iter()
+            .position(|l| l.source_line == Some(source_line))
+        {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+        self.lines[start..]
+            .iter()
+            .enumerate()
+            .take_while(|(i, l)| *i == 0 || l.macro_line)
+            .filter(|(_, l)| !l.bytes.is_empty())
+            .filter_map(|(_, l)| l.address)
+            .collect()
+    }
+
+    /// The value that was last assigned to `name` before the line
+    /// that produced the byte at the address (like `test_num` in
+    /// the functional test)
+    pub fn value_before(&self, address: u16, name: &str) -> Option<u16> {
+        let index = self.index_at(address)?;
+        self.lines[..=index]
+            .iter()
+            .rev()
+            .filter(|l| l.value.is_some())
+            .find(|l| {
+                let text = l.text.trim_start();
+                text.strip_prefix(name)
+                    .map(|rest| rest.starts_with([' ', '\t', '=']))
+                    .unwrap_or(false)
+            })
+            .and_then(|l| l.value)
+    }
+
+    /// All labels (names in the first column of lines with code)
+    pub fn labels(&self) -> &SymbolTable {
+        &self.labels
+    }
+
+    /// Describes the code at the address: the source line, and the
+    /// line of the macro that produced it
+    pub fn describe(&self, address: u16) -> Option<String> {
+        let line = self.line_at(address)?;
+        let source = self.source_line_at(address)?;
+        let mut text = format!(
+            "${:04X} {}, line {}",
+            address,
+            self.labels().format_address(address),
+            source.source_line.unwrap_or(0)
+        );
+        if let Some(file) = &self.source {
+            text.push_str(&format!(" of {}", file));
+        }
+        text.push_str(&format!("\n  {}", source.text));
+        if line.macro_line {
+            text.push_str(&format!("\n> {}", line.text));
+        }
+        Some(text)
+    }
+
+    /// The index of the line that produced the byte at the address
+    fn index_at(&self, address: u16) -> Option<usize> {
+        let (_, index) = self.by_address.range(..=address).next_back()?;
+        let line = &self.lines[*index];
+        let start = line.address? as usize;
+        if (address as usize) < start + line.bytes.len() {
+            Some(*index)
+        } else {
+            None
+        }
+    }
+}
+
+/// The names in the first column of lines with code
+fn find_labels(lines: &[ListingLine]) -> SymbolTable {
+    let mut table = SymbolTable::new();
+    for line in lines {
+        let address = match (line.address, line.value) {
+            (Some(address), None) => address,
+            _ => continue,
+        };
+        let name = match line.text.split_whitespace().next() {
+            Some(name) if !line.text.starts_with(char::is_whitespace) => name,
+            _ => continue,
+        };
+        let name = name.trim_end_matches(':');
+        if name.starts_with(';') || name.contains('\\') || name.is_empty() {
+            continue;
+        }
+        if table.get(name).is_none() {
+            table.insert(name, address);
+        }
+    }
+    table
+}
+
+/// The first 24 columns of a line
+struct Prefix {
+    address: Option<u16>,
+    value: Option<u16>,
+    bytes: Vec<u8>,
+}
+
+/// Parses the first 24 columns of a line,
+/// returns `None` if it is not a line of the listing
+fn parse_prefix(raw: &str) -> Option<Prefix> {
+    if raw.starts_with("                       ") {
+        return Some(Prefix {
+            address: None,
+            value: None,
+            bytes: Vec::new(),
+        });
+    }
+    let number = u16::from_str_radix(raw.get(0..4)?, 16).ok()?;
+    match raw.get(4..7)? {
+        " : " => {
+            let hex = raw.get(7..23).or_else(|| raw.get(7..))?.trim();
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+                .collect::<Option<Vec<u8>>>()?;
+            Some(Prefix {
+                address: Some(number),
+                value: None,
+                bytes,
+            })
+        }
+        " = " => Some(Prefix {
+            address: None,
+            value: Some(number),
+            bytes: Vec::new(),
+        }),
+        _ => None,
+    }
+}
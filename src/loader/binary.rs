@@ -0,0 +1,43 @@
+// Raw binary files
+//
+// There is no header, so where the data goes has to be given.
+// Optionally only a part of the file (from an offset, with a length)
+// is used.
+
+use super::{check_range, Image, LoadError};
+
+/// Creates an image out of (a part of) a raw binary
+pub fn parse(
+    bytes: &[u8],
+    address: u16,
+    offset: usize,
+    length: Option<usize>,
+) -> Result<Image, LoadError> {
+    if offset > bytes.len() {
+        return Err(LoadError::Format(format!(
+            "offset {} is behind the end of the file ({} bytes)",
+            offset,
+            bytes.len()
+        )));
+    }
+    let rest = &bytes[offset..];
+    let data = match length {
+        Some(length) if length > rest.len() => {
+            return Err(LoadError::Format(format!(
+                "{} bytes requested but only {} bytes are left after offset {}",
+                length,
+                rest.len(),
+                offset
+            )))
+        }
+        Some(length) => &rest[..length],
+        None => rest,
+    };
+    check_range(address as u32, data.len())?;
+
+    let mut image = Image::default();
+    if !data.is_empty() {
+        image.push(address, data);
+    }
+    Ok(image)
+}
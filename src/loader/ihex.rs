@@ -0,0 +1,146 @@
+// Intel HEX
+//
+// Every line is a record:
+// `:LLAAAATT<data>CC`
+// LL   ... number of data bytes
+// AAAA ... 16-bit address (big endian)
+// TT   ... record type
+// CC   ... two's complement of the sum of all the other bytes
+//
+// Reference: https://en.wikipedia.org/wiki/Intel_HEX
+
+use super::{check_record_range, hex_bytes, Image, LoadError};
+
+// The record types
+const DATA: u8 = 0x00;
+const END_OF_FILE: u8 = 0x01;
+const EXTENDED_SEGMENT_ADDRESS: u8 = 0x02;
+const START_SEGMENT_ADDRESS: u8 = 0x03;
+const EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+const START_LINEAR_ADDRESS: u8 = 0x05;
+
+/// Parses the text of an Intel HEX file
+pub fn parse(text: &str) -> Result<Image, LoadError> {
+    let mut image = Image::default();
+    // The base address set by the extended address records
+    let mut base: u32 = 0;
+    let mut last_line = 0;
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        last_line = line;
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+        let record = match raw.strip_prefix(':') {
+            Some(record) => record,
+            None => {
+                return Err(LoadError::Syntax {
+                    line,
+                    message: "record does not start with `:`".to_string(),
+                })
+            }
+        };
+        let bytes = hex_bytes(record, line)?;
+        if bytes.len() < 5 {
+            return Err(LoadError::Syntax {
+                line,
+                message: "record is too short".to_string(),
+            });
+        }
+        let count = bytes[0] as usize;
+        if bytes.len() != count + 5 {
+            return Err(LoadError::Syntax {
+                line,
+                message: format!(
+                    "record says {} data bytes but has {}",
+                    count,
+                    bytes.len() - 5
+                ),
+            });
+        }
+
+        let (body, checksum) = bytes.split_at(bytes.len() - 1);
+        let expected = body
+            .iter()
+            .fold(0u8, |sum, b| sum.wrapping_add(*b))
+            .wrapping_neg();
+        if expected != checksum[0] {
+            return Err(LoadError::Checksum {
+                line,
+                expected,
+                found: checksum[0],
+            });
+        }
+
+        let offset = ((bytes[1] as u32) << 8) | bytes[2] as u32;
+        let data = &body[4..];
+        match bytes[3] {
+            DATA => {
+                let address = base + offset;
+                check_record_range(address, data.len(), line)?;
+                image.push(address as u16, data);
+            }
+            END_OF_FILE => return Ok(image),
+            EXTENDED_SEGMENT_ADDRESS => {
+                base = (word(data, line)? as u32) << 4;
+            }
+            EXTENDED_LINEAR_ADDRESS => {
+                base = (word(data, line)? as u32) << 16;
+            }
+            START_SEGMENT_ADDRESS => {
+                // CS:IP like on the 8086
+                let [cs_hh, cs_ll, ip_hh, ip_ll] = start_bytes(data, line)?;
+                let cs = u16::from_be_bytes([cs_hh, cs_ll]) as u32;
+                let ip = u16::from_be_bytes([ip_hh, ip_ll]) as u32;
+                image.start = Some(start_address((cs << 4) + ip, line)?);
+            }
+            START_LINEAR_ADDRESS => {
+                let address = u32::from_be_bytes(start_bytes(data, line)?);
+                image.start = Some(start_address(address, line)?);
+            }
+            kind => {
+                return Err(LoadError::Syntax {
+                    line,
+                    message: format!("unknown record type {:02X}", kind),
+                })
+            }
+        }
+    }
+
+    Err(LoadError::Syntax {
+        line: last_line,
+        message: "missing end of file record".to_string(),
+    })
+}
+
+/// Reads the big endian word that is the whole data of an address record
+fn word(data: &[u8], line: usize) -> Result<u16, LoadError> {
+    if data.len() != 2 {
+        return Err(LoadError::Syntax {
+            line,
+            message: "address record needs 2 data bytes".to_string(),
+        });
+    }
+    Ok(((data[0] as u16) << 8) | data[1] as u16)
+}
+
+/// Reads the 4 data bytes of a start address record
+fn start_bytes(data: &[u8], line: usize) -> Result<[u8; 4], LoadError> {
+    data.try_into().map_err(|_| LoadError::Syntax {
+        line,
+        message: "start address record needs 4 data bytes".to_string(),
+    })
+}
+
+/// Checks that a start address is reachable by the 6502
+fn start_address(address: u32, line: usize) -> Result<u16, LoadError> {
+    if address > 0xFFFF {
+        return Err(LoadError::Syntax {
+            line,
+            message: format!("start address ${:X} is outside of the memory", address),
+        });
+    }
+    Ok(address as u16)
+}
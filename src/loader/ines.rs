@@ -0,0 +1,153 @@
+// iNES and NES 2.0 ROM files
+//
+// A 16 byte header, an optional 512 byte trainer,
+// the PRG-ROM (program) and the CHR-ROM (graphics).
+//
+// Byte 0-3 ... "NES" and $1A
+// Byte 4   ... PRG-ROM size in 16K units
+// Byte 5   ... CHR-ROM size in 8K units
+// Byte 6   ... flags: mirroring, battery, trainer, four screen, mapper (low nibble)
+// Byte 7   ... flags: NES 2.0 identifier, mapper (high nibble)
+// Byte 8   ... NES 2.0: mapper (bits 8-11) and submapper
+// Byte 9   ... NES 2.0: upper bits of the PRG-ROM and CHR-ROM sizes
+//
+// Only NROM (mapper 0) can be mapped into the memory of the chip,
+// everything else needs bank switching hardware.
+//
+// Reference: https://www.nesdev.org/wiki/INES
+// Reference: https://www.nesdev.org/wiki/NES_2.0
+
+use super::{Image, LoadError};
+
+const MAGIC: [u8; 4] = [b'N', b'E', b'S', 0x1A];
+const HEADER: usize = 16;
+const TRAINER: usize = 512;
+const PRG_UNIT: usize = 16 * 1024;
+const CHR_UNIT: usize = 8 * 1024;
+
+/// Where the PRG-ROM of NROM starts
+pub const PRG_START: u16 = 0x8000;
+
+/// Where nestest.nes has to be started to run without a PPU
+/// (instead of at its reset vector)
+pub const NESTEST_START: u16 = 0xC000;
+
+/// A parsed NES ROM
+#[derive(Debug, PartialEq, Clone)]
+pub struct Rom {
+    pub mapper: u16,
+    pub submapper: u8,
+    // The header is a NES 2.0 header:
+    pub nes2: bool,
+    // Vertical mirroring (horizontal if false):
+    pub vertical_mirroring: bool,
+    pub four_screen: bool,
+    // Battery backed PRG-RAM:
+    pub battery: bool,
+    pub trainer: Option<Vec<u8>>,
+    pub prg: Vec<u8>,
+    pub chr: Vec<u8>,
+}
+
+/// Parses an iNES or NES 2.0 file
+pub fn parse(bytes: &[u8]) -> Result<Rom, LoadError> {
+    if bytes.len() < HEADER || bytes[0..4] != MAGIC {
+        return Err(LoadError::Format("not an iNES file".to_string()));
+    }
+    let flags6 = bytes[6];
+    let flags7 = bytes[7];
+    let nes2 = flags7 & 0x0C == 0x08;
+
+    let mut mapper = ((flags7 & 0xF0) | (flags6 >> 4)) as u16;
+    let mut submapper = 0;
+    let mut prg_units = bytes[4] as usize;
+    let mut chr_units = bytes[5] as usize;
+    if nes2 {
+        mapper |= ((bytes[8] & 0x0F) as u16) << 8;
+        submapper = bytes[8] >> 4;
+        let prg_msb = (bytes[9] & 0x0F) as usize;
+        let chr_msb = (bytes[9] >> 4) as usize;
+        if prg_msb == 0x0F || chr_msb == 0x0F {
+            return Err(LoadError::Format(
+                "exponent-multiplier ROM sizes are not supported".to_string(),
+            ));
+        }
+        prg_units |= prg_msb << 8;
+        chr_units |= chr_msb << 8;
+    } else if bytes[12..16].iter().any(|&b| b != 0) {
+        // Old dumping tools wrote their name into the end of the header,
+        // so the upper nibble of the mapper can not be trusted
+        mapper &= 0x0F;
+    }
+
+    let mut pos = HEADER;
+    let trainer = if flags6 & 0x04 == 0x04 {
+        let trainer = take(bytes, &mut pos, TRAINER, "trainer")?;
+        Some(trainer.to_vec())
+    } else {
+        None
+    };
+    let prg = take(bytes, &mut pos, prg_units * PRG_UNIT, "PRG-ROM")?.to_vec();
+    let chr = take(bytes, &mut pos, chr_units * CHR_UNIT, "CHR-ROM")?.to_vec();
+
+    Ok(Rom {
+        mapper,
+        submapper,
+        nes2,
+        vertical_mirroring: flags6 & 0x01 == 0x01,
+        four_screen: flags6 & 0x08 == 0x08,
+        battery: flags6 & 0x02 == 0x02,
+        trainer,
+        prg,
+        chr,
+    })
+}
+
+impl Rom {
+    /// Maps the PRG-ROM to $8000-$FFFF like NROM does.
+    ///
+    /// A 16K PRG-ROM is mirrored at $C000.
+    pub fn image(&self) -> Result<Image, LoadError> {
+        if self.mapper != 0 {
+            return Err(LoadError::UnsupportedMapper(self.mapper));
+        }
+        let mut image = Image::default();
+        match self.prg.len() {
+            PRG_UNIT => {
+                image.push(PRG_START, &self.prg);
+                image.push(PRG_START + PRG_UNIT as u16, &self.prg);
+            }
+            0x8000 => image.push(PRG_START, &self.prg),
+            size => {
+                return Err(LoadError::Format(format!(
+                    "NROM needs 16K or 32K of PRG-ROM, not {} bytes",
+                    size
+                )))
+            }
+        }
+        // The reset vector is at the end of the PRG-ROM
+        image.start = Some(u16::from_le_bytes([
+            self.prg[self.prg.len() - 4],
+            self.prg[self.prg.len() - 3],
+        ]));
+        Ok(image)
+    }
+}
+
+/// Takes the next `length` bytes of the file
+fn take<'a>(
+    bytes: &'a [u8],
+    pos: &mut usize,
+    length: usize,
+    what: &str,
+) -> Result<&'a [u8], LoadError> {
+    if *pos + length > bytes.len() {
+        return Err(LoadError::Format(format!(
+            "the file ends inside of the {}",
+            what
+        )));
+    }
+    let data = &bytes[*pos..*pos + length];
+    *pos += length;
+    Ok(data)
+}
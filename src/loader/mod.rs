@@ -0,0 +1,176 @@
+// Loaders for the different image formats.
+//
+// Every format is parsed into an `Image` first,
+// which then gets placed into the memory of the chip
+// by `Chip::load_image`.
+
+use std::fmt;
+use std::io;
+
+use crate::chip::MEMORY;
+
+pub mod binary;
+pub mod ihex;
+pub mod ines;
+pub mod o65;
+pub mod patch;
+pub mod prg;
+pub mod sim65;
+pub mod srec;
+
+/// A block of bytes that belongs at a given address
+#[derive(Debug, PartialEq, Clone)]
+pub struct Segment {
+    pub address: u16,
+    pub data: Vec<u8>,
+}
+
+/// A parsed image: the data and where the execution should start
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Image {
+    pub segments: Vec<Segment>,
+    pub start: Option<u16>,
+}
+
+impl Image {
+    /// Adds the data at the address, merging it with the last segment
+    /// if it directly follows it
+    pub fn push(&mut self, address: u16, data: &[u8]) {
+        if let Some(last) = self.segments.last_mut() {
+            if last.address as usize + last.data.len() == address as usize {
+                last.data.extend_from_slice(data);
+                return;
+            }
+        }
+        self.segments.push(Segment {
+            address,
+            data: data.to_vec(),
+        });
+    }
+}
+
+/// Everything that can go wrong while loading an image
+#[derive(Debug)]
+pub enum LoadError {
+    /// The file could not be read
+    Io(io::Error),
+    /// A line (or record) could not be parsed
+    Syntax { line: usize, message: String },
+    /// The checksum of a record does not match its contents
+    Checksum {
+        line: usize,
+        expected: u8,
+        found: u8,
+    },
+    /// A binary file is not what its format says it should be
+    Format(String),
+    /// The NES ROM needs a mapper that is not implemented
+    UnsupportedMapper(u16),
+    /// The data does not fit into the 64K address space
+    /// (`line` is the record for the text formats)
+    OutOfRange {
+        address: u32,
+        length: usize,
+        line: Option<usize>,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "{}", e),
+            LoadError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            LoadError::Checksum {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: checksum mismatch (expected ${:02X}, found ${:02X})",
+                line, expected, found
+            ),
+            LoadError::Format(message) => write!(f, "{}", message),
+            LoadError::UnsupportedMapper(mapper) => {
+                write!(f, "mapper {} is not supported (only NROM)", mapper)
+            }
+            LoadError::OutOfRange {
+                address,
+                length,
+                line,
+            } => {
+                if let Some(line) = line {
+                    write!(f, "line {}: ", line)?;
+                }
+                write!(
+                    f,
+                    "{} bytes at ${:04X} do not fit into the memory",
+                    length, address
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> LoadError {
+        LoadError::Io(e)
+    }
+}
+
+/// Checks if `length` bytes starting at `address` fit into the memory
+pub(crate) fn check_range(address: u32, length: usize) -> Result<(), LoadError> {
+    if address as usize + length > MEMORY {
+        return Err(LoadError::OutOfRange {
+            address,
+            length,
+            line: None,
+        });
+    }
+    Ok(())
+}
+
+/// Like `check_range`, for a record in the given line
+pub(crate) fn check_record_range(
+    address: u32,
+    length: usize,
+    line: usize,
+) -> Result<(), LoadError> {
+    if address as usize + length > MEMORY {
+        return Err(LoadError::OutOfRange {
+            address,
+            length,
+            line: Some(line),
+        });
+    }
+    Ok(())
+}
+
+/// Parses a hexadecimal byte out of two characters
+pub(crate) fn hex_byte(text: &str, line: usize) -> Result<u8, LoadError> {
+    u8::from_str_radix(text, 16).map_err(|_| LoadError::Syntax {
+        line,
+        message: format!("invalid hex byte `{}`", text),
+    })
+}
+
+/// Parses a string of hexadecimal digits into bytes
+pub(crate) fn hex_bytes(text: &str, line: usize) -> Result<Vec<u8>, LoadError> {
+    if !text.is_ascii() {
+        return Err(LoadError::Syntax {
+            line,
+            message: "invalid character".to_string(),
+        });
+    }
+    if !text.len().is_multiple_of(2) {
+        return Err(LoadError::Syntax {
+            line,
+            message: "odd number of hex digits".to_string(),
+        });
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| hex_byte(&text[i..i + 2], line))
+        .collect()
+}
@@ -0,0 +1,394 @@
+// o65 relocatable object files (xa, ld65)
+//
+// Header:
+// $01 $00 "o65" ... marker and magic
+// version        ... 0
+// mode           ... word with the flags below
+// tbase, tlen    ... text segment base address and length
+// dbase, dlen    ... data segment
+// bbase, blen    ... bss segment
+// zbase, zlen    ... zeropage segment
+// stack          ... needed stack size
+// options        ... (length, type, data) until a zero length
+//
+// After the header come the text and data segments, the list of the
+// undefined (imported) symbols, the relocation tables of text and data
+// and the list of the exported symbols.
+//
+// Only the 16-bit variant for the 6502 is supported.
+//
+// Reference: http://www.6502.org/users/andre/o65/fileformat.html
+
+use std::collections::HashMap;
+
+use super::{Image, LoadError};
+
+const MAGIC: [u8; 5] = [0x01, 0x00, b'o', b'6', b'5'];
+
+// The mode bits
+pub const MODE_65816: u16 = 0x8000;
+pub const MODE_PAGED: u16 = 0x4000;
+pub const MODE_32BIT: u16 = 0x2000;
+pub const MODE_OBJECT: u16 = 0x1000;
+pub const MODE_BSSZERO: u16 = 0x0200;
+
+// The segment ids
+pub const SEG_UNDEFINED: u8 = 0;
+pub const SEG_ABSOLUTE: u8 = 1;
+pub const SEG_TEXT: u8 = 2;
+pub const SEG_DATA: u8 = 3;
+pub const SEG_BSS: u8 = 4;
+pub const SEG_ZERO: u8 = 5;
+
+/// What part of the address a relocation entry changes
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum RelocationKind {
+    /// a whole little endian word
+    Word,
+    /// the high byte (with the low byte kept in the table)
+    High(u8),
+    /// the low byte
+    Low,
+}
+
+/// One entry of a relocation table
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Relocation {
+    // Offset into the segment:
+    pub offset: usize,
+    pub kind: RelocationKind,
+    // The segment the value points into:
+    pub segment: u8,
+    // The index into the undefined symbols (if segment is SEG_UNDEFINED):
+    pub symbol: u16,
+}
+
+/// An exported symbol
+#[derive(Debug, PartialEq, Clone)]
+pub struct Export {
+    pub name: String,
+    pub segment: u8,
+    pub value: u16,
+}
+
+/// The base addresses of the segments
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Bases {
+    pub text: u16,
+    pub data: u16,
+    pub bss: u16,
+    pub zero: u16,
+}
+
+/// A parsed o65 file
+#[derive(Debug, PartialEq, Clone)]
+pub struct O65 {
+    pub mode: u16,
+    // The bases the file was assembled for:
+    pub bases: Bases,
+    pub bss_len: u16,
+    pub zero_len: u16,
+    pub stack: u16,
+    pub options: Vec<(u8, Vec<u8>)>,
+    pub text: Vec<u8>,
+    pub data: Vec<u8>,
+    pub undefined: Vec<String>,
+    pub text_relocations: Vec<Relocation>,
+    pub data_relocations: Vec<Relocation>,
+    pub exports: Vec<Export>,
+}
+
+/// The result of relocating an o65 file
+#[derive(Debug, PartialEq, Clone)]
+pub struct Linked {
+    pub image: Image,
+    // The exported symbols at their relocated addresses:
+    pub exports: HashMap<String, u16>,
+}
+
+/// Reads the file step by step
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize, what: &str) -> Result<&'a [u8], LoadError> {
+        if self.pos + length > self.bytes.len() {
+            return Err(LoadError::Format(format!(
+                "the file ends inside of the {}",
+                what
+            )));
+        }
+        let data = &self.bytes[self.pos..self.pos + length];
+        self.pos += length;
+        Ok(data)
+    }
+
+    fn byte(&mut self, what: &str) -> Result<u8, LoadError> {
+        Ok(self.take(1, what)?[0])
+    }
+
+    fn word(&mut self, what: &str) -> Result<u16, LoadError> {
+        let data = self.take(2, what)?;
+        Ok(u16::from_le_bytes([data[0], data[1]]))
+    }
+
+    fn name(&mut self, what: &str) -> Result<String, LoadError> {
+        let rest = &self.bytes[self.pos..];
+        let end = match rest.iter().position(|&b| b == 0) {
+            Some(end) => end,
+            None => {
+                return Err(LoadError::Format(format!(
+                    "the file ends inside of the {}",
+                    what
+                )))
+            }
+        };
+        let name = String::from_utf8_lossy(&rest[..end]).to_string();
+        self.pos += end + 1;
+        Ok(name)
+    }
+
+    fn relocations(&mut self, mode: u16, what: &str) -> Result<Vec<Relocation>, LoadError> {
+        let mut relocations = Vec::new();
+        // The first offset is counted from the byte before the segment
+        let mut address: isize = -1;
+        loop {
+            let offset = self.byte(what)?;
+            match offset {
+                0 => return Ok(relocations),
+                255 => {
+                    address += 254;
+                    continue;
+                }
+                _ => address += offset as isize,
+            }
+            let typebyte = self.byte(what)?;
+            let segment = typebyte & 0x07;
+            let symbol = if segment == SEG_UNDEFINED {
+                self.word(what)?
+            } else {
+                0
+            };
+            let kind = match typebyte & 0xE0 {
+                0x80 => RelocationKind::Word,
+                0x40 if mode & MODE_PAGED == MODE_PAGED => RelocationKind::High(0),
+                0x40 => RelocationKind::High(self.byte(what)?),
+                0x20 => RelocationKind::Low,
+                kind => {
+                    return Err(LoadError::Format(format!(
+                        "relocation type ${:02X} is not supported",
+                        kind
+                    )))
+                }
+            };
+            relocations.push(Relocation {
+                offset: address as usize,
+                kind,
+                segment,
+                symbol,
+            });
+        }
+    }
+}
+
+/// Parses an o65 file
+pub fn parse(bytes: &[u8]) -> Result<O65, LoadError> {
+    let mut r = Reader { bytes, pos: 0 };
+    if r.take(5, "header")? != MAGIC {
+        return Err(LoadError::Format("not an o65 file".to_string()));
+    }
+    let version = r.byte("header")?;
+    if version != 0 {
+        return Err(LoadError::Format(format!(
+            "o65 version {} is not supported",
+            version
+        )));
+    }
+    let mode = r.word("header")?;
+    if mode & (MODE_65816 | MODE_32BIT) != 0 {
+        return Err(LoadError::Format(
+            "only 16-bit 6502 o65 files are supported".to_string(),
+        ));
+    }
+    let text_base = r.word("header")?;
+    let text_len = r.word("header")?;
+    let data_base = r.word("header")?;
+    let data_len = r.word("header")?;
+    let bss_base = r.word("header")?;
+    let bss_len = r.word("header")?;
+    let zero_base = r.word("header")?;
+    let zero_len = r.word("header")?;
+    let stack = r.word("header")?;
+
+    let mut options = Vec::new();
+    loop {
+        let length = r.byte("header options")?;
+        if length == 0 {
+            break;
+        }
+        if length < 2 {
+            return Err(LoadError::Format("invalid header option".to_string()));
+        }
+        let kind = r.byte("header options")?;
+        let data = r.take(length as usize - 2, "header options")?;
+        options.push((kind, data.to_vec()));
+    }
+
+    let text = r.take(text_len as usize, "text segment")?.to_vec();
+    let data = r.take(data_len as usize, "data segment")?.to_vec();
+
+    let count = r.word("undefined symbols")?;
+    let mut undefined = Vec::new();
+    for _ in 0..count {
+        undefined.push(r.name("undefined symbols")?);
+    }
+
+    let text_relocations = r.relocations(mode, "text relocation table")?;
+    let data_relocations = r.relocations(mode, "data relocation table")?;
+
+    let count = r.word("exported symbols")?;
+    let mut exports = Vec::new();
+    for _ in 0..count {
+        let name = r.name("exported symbols")?;
+        let segment = r.byte("exported symbols")?;
+        let value = r.word("exported symbols")?;
+        exports.push(Export {
+            name,
+            segment,
+            value,
+        });
+    }
+
+    Ok(O65 {
+        mode,
+        bases: Bases {
+            text: text_base,
+            data: data_base,
+            bss: bss_base,
+            zero: zero_base,
+        },
+        bss_len,
+        zero_len,
+        stack,
+        options,
+        text,
+        data,
+        undefined,
+        text_relocations,
+        data_relocations,
+        exports,
+    })
+}
+
+impl O65 {
+    /// Moves the segments to the given bases.
+    ///
+    /// The undefined symbols are looked up in `imports`, a missing
+    /// symbol is an error.
+    pub fn relocate(
+        &self,
+        bases: &Bases,
+        imports: &HashMap<String, u16>,
+    ) -> Result<Linked, LoadError> {
+        let mut text = self.text.clone();
+        let mut data = self.data.clone();
+        self.apply(&mut text, &self.text_relocations, bases, imports, "text")?;
+        self.apply(&mut data, &self.data_relocations, bases, imports, "data")?;
+
+        let mut image = Image::default();
+        image.push(bases.text, &text);
+        image.push(bases.data, &data);
+        if self.mode & MODE_BSSZERO == MODE_BSSZERO {
+            image.push(bases.bss, &vec![0; self.bss_len as usize]);
+        }
+        image.segments.retain(|s| !s.data.is_empty());
+        for segment in &image.segments {
+            super::check_range(segment.address as u32, segment.data.len())?;
+        }
+
+        let mut exports = HashMap::new();
+        for export in &self.exports {
+            let value =
+                export
+                    .value
+                    .wrapping_add(self.difference(export.segment, 0, bases, imports)?);
+            exports.insert(export.name.clone(), value);
+        }
+        Ok(Linked { image, exports })
+    }
+
+    /// How much a value pointing into the segment has to move
+    fn difference(
+        &self,
+        segment: u8,
+        symbol: u16,
+        bases: &Bases,
+        imports: &HashMap<String, u16>,
+    ) -> Result<u16, LoadError> {
+        Ok(match segment {
+            SEG_UNDEFINED => {
+                let name = match self.undefined.get(symbol as usize) {
+                    Some(name) => name,
+                    None => {
+                        return Err(LoadError::Format(format!(
+                            "relocation refers to undefined symbol #{} that does not exist",
+                            symbol
+                        )))
+                    }
+                };
+                match imports.get(name) {
+                    Some(value) => *value,
+                    None => return Err(LoadError::Format(format!("undefined symbol `{}`", name))),
+                }
+            }
+            SEG_ABSOLUTE => 0,
+            SEG_TEXT => bases.text.wrapping_sub(self.bases.text),
+            SEG_DATA => bases.data.wrapping_sub(self.bases.data),
+            SEG_BSS => bases.bss.wrapping_sub(self.bases.bss),
+            SEG_ZERO => bases.zero.wrapping_sub(self.bases.zero),
+            _ => return Err(LoadError::Format(format!("unknown segment id {}", segment))),
+        })
+    }
+
+    /// Applies a relocation table to a segment
+    fn apply(
+        &self,
+        segment: &mut [u8],
+        relocations: &[Relocation],
+        bases: &Bases,
+        imports: &HashMap<String, u16>,
+        what: &str,
+    ) -> Result<(), LoadError> {
+        for relocation in relocations {
+            let diff = self.difference(relocation.segment, relocation.symbol, bases, imports)?;
+            let at = relocation.offset;
+            let size = match relocation.kind {
+                RelocationKind::Word => 2,
+                _ => 1,
+            };
+            if at + size > segment.len() {
+                return Err(LoadError::Format(format!(
+                    "relocation at offset {} is outside of the {} segment",
+                    at, what
+                )));
+            }
+            match relocation.kind {
+                RelocationKind::Word => {
+                    let value =
+                        u16::from_le_bytes([segment[at], segment[at + 1]]).wrapping_add(diff);
+                    segment[at..at + 2].copy_from_slice(&value.to_le_bytes());
+                }
+                RelocationKind::High(low) => {
+                    let value = u16::from_le_bytes([low, segment[at]]).wrapping_add(diff);
+                    segment[at] = (value >> 8) as u8;
+                }
+                RelocationKind::Low => {
+                    segment[at] = segment[at].wrapping_add(diff as u8);
+                }
+            }
+        }
+        Ok(())
+    }
+}
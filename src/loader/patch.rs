@@ -0,0 +1,238 @@
+// IPS and BPS patches
+//
+// IPS: "PATCH", then records until "EOF"
+//      offset (3 bytes, big endian), size (2 bytes), data
+//      a size of 0 is a run: size (2 bytes) and the byte to repeat
+//      after "EOF" there can be the size to truncate the file to (3 bytes)
+//
+// BPS: "BPS1", source size, target size, metadata size, metadata,
+//      actions, CRC32 of the source, the target and the patch
+//      (all numbers but the CRC32s are variable length)
+//
+//      every action is a number: (length - 1) << 2 | command
+//      0 SourceRead ... copy from the source at the same offset
+//      1 TargetRead ... copy from the patch
+//      2 SourceCopy ... copy from the source at a relative offset
+//      3 TargetCopy ... copy from the target at a relative offset
+//
+// Reference: https://zerosoft.zophar.net/ips.php
+//            https://github.com/blakesmith/rombp/blob/master/docs/bps_spec.md
+
+use super::LoadError;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: usize = 0x454F46;
+const BPS_MAGIC: &[u8] = b"BPS1";
+
+/// Applies an IPS or BPS patch (depending on its magic)
+pub fn apply(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, LoadError> {
+    if patch.starts_with(IPS_MAGIC) {
+        apply_ips(source, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(source, patch)
+    } else {
+        Err(LoadError::Format("not an IPS or BPS patch".to_string()))
+    }
+}
+
+/// Applies an IPS patch, the result grows if the patch
+/// writes behind the end of the source
+pub fn apply_ips(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, LoadError> {
+    if !patch.starts_with(IPS_MAGIC) {
+        return Err(LoadError::Format("not an IPS patch".to_string()));
+    }
+    let mut target = source.to_vec();
+    let mut reader = Reader::new(patch, IPS_MAGIC.len());
+    loop {
+        let offset = reader.number(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let size = reader.number(2)?;
+        let data = if size == 0 {
+            let size = reader.number(2)?;
+            vec![reader.byte()?; size]
+        } else {
+            reader.bytes(size)?.to_vec()
+        };
+        if target.len() < offset + data.len() {
+            target.resize(offset + data.len(), 0);
+        }
+        target[offset..offset + data.len()].copy_from_slice(&data);
+    }
+    if reader.remaining() >= 3 {
+        let size = reader.number(3)?;
+        target.resize(size, 0);
+    }
+    Ok(target)
+}
+
+/// Applies a BPS patch, the CRC32s of the source, the target
+/// and the patch itself are checked
+pub fn apply_bps(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, LoadError> {
+    if !patch.starts_with(BPS_MAGIC) || patch.len() < BPS_MAGIC.len() + 12 {
+        return Err(LoadError::Format("not a BPS patch".to_string()));
+    }
+    let footer = patch.len() - 12;
+    let crc =
+        |at: usize| u32::from_le_bytes([patch[at], patch[at + 1], patch[at + 2], patch[at + 3]]);
+    check_crc("patch", crc(footer + 8), crc32(&patch[..footer + 8]))?;
+    check_crc("source", crc(footer), crc32(source))?;
+
+    let mut reader = Reader::new(&patch[..footer], BPS_MAGIC.len());
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.bytes(metadata_size)?;
+    if source_size != source.len() {
+        return Err(LoadError::Format(format!(
+            "the patch is for {} bytes but the source has {}",
+            source_size,
+            source.len()
+        )));
+    }
+
+    let out_of_range =
+        || LoadError::Format("the patch copies from outside of its data".to_string());
+    let mut target: Vec<u8> = Vec::new();
+    let mut source_offset: usize = 0;
+    let mut target_offset: usize = 0;
+    while reader.remaining() > 0 {
+        let action = reader.varint()?;
+        let length = (action >> 2) + 1;
+        if target.len() + length > target_size {
+            return Err(LoadError::Format(
+                "the patch writes behind the end of the target".to_string(),
+            ));
+        }
+        match action & 0x03 {
+            0 => {
+                let start = target.len();
+                let data = source.get(start..start + length).ok_or_else(out_of_range)?;
+                target.extend_from_slice(data);
+            }
+            1 => target.extend_from_slice(reader.bytes(length)?),
+            2 => {
+                source_offset =
+                    relative(source_offset, reader.varint()?).ok_or_else(out_of_range)?;
+                let data = source
+                    .get(source_offset..source_offset + length)
+                    .ok_or_else(out_of_range)?;
+                target.extend_from_slice(data);
+                source_offset += length;
+            }
+            _ => {
+                target_offset =
+                    relative(target_offset, reader.varint()?).ok_or_else(out_of_range)?;
+                // the copy can overlap with what it writes (a run)
+                for _ in 0..length {
+                    let byte = *target.get(target_offset).ok_or_else(out_of_range)?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+    if target.len() != target_size {
+        return Err(LoadError::Format(format!(
+            "the patch made {} bytes but should make {}",
+            target.len(),
+            target_size
+        )));
+    }
+    check_crc("target", crc(footer + 4), crc32(&target))?;
+    Ok(target)
+}
+
+/// The CRC32 (like zip and PNG use it)
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn check_crc(what: &str, expected: u32, found: u32) -> Result<(), LoadError> {
+    if expected != found {
+        return Err(LoadError::Format(format!(
+            "CRC32 of the {} does not match (expected ${:08X}, found ${:08X})",
+            what, expected, found
+        )));
+    }
+    Ok(())
+}
+
+/// Moves an offset by a BPS relative number (the lowest bit is the sign)
+fn relative(offset: usize, number: usize) -> Option<usize> {
+    if number & 1 == 1 {
+        offset.checked_sub(number >> 1)
+    } else {
+        offset.checked_add(number >> 1)
+    }
+}
+
+/// Reads the numbers and data of a patch
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], at: usize) -> Reader<'a> {
+        Reader { bytes, at }
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.at
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], LoadError> {
+        let bytes = self
+            .at
+            .checked_add(length)
+            .and_then(|end| self.bytes.get(self.at..end))
+            .ok_or_else(|| LoadError::Format("the patch is cut off".to_string()))?;
+        self.at += length;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, LoadError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// A big endian number (IPS)
+    fn number(&mut self, size: usize) -> Result<usize, LoadError> {
+        Ok(self
+            .bytes(size)?
+            .iter()
+            .fold(0, |number, b| number << 8 | *b as usize))
+    }
+
+    /// A variable length number (BPS)
+    fn varint(&mut self) -> Result<usize, LoadError> {
+        let mut number: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let byte = self.byte()? as usize;
+            number = number
+                .checked_add((byte & 0x7F) * shift)
+                .ok_or_else(|| LoadError::Format("a number of the patch is too big".to_string()))?;
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+            shift = shift
+                .checked_shl(7)
+                .filter(|s| *s < 1 << 48)
+                .ok_or_else(|| LoadError::Format("a number of the patch is too big".to_string()))?;
+            number += shift;
+        }
+    }
+}
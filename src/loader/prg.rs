@@ -0,0 +1,69 @@
+// Commodore PRG files
+//
+// The first two bytes are the load address (little endian),
+// the rest is the data that is loaded there.
+//
+// Programs that are started with RUN are loaded to the start of
+// BASIC ($0801 on the C64) and begin with a small BASIC line
+// like `10 SYS 2061` that jumps into the machine code.
+
+use super::{check_range, Image, LoadError};
+
+/// The start of the BASIC program area of the C64
+pub const BASIC_START: u16 = 0x0801;
+
+// The BASIC token of SYS
+const SYS_TOKEN: u8 = 0x9E;
+
+/// Parses a PRG file
+pub fn parse(bytes: &[u8]) -> Result<Image, LoadError> {
+    if bytes.len() < 3 {
+        return Err(LoadError::Format(
+            "a PRG file needs a load address and data".to_string(),
+        ));
+    }
+    let address = u16::from_le_bytes([bytes[0], bytes[1]]);
+    let data = &bytes[2..];
+    check_range(address as u32, data.len())?;
+
+    let mut image = Image::default();
+    image.push(address, data);
+    Ok(image)
+}
+
+/// Finds the address of the `SYS` in the first BASIC line.
+///
+/// Returns `None` if the image is not loaded to the start of BASIC
+/// or its first line is no `SYS <number>`.
+pub fn sys_address(image: &Image) -> Option<u16> {
+    let segment = image.segments.first()?;
+    if segment.address != BASIC_START {
+        return None;
+    }
+    // skip the link to the next line and the line number
+    let mut tokens = segment.data.get(4..)?.iter().copied();
+    tokens
+        .by_ref()
+        .find(|&b| b != b' ')
+        .filter(|&b| b == SYS_TOKEN)?;
+
+    let mut address: u32 = 0;
+    let mut digits = 0;
+    for b in tokens {
+        match b {
+            b' ' if digits == 0 => {}
+            b'0'..=b'9' => {
+                address = address * 10 + (b - b'0') as u32;
+                digits += 1;
+                if address > 0xFFFF {
+                    return None;
+                }
+            }
+            _ => break,
+        }
+    }
+    if digits == 0 {
+        return None;
+    }
+    Some(address as u16)
+}
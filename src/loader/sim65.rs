@@ -0,0 +1,73 @@
+// cc65 sim65 binaries (the `sim6502` and `sim65c02` targets)
+//
+// Header (12 bytes):
+// "sim65"     ... magic
+// version     ... 2
+// cpu         ... 0 = 6502, 1 = 65C02
+// sp address  ... zeropage address of the C stack pointer
+// load        ... load address (little endian)
+// reset       ... reset address (little endian)
+//
+// The rest of the file is loaded to the load address, it has to end
+// before the paravirtualization addresses at $FFF4.
+//
+// Reference: https://cc65.github.io/doc/sim65.html
+
+use super::{Image, LoadError};
+use crate::chip::Cpu;
+
+pub const MAGIC: [u8; 5] = *b"sim65";
+const VERSION: u8 = 2;
+const HEADER: usize = 12;
+
+/// The first address that is used by the paravirtualization hooks
+pub const PARAVIRT_BASE: u16 = 0xFFF4;
+
+/// The header of a sim65 binary
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Header {
+    // The CPU the program was compiled for:
+    pub cpu: Cpu,
+    // Zeropage address of the C stack pointer:
+    pub sp_address: u8,
+    pub load: u16,
+    pub reset: u16,
+}
+
+/// Parses a sim65 binary
+pub fn parse(bytes: &[u8]) -> Result<(Header, Image), LoadError> {
+    if bytes.len() < HEADER || bytes[0..5] != MAGIC {
+        return Err(LoadError::Format("not a sim65 binary".to_string()));
+    }
+    if bytes[5] != VERSION {
+        return Err(LoadError::Format(format!(
+            "sim65 version {} is not supported",
+            bytes[5]
+        )));
+    }
+    let cpu = match bytes[6] {
+        0 => Cpu::Mos6502,
+        1 => Cpu::Wdc65C02,
+        cpu => return Err(LoadError::Format(format!("unknown CPU type {}", cpu))),
+    };
+    let header = Header {
+        cpu,
+        sp_address: bytes[7],
+        load: u16::from_le_bytes([bytes[8], bytes[9]]),
+        reset: u16::from_le_bytes([bytes[10], bytes[11]]),
+    };
+
+    let data = &bytes[HEADER..];
+    if header.load as usize + data.len() > PARAVIRT_BASE as usize {
+        return Err(LoadError::Format(format!(
+            "{} bytes at ${:04X} do not fit below ${:04X}",
+            data.len(),
+            header.load,
+            PARAVIRT_BASE
+        )));
+    }
+    let mut image = Image::default();
+    image.push(header.load, data);
+    image.start = Some(header.reset);
+    Ok((header, image))
+}
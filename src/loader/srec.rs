@@ -0,0 +1,100 @@
+// Motorola S-records
+//
+// Every line is a record:
+// `S<type><count><address><data><checksum>`
+// count    ... number of bytes of address, data and checksum
+// address  ... 2 (S1/S9), 3 (S2/S8) or 4 (S3/S7) bytes (big endian)
+// checksum ... one's complement of the sum of count, address and data
+//
+// Reference: https://en.wikipedia.org/wiki/SREC_(file_format)
+
+use super::{check_record_range, hex_bytes, Image, LoadError};
+
+/// Parses the text of an S-record file (S19, S28 or S37)
+pub fn parse(text: &str) -> Result<Image, LoadError> {
+    let mut image = Image::default();
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+        let mut chars = raw.chars();
+        let kind = match (chars.next(), chars.next()) {
+            (Some('S'), Some(kind)) if kind.is_ascii_digit() => kind,
+            _ => {
+                return Err(LoadError::Syntax {
+                    line,
+                    message: "record does not start with `S` and a type digit".to_string(),
+                })
+            }
+        };
+        let bytes = hex_bytes(chars.as_str(), line)?;
+        if bytes.is_empty() || bytes.len() != bytes[0] as usize + 1 {
+            return Err(LoadError::Syntax {
+                line,
+                message: "record length does not match its byte count".to_string(),
+            });
+        }
+
+        let (body, checksum) = bytes.split_at(bytes.len() - 1);
+        let expected = !body.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        if expected != checksum[0] {
+            return Err(LoadError::Checksum {
+                line,
+                expected,
+                found: checksum[0],
+            });
+        }
+
+        let body = &body[1..];
+        match kind {
+            // header, and the record counts
+            '0' | '5' | '6' => {}
+            '1' | '2' | '3' => {
+                let (address, data) = split_address(body, kind, line)?;
+                check_record_range(address, data.len(), line)?;
+                image.push(address as u16, data);
+            }
+            '7' | '8' | '9' => {
+                let (address, _) = split_address(body, kind, line)?;
+                if address > 0xFFFF {
+                    return Err(LoadError::Syntax {
+                        line,
+                        message: format!("start address ${:X} is outside of the memory", address),
+                    });
+                }
+                image.start = Some(address as u16);
+            }
+            _ => {
+                return Err(LoadError::Syntax {
+                    line,
+                    message: format!("unknown record type S{}", kind),
+                })
+            }
+        }
+    }
+
+    Ok(image)
+}
+
+/// Splits the address of the record from its data
+fn split_address(body: &[u8], kind: char, line: usize) -> Result<(u32, &[u8]), LoadError> {
+    let width = match kind {
+        '1' | '9' => 2,
+        '2' | '8' => 3,
+        _ => 4,
+    };
+    if body.len() < width {
+        return Err(LoadError::Syntax {
+            line,
+            message: format!("S{} record needs a {} byte address", kind, width),
+        });
+    }
+    let (address, data) = body.split_at(width);
+    let address = address
+        .iter()
+        .fold(0u32, |address, b| (address << 8) | *b as u32);
+    Ok((address, data))
+}
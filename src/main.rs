@@ -1,6 +1,137 @@
+use std::ops::RangeInclusive;
+
+use sixfiveohtwo::dump::{self, Format};
 use sixfiveohtwo::*;
 
 fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+
+    // --dump <range> [--format <format>] [--output <file>]
+    // dumps the memory once the program is done
+    let dump = match take_dump_options(&mut args) {
+        Ok(dump) => dump,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    };
+
+    // sixfiveohtwo --sim65 [-x <instructions>] <program> [arguments...]
+    if args.len() > 2 && args[1] == "--sim65" {
+        let mut max = paravirt::MAX_INSTRUCTIONS;
+        if args[2] == "-x" && args.len() > 4 {
+            max = match args[3].parse() {
+                Ok(max) => max,
+                Err(_) => {
+                    eprintln!("`{}` is not a number of instructions", args[3]);
+                    std::process::exit(2);
+                }
+            };
+            args.drain(2..4);
+        }
+        match run_sim65(args[2].clone(), args[2..].to_vec(), max) {
+            Ok((Some(code), c)) => {
+                write_dump(&c, &dump);
+                std::process::exit(code as i32)
+            }
+            Ok((None, c)) => {
+                eprintln!("{}: did not exit after {} instructions", args[2], max);
+                write_dump(&c, &dump);
+                std::process::exit(paravirt::EXIT_TIMEOUT as i32)
+            }
+            Err(e) => {
+                eprintln!("{}: {}", args[2], e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // sixfiveohtwo --assemble <source> <binary>
+    // writes the bytes like `as65 -h0` does
+    if args.len() > 3 && args[1] == "--assemble" {
+        let assembler = assembler::Assembler::new(assembler::Cpu::Mos6502);
+        match assembler.assemble_file(args[2].clone()) {
+            Ok(assembly) => {
+                let (start, bytes) = assembly.binary();
+                println!(
+                    "${:04X}-${:04X}",
+                    start,
+                    (start as usize + bytes.len()).saturating_sub(1)
+                );
+                if let Err(e) = std::fs::write(&args[3], bytes) {
+                    eprintln!("{}: {}", args[3], e);
+                    std::process::exit(1);
+                }
+                std::process::exit(0)
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     run();
-    run_testprogramm();
+    let c = run_testprogramm();
+    write_dump(&c, &dump);
+}
+
+struct Dump {
+    range: RangeInclusive<u16>,
+    format: Format,
+    output: Option<String>,
+}
+
+/// Removes the dump options from the arguments
+fn take_dump_options(args: &mut Vec<String>) -> Result<Option<Dump>, String> {
+    let mut take = |name: &str| -> Result<Option<String>, String> {
+        match args.iter().position(|a| a == name) {
+            Some(i) if i + 1 < args.len() => {
+                let value = args.remove(i + 1);
+                args.remove(i);
+                Ok(Some(value))
+            }
+            Some(_) => Err(format!("{} needs a value", name)),
+            None => Ok(None),
+        }
+    };
+    let range = take("--dump")?;
+    let format = take("--format")?;
+    let output = take("--output")?;
+
+    let range = match range {
+        Some(range) => dump::parse_range(&range)
+            .ok_or_else(|| format!("`{}` is not a range like $0200-$02FF", range))?,
+        None => return Ok(None),
+    };
+    let format = match format {
+        Some(format) => Format::parse(&format).ok_or_else(|| {
+            format!(
+                "`{}` is not a format (raw, ihex, hexdump or petscii)",
+                format
+            )
+        })?,
+        None => Format::Hexdump(dump::Charset::Ascii),
+    };
+    Ok(Some(Dump {
+        range,
+        format,
+        output,
+    }))
+}
+
+fn write_dump(c: &chip::Chip, dump: &Option<Dump>) {
+    let dump = match dump {
+        Some(dump) => dump,
+        None => return,
+    };
+    let bytes = dump::export(c, dump.range.clone(), dump.format);
+    let result = match &dump.output {
+        Some(file_path) => std::fs::write(file_path, bytes),
+        None => std::io::Write::write_all(&mut std::io::stdout(), &bytes),
+    };
+    if let Err(e) = result {
+        eprintln!("can not write the dump: {}", e);
+        std::process::exit(1);
+    }
 }
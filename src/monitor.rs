@@ -0,0 +1,573 @@
+// A command-line monitor for the chip
+//
+// Every command is one line, the numbers are written like in label
+// files ($hex, 0xhex or decimal) and every address can also be a label:
+//
+// load <file> [address]     loads an image (the format is taken from the
+//                           extension, anything else is a raw binary)
+// labels <file>             loads labels (VICE, `label = $1234` or a listing)
+// r [reg=value...]          shows or sets A, X, Y, SP, P (or F) and PC
+// s [count]                 steps and shows the registers after every step
+// g [address] [max]         runs until a breakpoint or a trap (`jmp *`),
+//                           but at most `max` instructions (10 million
+//                           by default)
+// b <address>               sets a breakpoint (`bd` deletes, `bl` lists)
+// m [address|range]         shows the memory as a hexdump
+// > <address> <byte...>     writes bytes into the memory
+// a <address> <instruction> assembles an instruction into the memory
+// d [address] [count]       disassembles
+// reset                     does what the RES line does
+// gdb [address]             lets a debugger connect over the GDB remote
+//                           protocol (127.0.0.1:1234 by default)
+// history, !n, !!           lists and repeats commands
+// source <file>             runs the commands of a file
+// q                         quits
+//
+// The registers are shown like this, with the next instruction:
+//
+// PC   A  X  Y  SP NV-BDIZC
+// 0200 01 00 00 ff 00100100  STA $10
+
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use crate::assembler::Assembler;
+use crate::chip::{Chip, Cpu};
+use crate::disassembler::Disassembler;
+use crate::dump::{self, Charset};
+use crate::gdb;
+use crate::listing::Listing;
+use crate::loader::sim65;
+use crate::symbols::{parse_value, SymbolTable};
+
+/// How many bytes `m` shows without a range
+const MEMORY_LINES: u16 = 8 * 16;
+/// How many instructions `d` shows without a count
+const INSTRUCTIONS: u16 = 10;
+/// How many instructions `g` runs without a maximum
+const GO_LIMIT: u64 = 10_000_000;
+/// Where `gdb` waits without an address
+pub const GDB_ADDRESS: &str = "127.0.0.1:1234";
+/// How deep `source` can nest
+const MAX_DEPTH: usize = 8;
+
+const HELP: &str = "\
+load <file> [address]     load an image (raw binaries at $0200 by default)
+labels <file>             load labels (VICE, `label = $1234` or a listing)
+r [reg=value...]          show or set A, X, Y, SP, P and PC
+s [count]                 step
+g [address] [max]         run until a breakpoint or a trap (or max steps)
+b <address>               set a breakpoint
+bd <address>              delete a breakpoint
+bl                        list the breakpoints
+m [address|range]         show the memory
+> <address> <byte...>     write bytes into the memory
+a <address> <instruction> assemble an instruction into the memory
+d [address] [count]       disassemble
+reset                     reset the chip
+gdb [address]             wait for a GDB remote debugger (127.0.0.1:1234)
+history, !n, !!           list and repeat commands
+source <file>             run the commands of a file
+q                         quit
+";
+
+pub struct Monitor {
+    pub chip: Chip,
+    pub symbols: SymbolTable,
+    breakpoints: BTreeSet<u16>,
+    history: Vec<String>,
+    // Where `m` and `d` continue without an address:
+    next_memory: u16,
+    next_disassembly: Option<u16>,
+    depth: usize,
+    quit: bool,
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Monitor::new()
+    }
+}
+
+impl Monitor {
+    pub fn new() -> Monitor {
+        Monitor::with_chip(Chip::new())
+    }
+
+    pub fn with_chip(chip: Chip) -> Monitor {
+        Monitor {
+            chip,
+            symbols: SymbolTable::new(),
+            breakpoints: BTreeSet::new(),
+            history: Vec::new(),
+            next_memory: 0,
+            next_disassembly: None,
+            depth: 0,
+            quit: false,
+        }
+    }
+
+    /// If `q` was given
+    pub fn quit(&self) -> bool {
+        self.quit
+    }
+
+    /// The commands in the order they were given
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Runs a command and returns what it prints
+    pub fn command(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            return Ok(String::new());
+        }
+
+        // `!!` and `!n` are replaced by the command they repeat
+        let line = match line.strip_prefix('!') {
+            Some("!") => self
+                .history
+                .last()
+                .cloned()
+                .ok_or_else(|| "the history is empty".to_string())?,
+            Some(n) => {
+                let n: usize = n
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("`!{}` is not a number from the history", n))?;
+                self.history
+                    .get(n.wrapping_sub(1))
+                    .cloned()
+                    .ok_or_else(|| format!("there is no command {} in the history", n))?
+            }
+            None => line.to_string(),
+        };
+        if line != "history" && line != "h" {
+            self.history.push(line.clone());
+        }
+
+        let (name, rest) = match line.split_once(char::is_whitespace) {
+            Some((name, rest)) => (name, rest.trim()),
+            None => (line.as_str(), ""),
+        };
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        match name {
+            "help" | "?" => Ok(HELP.to_string()),
+            "load" | "l" => match args.as_slice() {
+                [file] => self.load(file, None),
+                [file, address] => {
+                    let address = self.value(address)?;
+                    self.load(file, Some(address))
+                }
+                _ => Err("usage: load <file> [address]".to_string()),
+            },
+            "labels" | "sym" => match args.as_slice() {
+                [file] => self.load_labels(file),
+                _ => Err("usage: labels <file>".to_string()),
+            },
+            "r" | "reg" | "registers" => self.set_registers(&args),
+            "s" | "step" | "z" => self.step(&args),
+            "g" | "go" => self.go(&args),
+            "b" | "break" => self.set_breakpoint(&args),
+            "bd" | "delete" => self.delete_breakpoint(&args),
+            "bl" | "breaks" => Ok(self.list_breakpoints()),
+            "m" | "mem" => self.memory(&args),
+            ">" | "w" | "write" => self.write(&args),
+            "a" | "asm" => self.assemble(rest),
+            "d" | "dis" => self.disassemble(&args),
+            "reset" => {
+                self.chip.reset();
+                Ok(self.registers())
+            }
+            "gdb" => {
+                let address = args.first().copied().unwrap_or(GDB_ADDRESS);
+                gdb::listen(&mut self.chip, address).map_err(|e| format!("{}: {}", address, e))?;
+                Ok(format!("the debugger detached\n{}", self.registers()))
+            }
+            "history" | "h" => Ok(self.list_history()),
+            "source" | "script" => match args.as_slice() {
+                [file] => self.source(file),
+                _ => Err("usage: source <file>".to_string()),
+            },
+            "q" | "quit" | "x" => {
+                self.quit = true;
+                Ok(String::new())
+            }
+            _ => Err(format!("unknown command `{}` (try `help`)", name)),
+        }
+    }
+
+    /// Runs the commands of a script, stops at the first error
+    pub fn run_script(&mut self, text: &str) -> Result<String, String> {
+        let mut output = String::new();
+        for (i, line) in text.lines().enumerate() {
+            if self.quit {
+                break;
+            }
+            match self.command(line) {
+                Ok(text) => output.push_str(&text),
+                Err(e) => {
+                    output.push_str(&format!("line {}: {}\n", i + 1, e));
+                    return Err(output);
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// Runs at most `max` instructions and returns why it stopped
+    /// (with the registers), `None` if it did not stop before that
+    pub fn run(&mut self, max: u64) -> Option<String> {
+        for _ in 0..max {
+            let pc = self.chip.pc;
+            self.chip.execute_cycle();
+            if self.chip.pc == pc {
+                return Some(format!("trapped at ${:04X}\n{}", pc, self.registers()));
+            }
+            if self.breakpoints.contains(&self.chip.pc) {
+                return Some(format!(
+                    "breakpoint at {}\n{}",
+                    self.symbols.format_address(self.chip.pc),
+                    self.registers()
+                ));
+            }
+        }
+        None
+    }
+
+    /// The registers and the next instruction
+    pub fn registers(&self) -> String {
+        let c = &self.chip;
+        let next =
+            Disassembler::with_symbols(Cpu::Mos6502, &self.symbols).disassemble(&c.memory, c.pc);
+        format!(
+            "PC   A  X  Y  SP NV-BDIZC\n{:04x} {:02x} {:02x} {:02x} {:02x} {:08b}  {}\n",
+            c.pc,
+            c.acc,
+            c.rx,
+            c.ry,
+            c.sp,
+            c.f,
+            next.text()
+        )
+    }
+
+    // =====================
+    // Commands
+    // =====================
+
+    /// Loads an image like the `load` command (a raw binary goes to
+    /// the address, $0200 if it is not given)
+    pub fn load(&mut self, file: &str, address: Option<u16>) -> Result<String, String> {
+        let error = |e: crate::loader::LoadError| format!("{}: {}", file, e);
+        let extension = Path::new(file)
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        let c = &mut self.chip;
+        match extension.as_str() {
+            "hex" | "ihx" | "ihex" => c.load_ihex(file.to_string(), true).map_err(error)?,
+            "srec" | "s19" | "s28" | "s37" | "mot" => {
+                c.load_srec(file.to_string(), true).map_err(error)?
+            }
+            "prg" => {
+                let range = c.load_prg(file.to_string(), false).map_err(error)?;
+                return Ok(format!(
+                    "loaded ${:04X}-${:04X}\n",
+                    range.start(),
+                    range.end()
+                ));
+            }
+            "nes" => {
+                c.load_ines(file.to_string()).map_err(error)?;
+            }
+            "snap" | "state" => c.load_state(file.to_string()).map_err(error)?,
+            _ => {
+                let bytes = std::fs::read(file).map_err(|e| format!("{}: {}", file, e))?;
+                if bytes.starts_with(&sim65::MAGIC) {
+                    c.load_sim65(file.to_string()).map_err(error)?;
+                } else {
+                    let address = address.unwrap_or(0x0200);
+                    let length = c
+                        .load_binary(file.to_string(), address, 0, None)
+                        .map_err(error)?;
+                    c.pc = address;
+                    return Ok(format!(
+                        "loaded ${:04X}-${:04X}\n",
+                        address,
+                        (address as usize + length).saturating_sub(1)
+                    ));
+                }
+            }
+        }
+        Ok(format!("loaded {}, PC is ${:04X}\n", file, self.chip.pc))
+    }
+
+    /// Adds the labels of a file like the `labels` command
+    pub fn load_labels(&mut self, file: &str) -> Result<String, String> {
+        let symbols = if file.ends_with(".lst") {
+            Listing::load(file.to_string()).map(|l| l.labels().clone())
+        } else {
+            let text = std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+            if text
+                .lines()
+                .any(|line| line.trim_start().starts_with("al "))
+            {
+                SymbolTable::parse_vice(&text)
+            } else {
+                SymbolTable::parse_labels(&text)
+            }
+        }
+        .map_err(|e| format!("{}: {}", file, e))?;
+        self.symbols.merge(&symbols);
+        Ok(format!("{} labels\n", symbols.len()))
+    }
+
+    fn set_registers(&mut self, args: &[&str]) -> Result<String, String> {
+        for arg in args {
+            let (name, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("`{}` is not like `a=$01`", arg))?;
+            let value = self.value(value)?;
+            let byte = || {
+                u8::try_from(value).map_err(|_| format!("${:X} does not fit into {}", value, name))
+            };
+            let c = &mut self.chip;
+            match name.to_ascii_lowercase().as_str() {
+                "a" => c.acc = byte()?,
+                "x" => c.rx = byte()?,
+                "y" => c.ry = byte()?,
+                "sp" | "s" => c.sp = byte()?,
+                "p" | "f" => c.f = byte()?,
+                "pc" => c.pc = value,
+                _ => return Err(format!("there is no register `{}`", name)),
+            }
+        }
+        Ok(self.registers())
+    }
+
+    fn step(&mut self, args: &[&str]) -> Result<String, String> {
+        let count = match args {
+            [] => 1,
+            [count] => parse_value(count).ok_or_else(|| format!("`{}` is not a number", count))?,
+            _ => return Err("usage: s [count]".to_string()),
+        };
+        let mut output = String::new();
+        for _ in 0..count {
+            let pc = self.chip.pc;
+            output.push_str(&self.next_instruction());
+            self.chip.execute_cycle();
+            output.push_str(&self.registers());
+            if self.chip.pc == pc {
+                output.push_str(&format!("trapped at ${:04X}\n", pc));
+                break;
+            }
+        }
+        Ok(output)
+    }
+
+    fn go(&mut self, args: &[&str]) -> Result<String, String> {
+        let max = match args {
+            [] => GO_LIMIT,
+            [address] => {
+                self.chip.pc = self.value(address)?;
+                GO_LIMIT
+            }
+            [address, max] => {
+                let max = parse_count(max)?;
+                self.chip.pc = self.value(address)?;
+                max
+            }
+            _ => return Err("usage: g [address] [max]".to_string()),
+        };
+        match self.run(max) {
+            Some(text) => Ok(text),
+            None => Ok(format!(
+                "stopped after {} instructions\n{}",
+                max,
+                self.registers()
+            )),
+        }
+    }
+
+    fn set_breakpoint(&mut self, args: &[&str]) -> Result<String, String> {
+        match args {
+            [] => Ok(self.list_breakpoints()),
+            [address] => {
+                self.breakpoints.insert(self.value(address)?);
+                Ok(String::new())
+            }
+            _ => Err("usage: b <address>".to_string()),
+        }
+    }
+
+    fn delete_breakpoint(&mut self, args: &[&str]) -> Result<String, String> {
+        match args {
+            [] => {
+                self.breakpoints.clear();
+                Ok(String::new())
+            }
+            [address] => {
+                let address = self.value(address)?;
+                if self.breakpoints.remove(&address) {
+                    Ok(String::new())
+                } else {
+                    Err(format!("there is no breakpoint at ${:04X}", address))
+                }
+            }
+            _ => Err("usage: bd [address]".to_string()),
+        }
+    }
+
+    fn list_breakpoints(&self) -> String {
+        self.breakpoints
+            .iter()
+            .map(|address| format!("{}\n", self.symbols.format_address(*address)))
+            .collect()
+    }
+
+    fn memory(&mut self, args: &[&str]) -> Result<String, String> {
+        let range = match args {
+            [] => self.next_memory..=self.next_memory.saturating_add(MEMORY_LINES - 1),
+            [text] => self.range(text)?,
+            _ => return Err("usage: m [address|range]".to_string()),
+        };
+        self.next_memory = range.end().wrapping_add(1);
+        Ok(dump::hexdump(&self.chip, range, Charset::Ascii))
+    }
+
+    fn write(&mut self, args: &[&str]) -> Result<String, String> {
+        let (address, bytes) = match args.split_first() {
+            Some((address, bytes)) if !bytes.is_empty() => (self.value(address)?, bytes),
+            _ => return Err("usage: > <address> <byte...>".to_string()),
+        };
+        let bytes = bytes
+            .iter()
+            .map(|text| {
+                parse_value(text)
+                    .and_then(|value| u8::try_from(value).ok())
+                    .ok_or_else(|| format!("`{}` is not a byte", text))
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+        self.chip
+            .load_bytes(address, &bytes)
+            .map_err(|e| e.to_string())?;
+        Ok(String::new())
+    }
+
+    fn assemble(&mut self, rest: &str) -> Result<String, String> {
+        let (address, instruction) = rest
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| "usage: a <address> <instruction>".to_string())?;
+        let address = self.value(address)?;
+        // the labels are known to the assembler
+        let mut assembler = Assembler::new(Cpu::Mos6502);
+        for (name, value) in self.symbols.iter() {
+            assembler.define(name, value as i64);
+        }
+        let text = format!("        {}\n", instruction.trim());
+        let (_, bytes) = assembler
+            .assemble_at(&text, address)
+            .map_err(|e| e.message)?
+            .binary();
+        self.chip
+            .load_bytes(address, &bytes)
+            .map_err(|e| e.to_string())?;
+        let disassembler = Disassembler::with_symbols(Cpu::Mos6502, &self.symbols);
+        Ok(format!(
+            "{}\n",
+            disassembler.disassemble(&self.chip.memory, address)
+        ))
+    }
+
+    fn disassemble(&mut self, args: &[&str]) -> Result<String, String> {
+        let (address, count) = match args {
+            [] => (self.next_disassembly.unwrap_or(self.chip.pc), INSTRUCTIONS),
+            [address] => (self.value(address)?, INSTRUCTIONS),
+            [address, count] => (
+                self.value(address)?,
+                parse_value(count).ok_or_else(|| format!("`{}` is not a number", count))?,
+            ),
+            _ => return Err("usage: d [address] [count]".to_string()),
+        };
+        let disassembler = Disassembler::with_symbols(Cpu::Mos6502, &self.symbols);
+        let mut output = String::new();
+        let mut address = address;
+        for _ in 0..count {
+            if let Some(label) = self.symbols.label_at(address) {
+                output.push_str(&format!("{}:\n", label));
+            }
+            let instruction = disassembler.disassemble(&self.chip.memory, address);
+            output.push_str(&format!("{}\n", instruction));
+            address = address.wrapping_add(instruction.length() as u16);
+        }
+        self.next_disassembly = Some(address);
+        Ok(output)
+    }
+
+    fn list_history(&self) -> String {
+        self.history
+            .iter()
+            .enumerate()
+            .map(|(i, line)| format!("{:4}  {}\n", i + 1, line))
+            .collect()
+    }
+
+    fn source(&mut self, file: &str) -> Result<String, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("{}: the scripts are nested too deep", file));
+        }
+        let text = std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+        self.depth += 1;
+        let result = self.run_script(&text);
+        self.depth -= 1;
+        result.map_err(|output| format!("{}{}: the script stopped", output, file))
+    }
+
+    // =====================
+    // Helper functions
+    // =====================
+
+    /// The instruction at the program counter, like the disassembler shows it
+    fn next_instruction(&self) -> String {
+        let disassembler = Disassembler::with_symbols(Cpu::Mos6502, &self.symbols);
+        format!(
+            "{}\n",
+            disassembler.disassemble(&self.chip.memory, self.chip.pc)
+        )
+    }
+
+    /// A label or a number
+    fn value(&self, text: &str) -> Result<u16, String> {
+        self.symbols
+            .get(text)
+            .or_else(|| parse_value(text))
+            .ok_or_else(|| format!("`{}` is neither a number nor a label", text))
+    }
+
+    /// `$0200-$02FF`, `$0200+256` or an address (with the default length)
+    fn range(&self, text: &str) -> Result<RangeInclusive<u16>, String> {
+        if text.contains('-') || text.contains('+') {
+            return dump::parse_range(text)
+                .ok_or_else(|| format!("`{}` is not a range like $0200-$02FF", text));
+        }
+        let start = self.value(text)?;
+        Ok(start..=start.saturating_add(MEMORY_LINES - 1))
+    }
+}
+
+/// A number of instructions (decimal, or hex with `$` or `0x`)
+fn parse_count(text: &str) -> Result<u64, String> {
+    let count = if let Some(hex) = text.strip_prefix('$').or_else(|| text.strip_prefix("0x")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse().ok()
+    };
+    count.ok_or_else(|| format!("`{}` is not a number", text))
+}
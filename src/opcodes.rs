@@ -0,0 +1,135 @@
+// The metadata of the 256 opcodes of the 6502
+//
+// For every opcode there is its mnemonic and addressing mode (from the
+// tables of the disassembler), its length, the cycles it takes and the
+// flags it reads and writes.
+//
+// Indexed reads (and the (zp),Y ones) take one more cycle if the index
+// crosses a page, branches take one more cycle if they are taken and
+// another one if the target is on another page.
+//
+// The chip decodes every opcode with this table, so what it executes
+// is always what the table says.
+//
+// Reference: https://www.masswerk.at/6502/6502_instruction_set.html
+
+use std::sync::OnceLock;
+
+use crate::chip::{Cpu, C, D, I, N, V, Z};
+use crate::disassembler::opcode;
+pub use crate::disassembler::Mode;
+pub use crate::instruction::Mnemonic;
+
+/// All the flags that are in the status register
+/// (the B flag and bit 5 only exist when it is pushed)
+pub const ALL_FLAGS: u8 = N | V | D | I | Z | C;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Metadata {
+    pub opcode: u8,
+    pub mnemonic: &'static str,
+    pub mode: Mode,
+    // The number of bytes (with the opcode):
+    pub length: usize,
+    pub cycles: u8,
+    // The cycles that get added when a page is crossed:
+    pub page_penalty: u8,
+    // The flags (like `N | Z`):
+    pub reads: u8,
+    pub writes: u8,
+    pub documented: bool,
+    // The mnemonic the chip executes (`None` if undocumented):
+    pub instruction: Option<Mnemonic>,
+}
+
+/// The metadata of all opcodes
+pub fn table() -> &'static [Metadata; 256] {
+    static TABLE: OnceLock<[Metadata; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        std::array::from_fn(|i| {
+            let op = opcode(Cpu::Mos6502, i as u8);
+            let (reads, writes) = flags(op.mnemonic);
+            Metadata {
+                opcode: i as u8,
+                mnemonic: op.mnemonic,
+                mode: op.mode,
+                length: op.mode.length(),
+                cycles: CYCLES[i],
+                page_penalty: page_penalty(op.mode, CYCLES[i]),
+                reads,
+                writes,
+                documented: op.documented,
+                instruction: Mnemonic::parse(op.mnemonic).filter(|_| op.documented),
+            }
+        })
+    })
+}
+
+/// The metadata of an opcode
+pub fn metadata(opcode: u8) -> &'static Metadata {
+    &table()[opcode as usize]
+}
+
+/// The documented opcode of the mnemonic with the addressing mode
+pub fn find(mnemonic: Mnemonic, mode: Mode) -> Option<&'static Metadata> {
+    table()
+        .iter()
+        .find(|m| m.instruction == Some(mnemonic) && m.mode == mode)
+}
+
+/// The indexed reads are the ones that are not slowed down anyway
+/// (writes and read-modify-writes always take the extra cycle)
+fn page_penalty(mode: Mode, cycles: u8) -> u8 {
+    match (mode, cycles) {
+        (Mode::AbsoluteX, 4) | (Mode::AbsoluteY, 4) | (Mode::IndirectY, 5) => 1,
+        (Mode::Relative, _) => 1,
+        _ => 0,
+    }
+}
+
+/// The flags that a mnemonic reads and writes
+fn flags(mnemonic: &str) -> (u8, u8) {
+    match mnemonic {
+        "ADC" | "SBC" | "RRA" | "ISC" | "ARR" => (C | D, N | V | Z | C),
+        "AND" | "ORA" | "EOR" | "LDA" | "LDX" | "LDY" | "LAX" | "LAS" | "ANE" | "LXA" => (0, N | Z),
+        "TAX" | "TAY" | "TSX" | "TXA" | "TYA" | "PLA" => (0, N | Z),
+        "INC" | "INX" | "INY" | "DEC" | "DEX" | "DEY" => (0, N | Z),
+        "ASL" | "LSR" | "SLO" | "SRE" | "ANC" | "ALR" => (0, N | Z | C),
+        "ROL" | "ROR" | "RLA" => (C, N | Z | C),
+        "CMP" | "CPX" | "CPY" | "DCP" | "SBX" => (0, N | Z | C),
+        "BIT" => (0, N | V | Z),
+        "BCC" | "BCS" => (C, 0),
+        "BEQ" | "BNE" => (Z, 0),
+        "BMI" | "BPL" => (N, 0),
+        "BVC" | "BVS" => (V, 0),
+        "CLC" | "SEC" => (0, C),
+        "CLD" | "SED" => (0, D),
+        "CLI" | "SEI" => (0, I),
+        "CLV" => (0, V),
+        "PHP" => (ALL_FLAGS, 0),
+        "BRK" => (ALL_FLAGS, I),
+        "PLP" | "RTI" => (0, ALL_FLAGS),
+        _ => (0, 0),
+    }
+}
+
+// The JAMs stop the 6502, they have no cycles
+#[rustfmt::skip]
+const CYCLES: [u8; 256] = [
+    7, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 4, 4, 6, 6, // $00
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // $10
+    6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 4, 4, 6, 6, // $20
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // $30
+    6, 6, 0, 8, 3, 3, 5, 5, 3, 2, 2, 2, 3, 4, 6, 6, // $40
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // $50
+    6, 6, 0, 8, 3, 3, 5, 5, 4, 2, 2, 2, 5, 4, 6, 6, // $60
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // $70
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // $80
+    2, 6, 0, 6, 4, 4, 4, 4, 2, 5, 2, 5, 5, 5, 5, 5, // $90
+    2, 6, 2, 6, 3, 3, 3, 3, 2, 2, 2, 2, 4, 4, 4, 4, // $A0
+    2, 5, 0, 5, 4, 4, 4, 4, 2, 4, 2, 4, 4, 4, 4, 4, // $B0
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // $C0
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // $D0
+    2, 6, 2, 8, 3, 3, 5, 5, 2, 2, 2, 2, 4, 4, 6, 6, // $E0
+    2, 5, 0, 8, 4, 4, 6, 6, 2, 4, 2, 7, 4, 4, 7, 7, // $F0
+];
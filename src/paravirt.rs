@@ -0,0 +1,303 @@
+// The paravirtualization hooks of sim65
+//
+// Programs that cc65 builds for the `sim6502` target do their I/O by
+// JSRing into the last bytes of the memory. When the program counter
+// reaches one of these addresses the host does the work and then
+// returns from the subroutine like an RTS would.
+//
+// The arguments follow the cc65 calling convention: the last argument
+// is in A (low) and X (high), the others are on the C stack, pointed
+// to by the zeropage word at `sp_address` (from the sim65 header).
+// The return value goes back into A and X.
+//
+// Reference: https://github.com/cc65/cc65/blob/master/src/sim65/paravirt.c
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+
+use crate::chip::Chip;
+
+// The hook addresses
+pub const PV_OPEN: u16 = 0xFFF4;
+pub const PV_CLOSE: u16 = 0xFFF5;
+pub const PV_READ: u16 = 0xFFF6;
+pub const PV_WRITE: u16 = 0xFFF7;
+pub const PV_ARGS: u16 = 0xFFF8;
+pub const PV_EXIT: u16 = 0xFFF9;
+
+// The open flags of cc65 (fcntl.h)
+const O_RDONLY: u16 = 0x01;
+const O_WRONLY: u16 = 0x02;
+const O_RDWR: u16 = 0x03;
+const O_CREAT: u16 = 0x10;
+const O_TRUNC: u16 = 0x20;
+const O_APPEND: u16 = 0x40;
+const O_EXCL: u16 = 0x80;
+
+// What is returned on an error (-1)
+const ERROR: u16 = 0xFFFF;
+
+/// How many instructions a program runs if no other limit is given
+pub const MAX_INSTRUCTIONS: u64 = 100_000_000;
+/// The exit code of sim65 when a program hit the limit
+pub const EXIT_TIMEOUT: u8 = 0x7E;
+
+/// The host side of a sim65 program
+pub struct Paravirt {
+    // Zeropage address of the C stack pointer:
+    sp_address: u8,
+    // argv (including the program name):
+    args: Vec<String>,
+    stdin: Box<dyn Read>,
+    stdout: Box<dyn Write>,
+    stderr: Box<dyn Write>,
+    // The files opened by the program, by file descriptor:
+    files: HashMap<u16, File>,
+}
+
+impl Paravirt {
+    /// Creates the hooks with the stdin, stdout and stderr of the host
+    pub fn new(sp_address: u8, args: Vec<String>) -> Paravirt {
+        Paravirt::with_io(
+            sp_address,
+            args,
+            Box::new(io::stdin()),
+            Box::new(io::stdout()),
+            Box::new(io::stderr()),
+        )
+    }
+
+    /// Creates the hooks with the given streams for file descriptor 0, 1 and 2
+    pub fn with_io(
+        sp_address: u8,
+        args: Vec<String>,
+        stdin: Box<dyn Read>,
+        stdout: Box<dyn Write>,
+        stderr: Box<dyn Write>,
+    ) -> Paravirt {
+        Paravirt {
+            sp_address,
+            args,
+            stdin,
+            stdout,
+            stderr,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Executes one instruction, or a hook if the program counter is on one.
+    ///
+    /// Returns the exit code once the program called exit.
+    pub fn step(&mut self, c: &mut Chip) -> Option<u8> {
+        match c.pc {
+            PV_OPEN => self.open(c),
+            PV_CLOSE => self.close(c),
+            PV_READ => self.read(c),
+            PV_WRITE => self.write(c),
+            PV_ARGS => self.args(c),
+            PV_EXIT => {
+                let _ = self.stdout.flush();
+                let _ = self.stderr.flush();
+                return Some(c.acc);
+            }
+            _ => {
+                c.execute_cycle();
+                return None;
+            }
+        }
+        return_from_subroutine(c);
+        None
+    }
+
+    /// Runs the program until it exits and returns the exit code,
+    /// or `None` if it did not exit within `max` instructions
+    /// (like the `-x` option of sim65)
+    pub fn run(&mut self, c: &mut Chip, max: u64) -> Option<u8> {
+        for _ in 0..max {
+            if let Some(code) = self.step(c) {
+                return Some(code);
+            }
+        }
+        let _ = self.stdout.flush();
+        let _ = self.stderr.flush();
+        None
+    }
+
+    /// Pops a parameter from the C stack
+    fn pop_param(&self, c: &mut Chip, size: u16) -> u16 {
+        let sp = read_word(c, self.sp_address as u16);
+        let value = read_word(c, sp);
+        write_word(c, self.sp_address as u16, sp.wrapping_add(size));
+        value
+    }
+
+    // int open (const char* name, int flags, ...)
+    fn open(&mut self, c: &mut Chip) {
+        // The number of bytes of the arguments is in Y,
+        // the mode is only there if it was given
+        let mode_size = (c.ry as u16).saturating_sub(4);
+        self.pop_param(c, mode_size);
+        let flags = self.pop_param(c, 2);
+        let name = self.pop_param(c, 2);
+
+        let mut path = Vec::new();
+        let mut address = name;
+        while c.memory[address as usize] != 0 {
+            path.push(c.memory[address as usize]);
+            address = address.wrapping_add(1);
+        }
+        let path = String::from_utf8_lossy(&path).to_string();
+
+        let mut options = OpenOptions::new();
+        match flags & 0x03 {
+            O_RDONLY => options.read(true),
+            O_WRONLY => options.write(true),
+            O_RDWR => options.read(true).write(true),
+            _ => options.read(true),
+        };
+        if flags & O_EXCL == O_EXCL && flags & O_CREAT == O_CREAT {
+            options.create_new(true);
+        } else if flags & O_CREAT == O_CREAT {
+            options.create(true);
+        }
+        if flags & O_TRUNC == O_TRUNC {
+            options.truncate(true);
+        }
+        if flags & O_APPEND == O_APPEND {
+            options.append(true);
+        }
+
+        let result = match options.open(path) {
+            Ok(file) => {
+                let fd = (3..ERROR)
+                    .find(|fd| !self.files.contains_key(fd))
+                    .unwrap_or(ERROR);
+                self.files.insert(fd, file);
+                fd
+            }
+            Err(_) => ERROR,
+        };
+        set_ax(c, result);
+    }
+
+    // int close (int fd)
+    fn close(&mut self, c: &mut Chip) {
+        let fd = get_ax(c);
+        let result = match fd {
+            0..=2 => 0,
+            _ => match self.files.remove(&fd) {
+                Some(_) => 0,
+                None => ERROR,
+            },
+        };
+        set_ax(c, result);
+    }
+
+    // int read (int fd, void* buf, unsigned count)
+    fn read(&mut self, c: &mut Chip) {
+        let count = get_ax(c);
+        let buf = self.pop_param(c, 2);
+        let fd = self.pop_param(c, 2);
+
+        let mut data = vec![0; count as usize];
+        let result = match fd {
+            0 => self.stdin.read(&mut data),
+            1 | 2 => Err(io::Error::from(io::ErrorKind::PermissionDenied)),
+            _ => match self.files.get_mut(&fd) {
+                Some(file) => file.read(&mut data),
+                None => Err(io::Error::from(io::ErrorKind::NotFound)),
+            },
+        };
+        let result = match result {
+            Ok(read) => {
+                for (i, byte) in data[..read].iter().enumerate() {
+                    c.memory[buf.wrapping_add(i as u16) as usize] = *byte;
+                }
+                read as u16
+            }
+            Err(_) => ERROR,
+        };
+        set_ax(c, result);
+    }
+
+    // int write (int fd, const void* buf, unsigned count)
+    fn write(&mut self, c: &mut Chip) {
+        let count = get_ax(c);
+        let buf = self.pop_param(c, 2);
+        let fd = self.pop_param(c, 2);
+
+        let data: Vec<u8> = (0..count)
+            .map(|i| c.memory[buf.wrapping_add(i) as usize])
+            .collect();
+        let result = match fd {
+            0 => Err(io::Error::from(io::ErrorKind::PermissionDenied)),
+            1 => self.stdout.write_all(&data),
+            2 => self.stderr.write_all(&data),
+            _ => match self.files.get_mut(&fd) {
+                Some(file) => file.write_all(&data),
+                None => Err(io::Error::from(io::ErrorKind::NotFound)),
+            },
+        };
+        set_ax(c, if result.is_ok() { count } else { ERROR });
+    }
+
+    // void __fastcall__ args (int* argc, char*** argv)
+    //
+    // The strings and the argv array are put on the C stack,
+    // argv is written to the address in A and X and argc is returned.
+    fn args(&mut self, c: &mut Chip) {
+        let argv = get_ax(c);
+        let mut sp = read_word(c, self.sp_address as u16);
+        let argc = self.args.len() as u16;
+
+        // The array of pointers (and the NULL at its end)
+        let mut pointer = sp.wrapping_sub((argc + 1) * 2);
+        write_word(c, argv, pointer);
+        sp = pointer;
+        for arg in &self.args {
+            let bytes = arg.as_bytes();
+            sp = sp.wrapping_sub(bytes.len() as u16 + 1);
+            for (i, byte) in bytes.iter().chain([0].iter()).enumerate() {
+                c.memory[sp.wrapping_add(i as u16) as usize] = *byte;
+            }
+            write_word(c, pointer, sp);
+            pointer = pointer.wrapping_add(2);
+        }
+        write_word(c, pointer, 0);
+
+        write_word(c, self.sp_address as u16, sp);
+        set_ax(c, argc);
+    }
+}
+
+/// Does what an RTS does
+fn return_from_subroutine(c: &mut Chip) {
+    let ll = c.memory[0x0100 + c.sp.wrapping_add(1) as usize];
+    let hh = c.memory[0x0100 + c.sp.wrapping_add(2) as usize];
+    c.sp = c.sp.wrapping_add(2);
+    c.pc = u16::from_le_bytes([ll, hh]).wrapping_add(1);
+}
+
+fn get_ax(c: &Chip) -> u16 {
+    u16::from_le_bytes([c.acc, c.rx])
+}
+
+fn set_ax(c: &mut Chip, value: u16) {
+    let [ll, hh] = value.to_le_bytes();
+    c.acc = ll;
+    c.rx = hh;
+}
+
+fn read_word(c: &Chip, address: u16) -> u16 {
+    u16::from_le_bytes([
+        c.memory[address as usize],
+        c.memory[address.wrapping_add(1) as usize],
+    ])
+}
+
+fn write_word(c: &mut Chip, address: u16, value: u16) {
+    let [ll, hh] = value.to_le_bytes();
+    c.memory[address as usize] = ll;
+    c.memory[address.wrapping_add(1) as usize] = hh;
+}
@@ -0,0 +1,93 @@
+// The state a chip powers up with.
+//
+// Real hardware does not start with a clean memory and clean registers,
+// it starts with whatever the RAM cells and latches settled on.
+// To test programs against that, the fill of the memory and the
+// registers can be chosen here, and everything random is
+// reproducible from a seed.
+
+/// How the memory gets filled on power up
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum MemoryFill {
+    /// every byte is 0x00
+    Zeros,
+    /// every byte is 0xFF
+    Ones,
+    /// the bytes alternate between 0x00 and 0xFF
+    Alternating,
+    /// every byte is pseudo random (depending on the seed)
+    Random,
+}
+
+/// The configuration for `Chip::power_on`
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct PowerOn {
+    // How the memory is filled:
+    pub fill: MemoryFill,
+    // Randomize the accumulator, x, y and the flags:
+    pub random_registers: bool,
+    // The seed for everything random:
+    pub seed: u64,
+    // Where the program counter starts:
+    pub pc: u16,
+}
+
+impl Default for PowerOn {
+    /// The same state that `Chip::new` always had
+    fn default() -> PowerOn {
+        PowerOn {
+            fill: MemoryFill::Zeros,
+            random_registers: false,
+            seed: 0,
+            pc: 0x200,
+        }
+    }
+}
+
+/// A small xorshift64* pseudo random number generator
+///
+/// It is not good for anything but producing garbage,
+/// but the garbage is the same for the same seed.
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        // xorshift gets stuck on a zero state,
+        // so the seed is mixed with a constant first
+        let state = seed ^ 0x9E37_79B9_7F4A_7C15;
+        Rng {
+            state: if state == 0 { 1 } else { state },
+        }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub(crate) fn next_byte(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
+
+/// Fills the memory with the given pattern
+pub(crate) fn fill_memory(memory: &mut [u8], fill: MemoryFill, rng: &mut Rng) {
+    match fill {
+        MemoryFill::Zeros => memory.fill(0x00),
+        MemoryFill::Ones => memory.fill(0xFF),
+        MemoryFill::Alternating => {
+            for (i, byte) in memory.iter_mut().enumerate() {
+                *byte = if i % 2 == 0 { 0x00 } else { 0xFF };
+            }
+        }
+        MemoryFill::Random => {
+            for byte in memory.iter_mut() {
+                *byte = rng.next_byte();
+            }
+        }
+    }
+}
@@ -0,0 +1,380 @@
+// Building 6502 programs in Rust
+//
+// Program::new(0x200)
+//     .ldx_imm(5)
+//     .label("loop")
+//     .dex()
+//     .bne("loop")
+//     .rts()
+//     .build()
+//
+// There is a method for every instruction of the 6502 and addressing
+// mode (`lda_imm`, `lda_zp`, `lda_zpx`, `lda_abs`, `lda_absx`,
+// `lda_absy`, `lda_indx`, `lda_indy`, `asl_a`, `jmp_ind`, ...).
+// Branches, `jmp` and `jsr` go to labels, the labels can be defined
+// before or after they are used and are resolved by `build`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::instruction::{Instruction, Mnemonic, Mode, Operand};
+use crate::opcodes;
+use crate::symbols::SymbolTable;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ProgramError {
+    DuplicateLabel(String),
+    UndefinedLabel(String),
+    // The label and how far away it is:
+    BranchOutOfRange(String, i32),
+    // The 6502 does not have the instruction (with this addressing mode):
+    UnknownInstruction(String),
+    // The program goes past $FFFF:
+    TooLong(usize),
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProgramError::DuplicateLabel(label) => write!(f, "`{}` is defined twice", label),
+            ProgramError::UndefinedLabel(label) => write!(f, "`{}` is not defined", label),
+            ProgramError::BranchOutOfRange(label, offset) => write!(
+                f,
+                "the branch to `{}` is {} bytes away (-128 to 127)",
+                label, offset
+            ),
+            ProgramError::UnknownInstruction(instruction) => {
+                write!(f, "the 6502 has no `{}`", instruction)
+            }
+            ProgramError::TooLong(length) => {
+                write!(f, "{} bytes do not fit into the memory", length)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProgramError {}
+
+/// A use of a label that `build` fills in
+#[derive(Debug, PartialEq, Clone)]
+struct Fixup {
+    // The index of the operand in the bytes:
+    at: usize,
+    label: String,
+    relative: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Program {
+    origin: u16,
+    bytes: Vec<u8>,
+    labels: HashMap<String, u16>,
+    fixups: Vec<Fixup>,
+    // The first error (it is returned by `build`):
+    error: Option<ProgramError>,
+}
+
+impl Program {
+    /// A program that starts at `origin`
+    pub fn new(origin: u16) -> Program {
+        Program {
+            origin,
+            bytes: Vec::new(),
+            labels: HashMap::new(),
+            fixups: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// The address of the next instruction
+    pub fn address(&self) -> u16 {
+        self.origin.wrapping_add(self.bytes.len() as u16)
+    }
+
+    /// The labels that are defined so far
+    pub fn symbols(&self) -> SymbolTable {
+        let mut symbols = SymbolTable::new();
+        for (label, address) in &self.labels {
+            symbols.insert(label, *address);
+        }
+        symbols
+    }
+
+    /// The bytes of the program with all labels resolved
+    pub fn build(&self) -> Result<Vec<u8>, ProgramError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        if self.origin as usize + self.bytes.len() > 0x10000 {
+            return Err(ProgramError::TooLong(self.bytes.len()));
+        }
+        let mut bytes = self.bytes.clone();
+        for fixup in &self.fixups {
+            let target = match self.labels.get(&fixup.label) {
+                Some(target) => *target,
+                None => return Err(ProgramError::UndefinedLabel(fixup.label.clone())),
+            };
+            if fixup.relative {
+                let next = self.origin as i32 + fixup.at as i32 + 1;
+                let offset = target as i32 - next;
+                if !(-128..=127).contains(&offset) {
+                    return Err(ProgramError::BranchOutOfRange(fixup.label.clone(), offset));
+                }
+                bytes[fixup.at] = offset as u8;
+            } else {
+                bytes[fixup.at..fixup.at + 2].copy_from_slice(&target.to_le_bytes());
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Defines a label at the current address
+    pub fn label(mut self, label: &str) -> Program {
+        if self.labels.contains_key(label) {
+            return self.fail(ProgramError::DuplicateLabel(label.to_string()));
+        }
+        let address = self.address();
+        self.labels.insert(label.to_string(), address);
+        self
+    }
+
+    /// Adds an instruction
+    pub fn instruction(mut self, instruction: Instruction) -> Program {
+        let mode = instruction.operand.mode();
+        match opcodes::find(instruction.mnemonic, mode) {
+            Some(metadata) => {
+                self.bytes.push(metadata.opcode);
+                self.bytes.extend_from_slice(&instruction.operand.bytes());
+                self
+            }
+            None => self.fail(ProgramError::UnknownInstruction(instruction.to_string())),
+        }
+    }
+
+    /// Adds bytes (data)
+    pub fn bytes(mut self, bytes: &[u8]) -> Program {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    /// Adds words (little endian)
+    pub fn words(mut self, words: &[u16]) -> Program {
+        for word in words {
+            self.bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        self
+    }
+
+    /// Adds the address of a label (like `dw label`)
+    pub fn address_of(mut self, label: &str) -> Program {
+        self.fixups.push(Fixup {
+            at: self.bytes.len(),
+            label: label.to_string(),
+            relative: false,
+        });
+        self.bytes.extend_from_slice(&[0, 0]);
+        self
+    }
+
+    pub fn jmp(self, label: &str) -> Program {
+        self.label_operand(Mnemonic::Jmp, Operand::Absolute(0), label)
+    }
+
+    pub fn jsr(self, label: &str) -> Program {
+        self.label_operand(Mnemonic::Jsr, Operand::Absolute(0), label)
+    }
+
+    /// An instruction whose operand is the address of a label
+    fn label_operand(self, mnemonic: Mnemonic, operand: Operand, label: &str) -> Program {
+        let at = self.bytes.len() + 1;
+        let mut program = self.instruction(Instruction { mnemonic, operand });
+        program.fixups.push(Fixup {
+            at,
+            label: label.to_string(),
+            relative: operand.mode() == Mode::Relative,
+        });
+        program
+    }
+
+    /// Keeps the first error
+    fn fail(mut self, error: ProgramError) -> Program {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+        self
+    }
+}
+
+/// Methods for instructions without an operand
+macro_rules! implied {
+    ($($name:ident $mnemonic:ident $operand:ident;)*) => {
+        $(
+            pub fn $name(self) -> Program {
+                self.instruction(Instruction {
+                    mnemonic: Mnemonic::$mnemonic,
+                    operand: Operand::$operand,
+                })
+            }
+        )*
+    };
+}
+
+/// Methods for instructions with a value
+macro_rules! operand {
+    ($($name:ident $mnemonic:ident $operand:ident $type:ty;)*) => {
+        $(
+            pub fn $name(self, value: $type) -> Program {
+                self.instruction(Instruction {
+                    mnemonic: Mnemonic::$mnemonic,
+                    operand: Operand::$operand(value),
+                })
+            }
+        )*
+    };
+}
+
+/// Methods for branches to a label
+macro_rules! branch {
+    ($($name:ident $mnemonic:ident;)*) => {
+        $(
+            pub fn $name(self, label: &str) -> Program {
+                self.label_operand(Mnemonic::$mnemonic, Operand::Relative(0), label)
+            }
+        )*
+    };
+}
+
+/// Methods for the instructions that read a value
+/// (they have all the addressing modes but zeropage,Y)
+macro_rules! read {
+    ($($mnemonic:ident $imm:ident $zp:ident $zpx:ident $abs:ident $absx:ident
+        $absy:ident $indx:ident $indy:ident;)*) => {
+        $(
+            operand! {
+                $imm $mnemonic Immediate u8;
+                $zp $mnemonic Zeropage u8;
+                $zpx $mnemonic ZeropageX u8;
+                $abs $mnemonic Absolute u16;
+                $absx $mnemonic AbsoluteX u16;
+                $absy $mnemonic AbsoluteY u16;
+                $indx $mnemonic XIndirect u8;
+                $indy $mnemonic IndirectY u8;
+            }
+        )*
+    };
+}
+
+/// Methods for the shifts, rotations, increments and decrements
+macro_rules! modify {
+    ($($mnemonic:ident $zp:ident $zpx:ident $abs:ident $absx:ident;)*) => {
+        $(
+            operand! {
+                $zp $mnemonic Zeropage u8;
+                $zpx $mnemonic ZeropageX u8;
+                $abs $mnemonic Absolute u16;
+                $absx $mnemonic AbsoluteX u16;
+            }
+        )*
+    };
+}
+
+impl Program {
+    implied! {
+        brk Brk Implied;
+        clc Clc Implied;
+        cld Cld Implied;
+        cli Cli Implied;
+        clv Clv Implied;
+        dex Dex Implied;
+        dey Dey Implied;
+        inx Inx Implied;
+        iny Iny Implied;
+        nop Nop Implied;
+        pha Pha Implied;
+        php Php Implied;
+        pla Pla Implied;
+        plp Plp Implied;
+        rti Rti Implied;
+        rts Rts Implied;
+        sec Sec Implied;
+        sed Sed Implied;
+        sei Sei Implied;
+        tax Tax Implied;
+        tay Tay Implied;
+        tsx Tsx Implied;
+        txa Txa Implied;
+        txs Txs Implied;
+        tya Tya Implied;
+        asl_a Asl Accumulator;
+        lsr_a Lsr Accumulator;
+        rol_a Rol Accumulator;
+        ror_a Ror Accumulator;
+    }
+
+    read! {
+        Adc adc_imm adc_zp adc_zpx adc_abs adc_absx adc_absy adc_indx adc_indy;
+        And and_imm and_zp and_zpx and_abs and_absx and_absy and_indx and_indy;
+        Cmp cmp_imm cmp_zp cmp_zpx cmp_abs cmp_absx cmp_absy cmp_indx cmp_indy;
+        Eor eor_imm eor_zp eor_zpx eor_abs eor_absx eor_absy eor_indx eor_indy;
+        Lda lda_imm lda_zp lda_zpx lda_abs lda_absx lda_absy lda_indx lda_indy;
+        Ora ora_imm ora_zp ora_zpx ora_abs ora_absx ora_absy ora_indx ora_indy;
+        Sbc sbc_imm sbc_zp sbc_zpx sbc_abs sbc_absx sbc_absy sbc_indx sbc_indy;
+    }
+
+    modify! {
+        Asl asl_zp asl_zpx asl_abs asl_absx;
+        Lsr lsr_zp lsr_zpx lsr_abs lsr_absx;
+        Rol rol_zp rol_zpx rol_abs rol_absx;
+        Ror ror_zp ror_zpx ror_abs ror_absx;
+        Inc inc_zp inc_zpx inc_abs inc_absx;
+        Dec dec_zp dec_zpx dec_abs dec_absx;
+    }
+
+    operand! {
+        sta_zp Sta Zeropage u8;
+        sta_zpx Sta ZeropageX u8;
+        sta_abs Sta Absolute u16;
+        sta_absx Sta AbsoluteX u16;
+        sta_absy Sta AbsoluteY u16;
+        sta_indx Sta XIndirect u8;
+        sta_indy Sta IndirectY u8;
+        ldx_imm Ldx Immediate u8;
+        ldx_zp Ldx Zeropage u8;
+        ldx_zpy Ldx ZeropageY u8;
+        ldx_abs Ldx Absolute u16;
+        ldx_absy Ldx AbsoluteY u16;
+        ldy_imm Ldy Immediate u8;
+        ldy_zp Ldy Zeropage u8;
+        ldy_zpx Ldy ZeropageX u8;
+        ldy_abs Ldy Absolute u16;
+        ldy_absx Ldy AbsoluteX u16;
+        stx_zp Stx Zeropage u8;
+        stx_zpy Stx ZeropageY u8;
+        stx_abs Stx Absolute u16;
+        sty_zp Sty Zeropage u8;
+        sty_zpx Sty ZeropageX u8;
+        sty_abs Sty Absolute u16;
+        cpx_imm Cpx Immediate u8;
+        cpx_zp Cpx Zeropage u8;
+        cpx_abs Cpx Absolute u16;
+        cpy_imm Cpy Immediate u8;
+        cpy_zp Cpy Zeropage u8;
+        cpy_abs Cpy Absolute u16;
+        bit_zp Bit Zeropage u8;
+        bit_abs Bit Absolute u16;
+        jmp_abs Jmp Absolute u16;
+        jmp_ind Jmp Indirect u16;
+        jsr_abs Jsr Absolute u16;
+    }
+
+    branch! {
+        bcc Bcc;
+        bcs Bcs;
+        beq Beq;
+        bmi Bmi;
+        bne Bne;
+        bpl Bpl;
+        bvc Bvc;
+        bvs Bvs;
+    }
+}
@@ -0,0 +1,228 @@
+// Save states of the chip
+//
+// A snapshot is a header followed by chunks:
+//
+// "6502SNAP"  ... magic (8 bytes)
+// version     ... u16 (little endian)
+// chunks      ... tag (4 bytes), length of the data (u32), data
+//
+// REGS ... A, X, Y, P, S and the program counter (low, high)
+// MEM  ... the 64K of memory, compressed with PackBits
+// CYCL ... the cycle count (u64, little endian)
+// INTR ... the pending interrupts (bit 0: IRQ, bit 1: NMI)
+// END  ... the end of the snapshot (no data)
+//
+// Version 1 had no CYCL and INTR chunks, its snapshots are restored
+// with no cycles and no pending interrupts.
+//
+// The chip has no devices yet, their tag is reserved:
+//
+// DEVS ... the state of the devices
+//
+// A reserved chunk can not be restored by this version and is an
+// error. Chunks with any other unknown tag are skipped.
+
+use crate::chip::{Chip, MEMORY};
+use crate::loader::LoadError;
+
+pub const MAGIC: &[u8; 8] = b"6502SNAP";
+/// The newest version that can be written and read
+pub const VERSION: u16 = 2;
+
+const REGS: &[u8; 4] = b"REGS";
+const MEM: &[u8; 4] = b"MEM ";
+const CYCL: &[u8; 4] = b"CYCL";
+const INTR: &[u8; 4] = b"INTR";
+const END: &[u8; 4] = b"END ";
+/// The tags of the state that is not captured yet
+pub const RESERVED: [&[u8; 4]; 1] = [b"DEVS"];
+
+// The bits of the INTR chunk
+const IRQ: u8 = 0x01;
+const NMI: u8 = 0x02;
+
+/// Writes the state of the chip into a snapshot
+pub fn save(c: &Chip) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+
+    let [pc_ll, pc_hh] = c.pc.to_le_bytes();
+    push_chunk(
+        &mut bytes,
+        REGS,
+        &[c.acc, c.rx, c.ry, c.f, c.sp, pc_ll, pc_hh],
+    );
+    push_chunk(&mut bytes, MEM, &pack(&c.memory));
+    push_chunk(&mut bytes, CYCL, &c.cycles.to_le_bytes());
+    let mut interrupts = 0;
+    if c.irq {
+        interrupts |= IRQ;
+    }
+    if c.nmi {
+        interrupts |= NMI;
+    }
+    push_chunk(&mut bytes, INTR, &[interrupts]);
+    push_chunk(&mut bytes, END, &[]);
+    bytes
+}
+
+/// Restores the state of the chip from a snapshot.
+///
+/// The whole snapshot is checked first, nothing is changed
+/// if it can not be read.
+pub fn restore(c: &mut Chip, bytes: &[u8]) -> Result<(), LoadError> {
+    if bytes.len() < 10 || &bytes[0..8] != MAGIC {
+        return Err(LoadError::Format("not a snapshot".to_string()));
+    }
+    let version = u16::from_le_bytes([bytes[8], bytes[9]]);
+    if version == 0 || version > VERSION {
+        return Err(LoadError::Format(format!(
+            "snapshot version {} is not supported",
+            version
+        )));
+    }
+
+    let mut registers = None;
+    let mut memory = None;
+    let mut cycles = 0;
+    let mut interrupts = 0;
+    let mut rest = &bytes[10..];
+    loop {
+        if rest.len() < 8 {
+            return Err(LoadError::Format("the snapshot is cut off".to_string()));
+        }
+        let tag = [rest[0], rest[1], rest[2], rest[3]];
+        let length = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+        let data = match rest.get(8..8 + length) {
+            Some(data) => data,
+            None => return Err(LoadError::Format("the snapshot is cut off".to_string())),
+        };
+        rest = &rest[8 + length..];
+        match &tag {
+            REGS => {
+                if data.len() != 7 {
+                    return Err(LoadError::Format(
+                        "the REGS chunk has the wrong size".to_string(),
+                    ));
+                }
+                registers = Some(data);
+            }
+            MEM => memory = Some(unpack(data)?),
+            CYCL => {
+                let data: [u8; 8] = data.try_into().map_err(|_| {
+                    LoadError::Format("the CYCL chunk has the wrong size".to_string())
+                })?;
+                cycles = u64::from_le_bytes(data);
+            }
+            INTR => match data {
+                [byte] => interrupts = *byte,
+                _ => {
+                    return Err(LoadError::Format(
+                        "the INTR chunk has the wrong size".to_string(),
+                    ))
+                }
+            },
+            END => break,
+            tag if RESERVED.contains(&tag) => {
+                return Err(LoadError::Format(format!(
+                    "the {} chunk is not supported",
+                    String::from_utf8_lossy(tag)
+                )))
+            }
+            _ => {}
+        }
+    }
+
+    let (registers, memory) = match (registers, memory) {
+        (Some(registers), Some(memory)) => (registers, memory),
+        _ => {
+            return Err(LoadError::Format(
+                "the snapshot has no registers or memory".to_string(),
+            ))
+        }
+    };
+    c.acc = registers[0];
+    c.rx = registers[1];
+    c.ry = registers[2];
+    c.f = registers[3];
+    c.sp = registers[4];
+    c.pc = u16::from_le_bytes([registers[5], registers[6]]);
+    c.memory.copy_from_slice(&memory);
+    c.cycles = cycles;
+    c.irq = interrupts & IRQ != 0;
+    c.nmi = interrupts & NMI != 0;
+    Ok(())
+}
+
+fn push_chunk(bytes: &mut Vec<u8>, tag: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(tag);
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+}
+
+/// Compresses with PackBits: a header byte n is followed by
+/// n + 1 literal bytes (0 to 127) or by one byte that is
+/// repeated 257 - n times (129 to 255)
+fn pack(data: &[u8]) -> Vec<u8> {
+    let mut packed = Vec::new();
+    let mut literal: Vec<u8> = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(128)
+            .take_while(|b| **b == data[i])
+            .count();
+        if run > 2 || literal.len() == 128 {
+            flush_literal(&mut packed, &mut literal);
+        }
+        if run > 2 {
+            packed.push((257 - run) as u8);
+            packed.push(data[i]);
+            i += run;
+        } else {
+            literal.push(data[i]);
+            i += 1;
+        }
+    }
+    flush_literal(&mut packed, &mut literal);
+    packed
+}
+
+fn flush_literal(packed: &mut Vec<u8>, literal: &mut Vec<u8>) {
+    if !literal.is_empty() {
+        packed.push((literal.len() - 1) as u8);
+        packed.append(literal);
+    }
+}
+
+fn unpack(packed: &[u8]) -> Result<Vec<u8>, LoadError> {
+    let cut_off = || LoadError::Format("the MEM chunk is cut off".to_string());
+    let mut data = Vec::with_capacity(MEMORY);
+    let mut i = 0;
+    while i < packed.len() {
+        let header = packed[i] as usize;
+        i += 1;
+        match header {
+            0..=127 => {
+                let bytes = packed.get(i..i + header + 1).ok_or_else(cut_off)?;
+                data.extend_from_slice(bytes);
+                i += header + 1;
+            }
+            128 => {}
+            _ => {
+                let byte = *packed.get(i).ok_or_else(cut_off)?;
+                data.resize(data.len() + 257 - header, byte);
+                i += 1;
+            }
+        }
+    }
+    if data.len() != MEMORY {
+        return Err(LoadError::Format(format!(
+            "the memory has {} bytes and not {}",
+            data.len(),
+            MEMORY
+        )));
+    }
+    Ok(data)
+}
@@ -0,0 +1,203 @@
+// A table of symbols (labels and their addresses).
+//
+// It is filled from label files (VICE `al C:1234 .label` files or
+// plain `label = $1234` lists) or from ld65 debug info, and used by
+// everything that shows addresses to turn them back into labels.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::loader::LoadError;
+
+/// How far behind a label an address can be to still be shown
+/// relative to it (`loop+2`)
+pub const MAX_OFFSET: u16 = 0xFF;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SymbolTable {
+    by_name: HashMap<String, u16>,
+    // The names in the order they were added:
+    by_address: BTreeMap<u16, Vec<String>>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    /// Adds a symbol, a symbol with the same name is replaced
+    pub fn insert(&mut self, name: &str, address: u16) {
+        self.remove(name);
+        self.by_name.insert(name.to_string(), address);
+        self.by_address
+            .entry(address)
+            .or_default()
+            .push(name.to_string());
+    }
+
+    /// Removes a symbol and returns its address
+    pub fn remove(&mut self, name: &str) -> Option<u16> {
+        let address = self.by_name.remove(name)?;
+        if let Some(names) = self.by_address.get_mut(&address) {
+            names.retain(|n| n != name);
+            if names.is_empty() {
+                self.by_address.remove(&address);
+            }
+        }
+        Some(address)
+    }
+
+    /// Adds all symbols of the other table
+    pub fn merge(&mut self, other: &SymbolTable) {
+        for (name, address) in other.iter() {
+            self.insert(name, address);
+        }
+    }
+
+    /// The address of a symbol
+    pub fn get(&self, name: &str) -> Option<u16> {
+        self.by_name.get(name).copied()
+    }
+
+    /// All names of the address (in the order they were added)
+    pub fn labels_at(&self, address: u16) -> &[String] {
+        match self.by_address.get(&address) {
+            Some(names) => names,
+            None => &[],
+        }
+    }
+
+    /// The first name of the address
+    pub fn label_at(&self, address: u16) -> Option<&str> {
+        self.labels_at(address).first().map(|n| n.as_str())
+    }
+
+    /// The closest label at or before the address and the offset to it
+    pub fn nearest(&self, address: u16) -> Option<(&str, u16)> {
+        let (label_address, names) = self.by_address.range(..=address).next_back()?;
+        Some((names[0].as_str(), address - label_address))
+    }
+
+    /// Formats an address as `label`, `label+offset` or `$1234`
+    /// if there is no label close enough before it.
+    pub fn format_address(&self, address: u16) -> String {
+        match self.nearest(address) {
+            Some((name, 0)) => name.to_string(),
+            Some((name, offset)) if offset <= MAX_OFFSET => format!("{}+{}", name, offset),
+            _ => format!("${:04X}", address),
+        }
+    }
+
+    /// All symbols sorted by address
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u16)> {
+        self.by_address
+            .iter()
+            .flat_map(|(address, names)| names.iter().map(move |n| (n.as_str(), *address)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    /// Parses a VICE label file.
+    ///
+    /// Every `al [C:]1234 .label` line adds a label,
+    /// other monitor commands are skipped.
+    pub fn parse_vice(text: &str) -> Result<SymbolTable, LoadError> {
+        let mut table = SymbolTable::new();
+        for (i, raw) in text.lines().enumerate() {
+            let line = i + 1;
+            let mut words = raw.split_whitespace();
+            if words.next() != Some("al") {
+                continue;
+            }
+            let (address, name) = match (words.next(), words.next()) {
+                (Some(address), Some(name)) => (address, name),
+                _ => {
+                    return Err(LoadError::Syntax {
+                        line,
+                        message: "expected `al <address> <label>`".to_string(),
+                    })
+                }
+            };
+            // the memory space (C: is the computer) is optional
+            let address = address.rsplit(':').next().unwrap_or(address);
+            let address = u16::from_str_radix(address, 16).map_err(|_| LoadError::Syntax {
+                line,
+                message: format!("`{}` is not an address", address),
+            })?;
+            table.insert(name.trim_start_matches('.'), address);
+        }
+        Ok(table)
+    }
+
+    /// Parses a plain list of `label = $1234` lines.
+    ///
+    /// `:=` and `equ` work like `=`, the value can be `$1234`,
+    /// `0x1234` or decimal, and `;` starts a comment.
+    pub fn parse_labels(text: &str) -> Result<SymbolTable, LoadError> {
+        let mut table = SymbolTable::new();
+        for (i, raw) in text.lines().enumerate() {
+            let line = i + 1;
+            let raw = raw.split(';').next().unwrap_or("").trim();
+            if raw.is_empty() {
+                continue;
+            }
+            let (name, value) = match split_definition(raw) {
+                Some(definition) => definition,
+                None => {
+                    return Err(LoadError::Syntax {
+                        line,
+                        message: "expected `label = value`".to_string(),
+                    })
+                }
+            };
+            let address = parse_value(value).ok_or_else(|| LoadError::Syntax {
+                line,
+                message: format!("`{}` is not an address", value),
+            })?;
+            table.insert(name, address);
+        }
+        Ok(table)
+    }
+
+    /// Loads a VICE label file
+    pub fn load_vice(file_path: String) -> Result<SymbolTable, LoadError> {
+        SymbolTable::parse_vice(&std::fs::read_to_string(file_path)?)
+    }
+
+    /// Loads a plain list of labels
+    pub fn load_labels(file_path: String) -> Result<SymbolTable, LoadError> {
+        SymbolTable::parse_labels(&std::fs::read_to_string(file_path)?)
+    }
+}
+
+/// Splits `name = value`, `name := value` and `name equ value`
+fn split_definition(text: &str) -> Option<(&str, &str)> {
+    let (name, value) = match text.split_once('=') {
+        Some((name, value)) => (name.trim_end_matches(':').trim(), value.trim()),
+        None => {
+            let lower = text.to_ascii_lowercase();
+            let at = lower.find(" equ ")?;
+            (text[..at].trim(), text[at + 5..].trim())
+        }
+    };
+    if name.is_empty() || name.contains(char::is_whitespace) || value.is_empty() {
+        return None;
+    }
+    Some((name, value))
+}
+
+/// Parses `$1234`, `0x1234` or `1234` (decimal)
+pub(crate) fn parse_value(text: &str) -> Option<u16> {
+    if let Some(hex) = text.strip_prefix('$') {
+        u16::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = text.strip_prefix("0x") {
+        u16::from_str_radix(hex, 16).ok()
+    } else {
+        text.parse().ok()
+    }
+}
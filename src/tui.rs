@@ -0,0 +1,388 @@
+// A full-screen debugger for ANSI terminals
+//
+// It needs nothing but a terminal that understands the ANSI escape
+// codes, so it also works over SSH. The screen has 80x24 characters:
+//
+// title and keys
+// disassembly around PC        registers, flags and the stack page
+// zero page
+// memory
+// messages, or the command line after `:`
+//
+// The keys:
+//
+// s, space     step
+// g            run until a breakpoint or a trap (any key stops it)
+// b            set or delete a breakpoint at PC
+// up, down     scroll the memory pane (page up and page down by a page)
+// [, ]         scroll the zero page pane
+// :            a monitor command (like `m $0300` or `r pc=$0400`)
+// q            quit
+//
+// Everything else (loading, labels, registers, ...) goes through the
+// commands of the monitor.
+//
+// `g` does not run the program itself, the host calls `run` as long
+// as `running` is true and reads the keys in between.
+
+use crate::chip::Cpu;
+use crate::disassembler::Disassembler;
+use crate::dump::{self, Charset};
+use crate::monitor::Monitor;
+
+pub const WIDTH: usize = 80;
+pub const HEIGHT: usize = 24;
+
+/// The width of the disassembly pane
+const LEFT: usize = 44;
+/// The rows of the disassembly, registers and stack panes
+const TOP: usize = 12;
+/// The rows of the zero page and the memory pane
+const PANE: usize = 4;
+
+// The escape codes
+const CLEAR: &str = "\x1b[H\x1b[2J";
+const REVERSE: &str = "\x1b[7m";
+const BOLD: &str = "\x1b[1m";
+const NORMAL: &str = "\x1b[0m";
+
+/// A key that was pressed
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+}
+
+/// Splits what the terminal sends into keys
+/// (unknown escape sequences are dropped)
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            0x1B if bytes.get(i + 1) == Some(&b'[') => {
+                // CSI: parameters and then the final byte
+                let end = bytes[i + 2..]
+                    .iter()
+                    .position(|b| (0x40..=0x7E).contains(b))
+                    .map(|p| i + 2 + p);
+                let end = match end {
+                    Some(end) => end,
+                    None => break,
+                };
+                match &bytes[i + 2..=end] {
+                    b"A" => keys.push(Key::Up),
+                    b"B" => keys.push(Key::Down),
+                    b"5~" => keys.push(Key::PageUp),
+                    b"6~" => keys.push(Key::PageDown),
+                    _ => {}
+                }
+                i = end + 1;
+                continue;
+            }
+            0x1B => keys.push(Key::Escape),
+            b'\r' | b'\n' => keys.push(Key::Enter),
+            0x08 | 0x7F => keys.push(Key::Backspace),
+            byte if byte.is_ascii() && !byte.is_ascii_control() => {
+                keys.push(Key::Char(byte as char))
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    keys
+}
+
+pub struct Tui {
+    pub monitor: Monitor,
+    // The first address of the disassembly pane:
+    top: u16,
+    zeropage: u8,
+    memory: u16,
+    // The command line after `:`
+    input: Option<String>,
+    message: String,
+    // If `g` was pressed and the program did not stop yet:
+    running: bool,
+}
+
+impl Tui {
+    pub fn new(monitor: Monitor) -> Tui {
+        let mut tui = Tui {
+            top: monitor.chip.pc,
+            monitor,
+            zeropage: 0,
+            memory: 0x0200,
+            input: None,
+            message: String::new(),
+            running: false,
+        };
+        tui.follow_pc();
+        tui
+    }
+
+    /// If the debugger should end
+    pub fn quit(&self) -> bool {
+        self.monitor.quit()
+    }
+
+    /// The last message (the first line of what a command printed)
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// If the program runs (after `g`)
+    pub fn running(&self) -> bool {
+        self.running
+    }
+
+    /// Runs at most `count` instructions of the program if it runs
+    pub fn run(&mut self, count: u64) {
+        if !self.running {
+            return;
+        }
+        if let Some(text) = self.monitor.run(count) {
+            self.running = false;
+            self.message = text.lines().next().unwrap_or_default().to_string();
+            self.follow_pc();
+        }
+    }
+
+    /// Stops the program if it runs
+    pub fn pause(&mut self) {
+        if self.running {
+            self.running = false;
+            self.message = format!(
+                "stopped at {}",
+                self.monitor.symbols.format_address(self.monitor.chip.pc)
+            );
+            self.follow_pc();
+        }
+    }
+
+    /// Handles a key (any key stops a running program)
+    pub fn key(&mut self, key: Key) {
+        if self.running {
+            self.pause();
+            return;
+        }
+        if let Some(input) = &mut self.input {
+            match key {
+                Key::Char(c) => input.push(c),
+                Key::Backspace => {
+                    input.pop();
+                }
+                Key::Escape => self.input = None,
+                Key::Enter => {
+                    let line = self.input.take().unwrap_or_default();
+                    self.command(&line);
+                }
+                _ => {}
+            }
+            return;
+        }
+        match key {
+            Key::Char('s') | Key::Char(' ') => self.command("s"),
+            Key::Char('g') => {
+                self.running = true;
+                self.message = "running, any key stops".to_string();
+            }
+            Key::Char('b') => {
+                let pc = self.monitor.chip.pc;
+                if self.monitor.breakpoints().any(|address| address == pc) {
+                    self.command(&format!("bd ${:04X}", pc));
+                } else {
+                    self.command(&format!("b ${:04X}", pc));
+                }
+            }
+            Key::Char('q') => self.command("q"),
+            Key::Char(':') => self.input = Some(String::new()),
+            Key::Char('[') => self.zeropage = self.zeropage.wrapping_sub(16),
+            Key::Char(']') => self.zeropage = self.zeropage.wrapping_add(16),
+            Key::Up => self.memory = self.memory.wrapping_sub(16),
+            Key::Down => self.memory = self.memory.wrapping_add(16),
+            Key::PageUp => self.memory = self.memory.wrapping_sub(16 * PANE as u16),
+            Key::PageDown => self.memory = self.memory.wrapping_add(16 * PANE as u16),
+            _ => {}
+        }
+    }
+
+    /// The whole screen, starting with clearing it
+    pub fn render(&self) -> String {
+        let mut rows = Vec::with_capacity(HEIGHT);
+        let title = format!(
+            " sixfiveohtwo  PC ${:04X}   [s]tep [g]o [b]reak [:]command [q]uit",
+            self.monitor.chip.pc
+        );
+        rows.push(format!("{}{}{}", REVERSE, pad(&title, WIDTH), NORMAL));
+
+        let right = self.registers_and_stack();
+        for (left, right) in self.disassembly().iter().zip(right) {
+            rows.push(format!("{}{}", left, right));
+        }
+
+        rows.push(header(&format!("Zero page ${:02X}", self.zeropage)));
+        let start = self.zeropage as u16;
+        let end = start + 16 * PANE as u16 - 1;
+        rows.extend(self.hexdump(start, end.min(0x00FF)));
+
+        rows.push(header(&format!("Memory ${:04X}", self.memory)));
+        let end = self.memory.saturating_add(16 * PANE as u16 - 1);
+        rows.extend(self.hexdump(self.memory, end));
+
+        rows.push(match &self.input {
+            Some(input) => pad(&format!(":{}", input), WIDTH),
+            None => pad(&self.message, WIDTH),
+        });
+        while rows.len() < HEIGHT {
+            rows.push(String::new());
+        }
+
+        let mut screen = CLEAR.to_string();
+        screen.push_str(&rows.join("\r\n"));
+        screen
+    }
+
+    // =====================
+    // Helper functions
+    // =====================
+
+    /// Runs a monitor command and keeps the line of what it printed
+    /// that matters (the registers are on the screen anyway)
+    fn command(&mut self, line: &str) {
+        self.message = match self.monitor.command(line) {
+            Ok(text) => {
+                let lines: Vec<&str> = text.lines().collect();
+                let stop = lines.iter().find(|line| {
+                    line.starts_with("trapped at") || line.starts_with("breakpoint at")
+                });
+                match (stop, lines.len()) {
+                    (Some(stop), _) => stop.to_string(),
+                    (None, 0) => String::new(),
+                    (None, 1) => lines[0].to_string(),
+                    (None, _) if text.contains("PC   A") => String::new(),
+                    (None, n) => format!("{} (+{} lines)", lines[0], n - 1),
+                }
+            }
+            Err(e) => e,
+        };
+        self.follow_pc();
+    }
+
+    /// Moves the disassembly pane if PC is not (well) inside of it
+    fn follow_pc(&mut self) {
+        let pc = self.monitor.chip.pc;
+        let disassembler = Disassembler::new(Cpu::Mos6502);
+        let mut address = self.top;
+        // the last two rows do not count, the next instructions
+        // should be visible
+        for _ in 0..TOP - 3 {
+            if address == pc {
+                return;
+            }
+            address = address.wrapping_add(
+                disassembler
+                    .disassemble(&self.monitor.chip.memory, address)
+                    .length() as u16,
+            );
+        }
+        self.top = pc;
+    }
+
+    /// The rows of the disassembly pane, `>` is PC and `*` a breakpoint
+    fn disassembly(&self) -> Vec<String> {
+        let c = &self.monitor.chip;
+        let disassembler = Disassembler::with_symbols(Cpu::Mos6502, &self.monitor.symbols);
+        let mut rows = [pad_header("Disassembly", LEFT)].to_vec();
+        let mut address = self.top;
+        while rows.len() < TOP {
+            let instruction = disassembler.disassemble(&c.memory, address);
+            let breakpoint = self.monitor.breakpoints().any(|b| b == address);
+            let row = format!(
+                "{}{} {}",
+                if address == c.pc { '>' } else { ' ' },
+                if breakpoint { '*' } else { ' ' },
+                instruction
+            );
+            if address == c.pc {
+                rows.push(format!("{}{}{}", REVERSE, pad(&row, LEFT - 1), NORMAL) + " ");
+            } else {
+                rows.push(pad(&row, LEFT));
+            }
+            address = address.wrapping_add(instruction.length() as u16);
+        }
+        rows
+    }
+
+    /// The rows of the registers and the stack pane
+    fn registers_and_stack(&self) -> Vec<String> {
+        let c = &self.monitor.chip;
+        let flags: String = "NV-BDIZC"
+            .chars()
+            .enumerate()
+            .map(|(i, name)| {
+                if c.f & (0x80 >> i) != 0 {
+                    format!("{}{}{}", BOLD, name, NORMAL)
+                } else {
+                    ".".to_string()
+                }
+            })
+            .collect();
+        let mut rows = [
+            pad_header("Registers", WIDTH - LEFT),
+            format!(" A  ${:02X}   X  ${:02X}   Y  ${:02X}", c.acc, c.rx, c.ry),
+            format!(" SP ${:02X}   PC ${:04X}", c.sp, c.pc),
+            format!(" P  ${:02X}   {:08b}", c.f, c.f),
+            format!("          {}", flags),
+            pad_header("Stack", WIDTH - LEFT),
+        ]
+        .to_vec();
+        // the bytes that were pushed, the last one first
+        let mut address = 0x0100 + c.sp as usize + 1;
+        while rows.len() < TOP {
+            if address <= 0x01FF {
+                rows.push(format!(" {:04X}  {:02x}", address, c.memory[address]));
+                address += 1;
+            } else {
+                rows.push(String::new());
+            }
+        }
+        rows
+    }
+
+    fn hexdump(&self, start: u16, end: u16) -> Vec<String> {
+        let text = dump::hexdump(&self.monitor.chip, start..=end, Charset::Ascii);
+        let mut rows: Vec<String> = text.lines().map(|line| line.to_string()).collect();
+        rows.resize(PANE, String::new());
+        rows
+    }
+}
+
+/// Fills the text with spaces (or cuts it) to the width
+fn pad(text: &str, width: usize) -> String {
+    let mut text: String = text.chars().take(width).collect();
+    while text.chars().count() < width {
+        text.push(' ');
+    }
+    text
+}
+
+/// A pane title over the whole width
+fn header(title: &str) -> String {
+    pad_header(title, WIDTH)
+}
+
+/// `-- Title -----`
+fn pad_header(title: &str, width: usize) -> String {
+    let mut text = format!("-- {} ", title);
+    while text.chars().count() < width {
+        text.push('-');
+    }
+    text
+}
@@ -1,11 +1,10 @@
 use sixfiveohtwo::chip::*;
 
+// TODO: Making these tests...
+
 /// ==========================
 /// ARITHMETIC OPERATION TESTS
 /// ==========================
-
-// TODO: Making these tests...
-
 #[cfg(test)]
 mod add_with_carry {
     use crate::*;
@@ -0,0 +1,344 @@
+use sixfiveohtwo::assembler::*;
+
+/// ==========================
+/// ASSEMBLER TESTS
+/// ==========================
+#[cfg(test)]
+mod instructions {
+    use crate::*;
+
+    fn bytes(text: &str) -> Vec<u8> {
+        assemble(text).unwrap().binary().1
+    }
+
+    #[test]
+    fn addressing_modes() {
+        let text = "
+        org $200
+        lda #$01
+        sta $10
+        lda $10,x
+        ldx $10,y
+        sta $1234
+        sta $1234,x
+        sta $1234,y
+        jmp ($1234)
+        lda ($10,x)
+        lda ($10),y
+        asl a
+        lsr
+        inx
+";
+        #[rustfmt::skip]
+        let expected = [
+            0xA9, 0x01,
+            0x85, 0x10,
+            0xB5, 0x10,
+            0xB6, 0x10,
+            0x8D, 0x34, 0x12,
+            0x9D, 0x34, 0x12,
+            0x99, 0x34, 0x12,
+            0x6C, 0x34, 0x12,
+            0xA1, 0x10,
+            0xB1, 0x10,
+            0x0A,
+            0x4A,
+            0xE8,
+        ];
+        assert_eq!(bytes(text), expected);
+    }
+
+    #[test]
+    fn branches_and_labels() {
+        let text = "
+        org $200
+loop    dex
+        bne loop
+        beq done
+        nop
+done    rts
+";
+        assert_eq!(bytes(text), [0xCA, 0xD0, 0xFD, 0xF0, 0x01, 0xEA, 0x60]);
+        let assembly = assemble(text).unwrap();
+        assert_eq!(assembly.labels.get("loop"), Some(0x0200));
+        assert_eq!(assembly.labels.get("done"), Some(0x0206));
+    }
+
+    #[test]
+    fn forward_references_are_absolute() {
+        let text = "
+        org $200
+        lda later
+        lda early
+early   = $10
+later   = $20
+";
+        // `early` is known in the second pass, but not in the first
+        assert_eq!(bytes(text), [0xAD, 0x20, 0x00, 0xAD, 0x10, 0x00]);
+    }
+
+    #[test]
+    fn predefined_symbols() {
+        let mut assembler = Assembler::new(Cpu::Mos6502);
+        assembler.define("@out", 0x10);
+        assembler.define("target", 0x1234);
+        let text = "
+        org $200
+        jmp target
+target  lda #0
+";
+        let assembly = assembler.assemble(text).unwrap();
+        // the source defines `target` again
+        assert_eq!(assembly.binary().1, [0x4C, 0x03, 0x02, 0xA9, 0x00]);
+        assert!(!assembly.symbols.contains_key("@out"));
+    }
+
+    #[test]
+    fn cmos_instructions() {
+        let text = "
+        org $200
+        bra *
+        lda ($10)
+        stz $1234
+        jmp ($1234,x)
+        bbr0 $10,*
+";
+        let assembly = Assembler::new(Cpu::Wdc65C02).assemble(text).unwrap();
+        assert_eq!(
+            assembly.binary().1,
+            [0x80, 0xFE, 0xB2, 0x10, 0x9C, 0x34, 0x12, 0x7C, 0x34, 0x12, 0x0F, 0x10, 0xFD]
+        );
+        assert!(assemble("        bra *").is_err());
+    }
+}
+
+#[cfg(test)]
+mod directives {
+    use crate::*;
+
+    #[test]
+    fn data() {
+        let text = "
+        org $300
+        db 1,$ff,-1,'A',\"hi\"
+        dw $1234,table
+table   ds 3
+        align 4
+        db lo $1234, >$1234
+";
+        let (start, bytes) = assemble(text).unwrap().binary();
+        assert_eq!(start, 0x0300);
+        assert_eq!(
+            bytes,
+            [
+                1, 0xFF, 0xFF, 0x41, 0x68, 0x69, 0x34, 0x12, 0x0A, 0x03, 0, 0, 0, 0xFF, 0xFF, 0xFF,
+                0x34, 0x12
+            ]
+        );
+    }
+
+    #[test]
+    fn macros_and_conditions() {
+        let text = "
+debug   equ 0
+count   = 1
+count   = count+1
+store   macro
+        lda #\\1
+        sta \\2
+        bne skip\\?
+skip\\?
+        endm
+        org $200
+        if debug
+        brk
+        else
+        store count,$10
+        endif
+        end $200
+";
+        let assembly = assemble(text).unwrap();
+        assert_eq!(assembly.binary().1, [0xA9, 0x02, 0x85, 0x10, 0xD0, 0x00]);
+        assert_eq!(assembly.symbols["count"], 2);
+        assert_eq!(assembly.image.start, Some(0x0200));
+    }
+
+    #[test]
+    fn segments() {
+        let text = "
+        bss
+        org $10
+zp_var  ds 2
+        code
+        org $200
+        lda zp_var+1
+";
+        let assembly = assemble(text).unwrap();
+        assert_eq!(assembly.binary(), (0x0200, [0xA5, 0x11].to_vec()));
+        assert_eq!(assembly.labels.get("zp_var"), Some(0x0010));
+    }
+}
+
+#[cfg(test)]
+mod errors {
+    use crate::*;
+
+    fn message(text: &str) -> AssemblerError {
+        assemble(text).unwrap_err()
+    }
+
+    #[test]
+    fn reports_the_line() {
+        let error = message("        org $200\n        nop\n        foo #1\n");
+        assert_eq!(error.line, 3);
+        assert!(error.message.contains("foo"));
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert!(message("        lda #256").message.contains("byte"));
+        assert!(message("        lda ($1234),y")
+            .message
+            .contains("zeropage"));
+        let far = "        org $200\n        beq far\n        ds 200\nfar     rts\n";
+        assert!(message(far).message.contains("branch"));
+    }
+
+    #[test]
+    fn undefined_and_duplicate_symbols() {
+        assert!(message("        jmp nowhere").message.contains("nowhere"));
+        assert!(message("a       nop\na       nop")
+            .message
+            .contains("already"));
+        assert!(message("        if later\n        endif\nlater = 1")
+            .message
+            .contains("known"));
+    }
+
+    #[test]
+    fn overflowing_expressions() {
+        assert!(message("        lda #-(1<<63)").message.contains("byte"));
+        assert!(message("        lda #(1<<63)/-1")
+            .message
+            .contains("overflows"));
+        assert!(message("        lda #(1<<63)%-1")
+            .message
+            .contains("overflows"));
+    }
+
+    #[test]
+    fn missing_operands() {
+        // these used to assemble as `#0` and `0`
+        assert_eq!(message("        lda #").line, 1);
+        assert_eq!(message("        lda ,x").line, 1);
+        assert_eq!(message("        nop\n        jmp ()").line, 2);
+        // implied and accumulator still need none
+        assert!(assemble("        nop\n        asl\n        asl a").is_ok());
+    }
+}
+
+#[cfg(test)]
+mod functional_test {
+    use crate::*;
+
+    #[test]
+    fn is_the_same_as_the_binary() {
+        let assembly = Assembler::new(Cpu::Mos6502)
+            .assemble_file("bin/6502_functional_test.a65".to_string())
+            .unwrap();
+        let (start, bytes) = assembly.binary();
+        assert_eq!(start, 0x000A);
+        assert!(bytes == std::fs::read("bin/6502_functional_test.bin").unwrap());
+        assert_eq!(assembly.image.start, Some(0x0400));
+        assert_eq!(assembly.labels.get("start"), Some(0x0400));
+    }
+}
+
+#[cfg(test)]
+mod programs {
+    use crate::*;
+
+    #[test]
+    fn runs_at_the_origin() {
+        let mut c = chip(
+            "
+        lda #$01
+        adc #$02
+        sta result
+        brk
+result  ds 1
+",
+            0x0400,
+        )
+        .unwrap();
+        assert_eq!(c.pc, 0x0400);
+        c.execute_cycle();
+        c.execute_cycle();
+        c.execute_cycle();
+        assert_eq!(c.acc, 0x03);
+        assert_eq!(c.memory[0x0408], 0x03);
+    }
+
+    #[test]
+    fn loops_and_subroutines() {
+        let mut c = chip(
+            "
+        ldx #5
+        lda #0
+loop    jsr add_two
+        dex
+        bne loop
+done    jmp done
+
+add_two clc
+        adc #2
+        rts
+",
+            0x0200,
+        )
+        .unwrap();
+        while c.pc != 0x020A {
+            c.execute_cycle();
+        }
+        assert_eq!(c.acc, 10);
+        assert_eq!(c.rx, 0);
+    }
+
+    #[test]
+    fn data_elsewhere_and_end() {
+        let mut c = chip(
+            "
+        org $3000
+table   db $11,$22,$33
+        code
+        org $0300
+start   ldy #2
+        lda table,y
+        end start
+",
+            0x0200,
+        )
+        .unwrap();
+        assert_eq!(c.pc, 0x0300);
+        c.execute_cycle();
+        c.execute_cycle();
+        assert_eq!(c.acc, 0x33);
+    }
+
+    #[test]
+    fn instructions_in_the_first_column() {
+        let c = chip("lda #1\nloop: inx\nLDY #2\nbne loop\nbrk", 0x0200).unwrap();
+        assert_eq!(
+            &c.memory[0x0200..0x0209],
+            &[0xA9, 0x01, 0xE8, 0xA0, 0x02, 0xD0, 0xFB, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn errors_are_returned() {
+        match chip("        lda $10\n        lda (", 0x0200) {
+            Err(error) => assert_eq!(error.line, 2),
+            Ok(_) => panic!("`lda (` should not assemble"),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::loader::*;
+
+/// ==========================
+/// BINARY LOADER TESTS
+/// ==========================
+#[cfg(test)]
+mod parse {
+    use crate::*;
+
+    #[test]
+    fn offset_and_length() {
+        let image = binary::parse(&[0x00, 0x01, 0x02, 0x03, 0x04], 0x0300, 1, Some(3)).unwrap();
+
+        assert_eq!(image.segments[0].address, 0x0300);
+        assert_eq!(image.segments[0].data, [0x01, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn offset_behind_the_end() {
+        assert!(matches!(
+            binary::parse(&[0x00, 0x01], 0x0300, 3, None),
+            Err(LoadError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn length_behind_the_end() {
+        assert!(matches!(
+            binary::parse(&[0x00, 0x01], 0x0300, 1, Some(2)),
+            Err(LoadError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn overruns_memory() {
+        assert!(matches!(
+            binary::parse(&[0x00, 0x01], 0xFFFF, 0, None),
+            Err(LoadError::OutOfRange {
+                address: 0xFFFF,
+                length: 2,
+                line: None
+            })
+        ));
+    }
+}
+
+#[cfg(test)]
+mod load {
+    use crate::*;
+
+    #[test]
+    fn load_bytes() {
+        let mut c = Chip::new();
+
+        c.load_bytes(0xFFFE, &[0x34, 0x12]).unwrap();
+        assert_eq!(c.memory[0xFFFE], 0x34);
+        assert_eq!(c.memory[0xFFFF], 0x12);
+        assert!(c.load_bytes(0xFFFF, &[0x34, 0x12]).is_err());
+    }
+
+    #[test]
+    fn small_file_with_load_exe() {
+        let path = std::env::temp_dir().join("sixfiveohtwo_binary_test.bin");
+        std::fs::write(&path, [0xA9, 0x01]).unwrap();
+        let mut c = Chip::new();
+
+        c.load_exe(path.to_string_lossy().to_string(), 0x0400)
+            .unwrap();
+        assert_eq!(c.memory[0x0400], 0xA9);
+        assert_eq!(c.memory[0x0401], 0x01);
+    }
+
+    #[test]
+    fn load_binary_part() {
+        let path = std::env::temp_dir().join("sixfiveohtwo_binary_part_test.bin");
+        std::fs::write(&path, [0xFF, 0xA9, 0x01, 0xFF]).unwrap();
+        let mut c = Chip::new();
+
+        let loaded = c
+            .load_binary(path.to_string_lossy().to_string(), 0x0200, 1, Some(2))
+            .unwrap();
+        assert_eq!(loaded, 2);
+
+        c.execute_cycle();
+        assert_eq!(c.acc, 0x01);
+    }
+
+    #[test]
+    fn functional_test_image() {
+        let mut c = Chip::new();
+
+        c.load_exe("bin/6502_functional_test.bin".to_string(), 0x000A)
+            .unwrap();
+        assert_eq!(c.memory[0x0400], 0xD8);
+    }
+
+    #[test]
+    #[should_panic]
+    fn load_program_too_big() {
+        let mut c = Chip::new();
+
+        c.load_program([0xEA; 0x10000].to_vec());
+    }
+}
@@ -3,7 +3,6 @@ use sixfiveohtwo::chip::*;
 /// ==========================
 /// COMPARISON TESTS
 /// ==========================
-
 #[cfg(test)]
 mod compare_with_accumulator {
     use crate::*;
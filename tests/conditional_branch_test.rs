@@ -3,7 +3,6 @@ use sixfiveohtwo::chip::*;
 /// ==========================
 /// CONDITIONAL BRANCH TESTS
 /// ==========================
-
 #[cfg(test)]
 mod branch_on_carry_clear {
     use crate::*;
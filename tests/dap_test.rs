@@ -0,0 +1,324 @@
+use sixfiveohtwo::dap::*;
+
+#[rustfmt::skip]
+const LISTING: &str = "\
+AS65 Assembler for R6502 [1.42].                                     Page    1
+---------------------------------- dap.a65 -----------------------------------
+
+0200 : a203             start   ldx #3
+0202 : 200a02           loop    jsr count
+0205 : ca                       dex
+0206 : d0fa                     bne loop
+0208 : f0fe             done    beq done
+020a : e610             count   inc $10
+020c : 60                       rts
+No errors in pass 2.
+";
+
+#[rustfmt::skip]
+const PROGRAM: [u8; 13] = [
+    0xA2, 0x03,       // ldx #3
+    0x20, 0x0A, 0x02, // jsr count
+    0xCA,             // dex
+    0xD0, 0xFA,       // bne loop
+    0xF0, 0xFE,       // beq done
+    0xE6, 0x10,       // inc $10
+    0x60,             // rts
+];
+
+/// ==========================
+/// JSON TESTS
+/// ==========================
+#[cfg(test)]
+mod json {
+    use crate::*;
+
+    #[test]
+    fn parses_and_writes() {
+        let text = r#"{"a":[1,-2.5,true,false,null],"b":{"c":"x\"y\\z\n"},"d":"ä😀"}"#;
+        let value = Json::parse(text).unwrap();
+        assert_eq!(value.get("a").unwrap().as_array().unwrap().len(), 5);
+        assert_eq!(
+            value.get("b").unwrap().get("c").unwrap().as_str(),
+            Some("x\"y\\z\n")
+        );
+        assert_eq!(value.get("d").unwrap().as_str(), Some("ä😀"));
+        assert_eq!(
+            value.to_string(),
+            r#"{"a":[1,-2.5,true,false,null],"b":{"c":"x\"y\\z\n"},"d":"ä😀"}"#
+        );
+        assert_eq!(Json::parse(" [ ] ").unwrap(), Json::Array(Vec::new()));
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("[1,").is_err());
+        assert!(Json::parse("tru").is_err());
+        assert!(Json::parse("1 2").is_err());
+    }
+}
+
+/// ==========================
+/// DEBUG ADAPTER TESTS
+/// ==========================
+#[cfg(test)]
+mod session {
+    use crate::*;
+
+    fn request(command: &str, arguments: &str) -> Json {
+        Json::parse(&format!(
+            r#"{{"seq":1,"type":"request","command":"{}","arguments":{}}}"#,
+            command, arguments
+        ))
+        .unwrap()
+    }
+
+    /// The body of the response (it has to be successful)
+    fn body(server: &mut Server, command: &str, arguments: &str) -> Json {
+        let messages = server.handle(&request(command, arguments));
+        assert_eq!(
+            messages[0].get("success"),
+            Some(&Json::Bool(true)),
+            "{}",
+            messages[0]
+        );
+        messages[0].get("body").cloned().unwrap_or(Json::Null)
+    }
+
+    fn field(value: &Json, path: &[&str]) -> String {
+        let mut value = value;
+        for name in path {
+            value = match name.parse::<usize>() {
+                Ok(i) => &value.as_array().unwrap()[i],
+                Err(_) => value.get(name).unwrap(),
+            };
+        }
+        match value {
+            Json::String(text) => text.clone(),
+            other => other.to_string(),
+        }
+    }
+
+    /// Launches the program with its listing, stopped at the entry
+    fn launch(name: &str) -> Server {
+        let dir = std::env::temp_dir();
+        let listing = dir.join(format!("{}.lst", name));
+        let program = dir.join(format!("{}.bin", name));
+        std::fs::write(&listing, LISTING).unwrap();
+        std::fs::write(&program, PROGRAM).unwrap();
+
+        let mut server = Server::new();
+        let messages = server.handle(&request("initialize", r#"{"adapterID":"6502"}"#));
+        assert_eq!(field(&messages[1], &["event"]), "initialized");
+        let arguments = format!(
+            r#"{{"program":"{}","address":"$0200","listing":"{}","stopOnEntry":true}}"#,
+            program.display(),
+            listing.display()
+        );
+        body(&mut server, "launch", &arguments);
+        let messages = server.handle(&request("configurationDone", "{}"));
+        assert_eq!(field(&messages[1], &["body", "reason"]), "entry");
+        server
+    }
+
+    fn breakpoints(server: &mut Server, lines: &str) -> Json {
+        let path = std::env::temp_dir().join("dap.a65");
+        body(
+            server,
+            "setBreakpoints",
+            &format!(
+                r#"{{"source":{{"path":"{}"}},"breakpoints":[{}]}}"#,
+                path.display(),
+                lines
+            ),
+        )
+    }
+
+    /// Runs until the chip stops and returns the reason
+    fn run(server: &mut Server) -> Json {
+        let messages = server.run(10_000);
+        assert!(!server.running());
+        messages[0].get("body").cloned().unwrap()
+    }
+
+    #[test]
+    fn stops_at_breakpoints_by_line() {
+        let mut server = launch("dap_breakpoints");
+        let reply = breakpoints(&mut server, r#"{"line":6},{"line":9}"#);
+        assert_eq!(field(&reply, &["breakpoints", "0", "verified"]), "true");
+        assert_eq!(
+            field(&reply, &["breakpoints", "0", "instructionReference"]),
+            "0x020A"
+        );
+        assert_eq!(field(&reply, &["breakpoints", "1", "verified"]), "false");
+
+        body(&mut server, "continue", r#"{"threadId":1}"#);
+        assert_eq!(field(&run(&mut server), &["reason"]), "breakpoint");
+        assert_eq!(server.monitor.chip.pc, 0x020A);
+
+        // the frame of the subroutine and the one of the JSR
+        let trace = body(&mut server, "stackTrace", r#"{"threadId":1}"#);
+        assert_eq!(field(&trace, &["totalFrames"]), "2");
+        assert_eq!(field(&trace, &["stackFrames", "0", "name"]), "count");
+        assert_eq!(field(&trace, &["stackFrames", "0", "line"]), "6");
+        assert_eq!(
+            field(&trace, &["stackFrames", "0", "source", "name"]),
+            "dap.a65"
+        );
+        assert_eq!(field(&trace, &["stackFrames", "1", "name"]), "loop");
+        assert_eq!(field(&trace, &["stackFrames", "1", "line"]), "2");
+    }
+
+    #[test]
+    fn resolves_breakpoints_set_before_the_launch() {
+        let dir = std::env::temp_dir().join("dap with spaces");
+        std::fs::create_dir_all(&dir).unwrap();
+        let listing = dir.join("dap early.lst");
+        let program = dir.join("dap early.bin");
+        std::fs::write(&listing, LISTING).unwrap();
+        std::fs::write(&program, PROGRAM).unwrap();
+
+        let mut server = Server::new();
+        server.handle(&request("initialize", r#"{"adapterID":"6502"}"#));
+        let reply = breakpoints(&mut server, r#"{"line":6}"#);
+        assert_eq!(field(&reply, &["breakpoints", "0", "verified"]), "false");
+        let id = field(&reply, &["breakpoints", "0", "id"]);
+
+        let arguments = format!(
+            r#"{{"program":"{}","address":"$0200","listing":"{}"}}"#,
+            program.display(),
+            listing.display()
+        );
+        let messages = server.handle(&request("launch", &arguments));
+        assert_eq!(messages[0].get("success"), Some(&Json::Bool(true)));
+        assert_eq!(field(&messages[1], &["event"]), "breakpoint");
+        assert_eq!(field(&messages[1], &["body", "reason"]), "changed");
+        assert_eq!(field(&messages[1], &["body", "breakpoint", "id"]), id);
+        assert_eq!(
+            field(&messages[1], &["body", "breakpoint", "verified"]),
+            "true"
+        );
+
+        body(&mut server, "configurationDone", "{}");
+        assert_eq!(field(&run(&mut server), &["reason"]), "breakpoint");
+        assert_eq!(server.monitor.chip.pc, 0x020A);
+    }
+
+    #[test]
+    fn steps_over_subroutines() {
+        let mut server = launch("dap_steps");
+        body(&mut server, "next", r#"{"threadId":1}"#);
+        run(&mut server);
+        assert_eq!(server.monitor.chip.pc, 0x0202);
+        // over the subroutine
+        body(&mut server, "next", r#"{"threadId":1}"#);
+        run(&mut server);
+        assert_eq!(server.monitor.chip.pc, 0x0205);
+        assert_eq!(server.monitor.chip.memory[0x10], 1);
+    }
+
+    #[test]
+    fn steps_into_and_out_of_subroutines() {
+        let mut server = launch("dap_step_in");
+        let mut step = |command: &str, arguments: &str| {
+            body(&mut server, command, arguments);
+            run(&mut server);
+            server.monitor.chip.pc
+        };
+        assert_eq!(step("stepIn", "{}"), 0x0202);
+        assert_eq!(step("stepIn", "{}"), 0x020A);
+        assert_eq!(step("stepOut", "{}"), 0x0205);
+        assert_eq!(step("stepIn", r#"{"granularity":"instruction"}"#), 0x0206);
+    }
+
+    #[test]
+    fn traps_are_exceptions_and_pause_stops() {
+        let mut server = launch("dap_trap");
+        body(&mut server, "continue", "{}");
+        let stopped = run(&mut server);
+        assert_eq!(field(&stopped, &["reason"]), "exception");
+        assert_eq!(field(&stopped, &["text"]), "trapped at $0208");
+        assert_eq!(server.monitor.chip.memory[0x10], 3);
+
+        body(&mut server, "continue", "{}");
+        let messages = server.handle(&request("pause", "{}"));
+        assert_eq!(field(&messages[1], &["body", "reason"]), "pause");
+        assert!(!server.running());
+    }
+
+    #[test]
+    fn shows_registers_and_memory() {
+        let mut server = launch("dap_variables");
+        body(&mut server, "stepIn", r#"{"granularity":"instruction"}"#);
+        run(&mut server);
+        body(&mut server, "stepIn", r#"{"granularity":"instruction"}"#);
+        run(&mut server);
+
+        let scopes = body(&mut server, "scopes", r#"{"frameId":0}"#);
+        assert_eq!(field(&scopes, &["scopes", "0", "name"]), "Registers");
+        let registers = body(&mut server, "variables", r#"{"variablesReference":1}"#);
+        assert_eq!(field(&registers, &["variables", "1", "value"]), "$03");
+        assert_eq!(field(&registers, &["variables", "4", "value"]), "$020A");
+        // the return address of the JSR
+        let stack = body(&mut server, "variables", r#"{"variablesReference":4}"#);
+        assert_eq!(field(&stack, &["variables", "0", "name"]), "$01FF");
+        assert_eq!(field(&stack, &["variables", "0", "value"]), "$02");
+        assert_eq!(field(&stack, &["variables", "1", "value"]), "$04");
+
+        let value = body(&mut server, "evaluate", r#"{"expression":"count"}"#);
+        assert_eq!(field(&value, &["result"]), "$020A ($E6)");
+        let memory = body(
+            &mut server,
+            "readMemory",
+            r#"{"memoryReference":"0x0200","count":4}"#,
+        );
+        assert_eq!(field(&memory, &["data"]), "ogMgCg==");
+        assert_eq!(field(&memory, &["address"]), "0x0200");
+    }
+
+    #[test]
+    fn fails_unknown_requests() {
+        let mut server = Server::new();
+        let messages = server.handle(&request("goto", "{}"));
+        assert_eq!(messages[0].get("success"), Some(&Json::Bool(false)));
+        let messages = server.handle(&request("launch", r#"{"program":"/nowhere/at/all"}"#));
+        assert_eq!(messages[0].get("success"), Some(&Json::Bool(false)));
+    }
+}
+
+#[cfg(test)]
+mod stdio {
+    use crate::*;
+
+    fn message(text: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{}", text.len(), text)
+    }
+
+    #[test]
+    fn answers_framed_messages() {
+        let input = message(r#"{"seq":1,"type":"request","command":"threads"}"#)
+            + &message(r#"{"seq":2,"type":"request","command":"disconnect"}"#);
+        let mut output = Vec::new();
+        serve(
+            &mut Server::new(),
+            std::io::Cursor::new(input.into_bytes()),
+            &mut output,
+        )
+        .unwrap();
+
+        let mut reader = std::io::BufReader::new(&output[..]);
+        let threads = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(threads.get("request_seq"), Some(&Json::Number(1.0)));
+        assert_eq!(
+            threads.get("body").unwrap().to_string(),
+            r#"{"threads":[{"id":1,"name":"6502"}]}"#
+        );
+        let disconnect = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(
+            disconnect.get("command").unwrap().as_str(),
+            Some("disconnect")
+        );
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+}
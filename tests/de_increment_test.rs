@@ -3,7 +3,6 @@ use sixfiveohtwo::chip::*;
 /// ==============================
 /// DECREMENT & INCREMENT TESTS
 /// ==============================
-
 #[cfg(test)]
 mod decrement {
     use crate::*;
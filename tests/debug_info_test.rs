@@ -0,0 +1,83 @@
+use sixfiveohtwo::debug_info::*;
+use sixfiveohtwo::loader::LoadError;
+
+const DBG: &str = "version\tmajor=2,minor=0
+info\tcsym=0,file=1,lib=0,line=3,mod=1,scope=1,seg=1,span=2,sym=3,type=0
+file\tid=0,name=\"src/hello.s\",size=120,mtime=0x64000000,mod=0
+mod\tid=0,name=\"hello.o\",file=0
+seg\tid=0,name=\"CODE\",start=0x000200,size=0x0005,addrsize=absolute,type=ro,oname=\"hello.bin\",ooffs=0
+span\tid=0,seg=0,start=0,size=2
+span\tid=1,seg=0,start=2,size=3
+scope\tid=0,name=\"\",mod=0,size=5,span=0+1
+line\tid=0,file=0,line=3,span=0
+line\tid=1,file=0,line=4,span=1
+line\tid=2,file=0,line=9,type=2,span=1
+sym\tid=0,name=\"main\",addrsize=absolute,scope=0,def=0,val=0x200,seg=0,type=lab
+sym\tid=1,name=\"value\",addrsize=zeropage,scope=0,def=0,val=0x10,type=equ
+sym\tid=2,name=\"_exit\",addrsize=absolute,scope=0,def=0,type=imp
+";
+
+/// ==========================
+/// DEBUG INFO TESTS
+/// ==========================
+#[cfg(test)]
+mod parse {
+    use crate::*;
+
+    #[test]
+    fn symbols() {
+        let info = parse(DBG).unwrap();
+
+        assert_eq!(info.symbol("main"), Some(0x0200));
+        assert_eq!(info.symbol("value"), Some(0x0010));
+        assert_eq!(info.label_at(0x0200), Some("main"));
+        assert_eq!(info.label_at(0x0010), None);
+        assert_eq!(info.symbol("_exit"), None);
+    }
+
+    #[test]
+    fn segments() {
+        let info = parse(DBG).unwrap();
+
+        assert_eq!(info.segments[0].name, "CODE");
+        assert_eq!(info.segments[0].start, 0x0200);
+    }
+
+    #[test]
+    fn lines() {
+        let info = parse(DBG).unwrap();
+
+        assert_eq!(info.line_at(0x0200), Some(("src/hello.s", 3)));
+        assert_eq!(info.line_at(0x0203), Some(("src/hello.s", 4)));
+        assert_eq!(info.line_at(0x0300), None);
+        assert_eq!(info.addresses_of("hello.s", 4), [0x0202]);
+    }
+
+    #[test]
+    fn unknown_span() {
+        let err = parse(
+            "version\tmajor=2,minor=0\nfile\tid=0,name=\"a.s\"\nline\tid=0,file=0,line=1,span=9\n",
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, LoadError::Syntax { line: 3, .. }));
+    }
+
+    #[test]
+    fn wrong_version() {
+        assert!(parse("version\tmajor=1,minor=0\n").is_err());
+    }
+}
+
+#[cfg(test)]
+mod symbol_table {
+    use crate::*;
+
+    #[test]
+    fn labels_and_equates() {
+        let table = parse(DBG).unwrap().symbol_table();
+
+        assert_eq!(table.get("value"), Some(0x0010));
+        assert_eq!(table.format_address(0x0203), "main+3");
+    }
+}
@@ -0,0 +1,185 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::disassembler::*;
+use sixfiveohtwo::symbols::*;
+
+/// ==========================
+/// DISASSEMBLER TESTS
+/// ==========================
+#[cfg(test)]
+mod nmos {
+    use crate::*;
+
+    #[test]
+    fn addressing_modes() {
+        let mut c = Chip::new();
+        #[rustfmt::skip]
+        let prog: Vec<u8> = [
+            0xA9, 0x01,       // LDA #$01
+            0x85, 0x10,       // STA $10
+            0xB5, 0x10,       // LDA $10,X
+            0xB6, 0x10,       // LDX $10,Y
+            0x8D, 0x34, 0x12, // STA $1234
+            0x9D, 0x34, 0x12, // STA $1234,X
+            0x99, 0x34, 0x12, // STA $1234,Y
+            0x6C, 0x34, 0x12, // JMP ($1234)
+            0xA1, 0x10,       // LDA ($10,X)
+            0xB1, 0x10,       // LDA ($10),Y
+            0x0A,             // ASL A
+            0xE8,             // INX
+        ]
+        .to_vec();
+        c.load_program(prog);
+
+        let texts: Vec<String> = Disassembler::new(Cpu::Mos6502)
+            .range(&c.memory, 0x0200..=0x0219)
+            .iter()
+            .map(|i| i.text())
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                "LDA #$01",
+                "STA $10",
+                "LDA $10,X",
+                "LDX $10,Y",
+                "STA $1234",
+                "STA $1234,X",
+                "STA $1234,Y",
+                "JMP ($1234)",
+                "LDA ($10,X)",
+                "LDA ($10),Y",
+                "ASL A",
+                "INX"
+            ]
+        );
+    }
+
+    #[test]
+    fn branches_show_their_target() {
+        let mut c = Chip::new();
+        // BNE -2 and BEQ +4
+        c.load_program([0xD0, 0xFE, 0xF0, 0x04].to_vec());
+        let d = Disassembler::new(Cpu::Mos6502);
+        assert_eq!(d.disassemble(&c.memory, 0x0200).text(), "BNE $0200");
+        assert_eq!(d.disassemble(&c.memory, 0x0202).text(), "BEQ $0208");
+    }
+
+    #[test]
+    fn undocumented() {
+        let mut c = Chip::new();
+        c.load_program([0xA7, 0x10, 0x02, 0xEB, 0x01].to_vec());
+        let d = Disassembler::new(Cpu::Mos6502);
+
+        let lax = d.disassemble(&c.memory, 0x0200);
+        assert_eq!(lax.text(), "LAX $10");
+        assert!(!lax.documented);
+        assert_eq!(d.disassemble(&c.memory, 0x0202).text(), "JAM");
+        assert_eq!(d.disassemble(&c.memory, 0x0203).text(), "SBC #$01");
+    }
+
+    #[test]
+    fn documented_count() {
+        let count = (0..=255)
+            .filter(|b| opcode(Cpu::Mos6502, *b).documented)
+            .count();
+        assert_eq!(count, 151);
+    }
+
+    #[test]
+    fn display() {
+        let mut c = Chip::new();
+        c.load_program([0x8D, 0x00, 0x03].to_vec());
+        let d = Disassembler::new(Cpu::Mos6502);
+        assert_eq!(
+            d.disassemble(&c.memory, 0x0200).to_string(),
+            "0200  8d 00 03  STA $0300"
+        );
+    }
+
+    #[test]
+    fn wraps_around() {
+        let mut c = Chip::new();
+        c.memory[0xFFFF] = 0xAD;
+        c.memory[0x0000] = 0x34;
+        c.memory[0x0001] = 0x12;
+        let i = Disassembler::new(Cpu::Mos6502).disassemble(&c.memory, 0xFFFF);
+        assert_eq!(i.text(), "LDA $1234");
+        assert_eq!(i.length(), 3);
+    }
+}
+
+#[cfg(test)]
+mod cmos {
+    use crate::*;
+
+    #[test]
+    fn new_instructions() {
+        let mut c = Chip::new();
+        #[rustfmt::skip]
+        let prog: Vec<u8> = [
+            0xB2, 0x10,       // LDA ($10)
+            0x7C, 0x34, 0x12, // JMP ($1234,X)
+            0x0F, 0x10, 0xFD, // BBR0 $10,$0205
+            0x80, 0x00,       // BRA $020A
+            0xDA,             // PHX
+            0x03,             // NOP (unused)
+        ]
+        .to_vec();
+        c.load_program(prog);
+
+        let instructions = Disassembler::new(Cpu::Wdc65C02).range(&c.memory, 0x0200..=0x020B);
+        let texts: Vec<String> = instructions.iter().map(|i| i.text()).collect();
+        assert_eq!(
+            texts,
+            [
+                "LDA ($10)",
+                "JMP ($1234,X)",
+                "BBR0 $10,$0205",
+                "BRA $020A",
+                "PHX",
+                "NOP"
+            ]
+        );
+        assert!(!instructions[5].documented);
+    }
+
+    #[test]
+    fn documented_count() {
+        let count = (0..=255)
+            .filter(|b| opcode(Cpu::Wdc65C02, *b).documented)
+            .count();
+        assert_eq!(count, 212);
+    }
+}
+
+#[cfg(test)]
+mod symbols {
+    use crate::*;
+
+    #[test]
+    fn labels_in_operands() {
+        let mut table = SymbolTable::new();
+        table.insert("loop", 0x0200);
+        table.insert("table", 0x0300);
+        table.insert("ptr", 0x0010);
+
+        let mut c = Chip::new();
+        #[rustfmt::skip]
+        let prog: Vec<u8> = [
+            0xBD, 0x02, 0x03, // LDA table+2,X
+            0x91, 0x10,       // STA (ptr),Y
+            0xD0, 0xF9,       // BNE loop
+        ]
+        .to_vec();
+        c.load_program(prog);
+
+        let d = Disassembler::with_symbols(Cpu::Mos6502, &table);
+        assert_eq!(
+            d.listing(&c.memory, 0x0200..=0x0206),
+            "loop:\n\
+             0200  bd 02 03  LDA table+2,X\n\
+             0203  91 10     STA (ptr),Y\n\
+             0205  d0 f9     BNE loop\n"
+        );
+    }
+}
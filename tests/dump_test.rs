@@ -0,0 +1,79 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::dump::*;
+use sixfiveohtwo::loader::ihex;
+
+/// ==========================
+/// DUMP TESTS
+/// ==========================
+#[cfg(test)]
+mod export {
+    use crate::*;
+
+    #[test]
+    fn raw_range() {
+        let mut c = Chip::new();
+        c.load_program([0xA9, 0x01, 0x69, 0x01].to_vec());
+        assert_eq!(raw(&c, 0x0201..=0x0203), [0x01, 0x69, 0x01].to_vec());
+        assert_eq!(raw(&c, 0xFFFF..=0xFFFF).len(), 1);
+    }
+
+    #[test]
+    fn ihex_round_trip() {
+        let mut c = Chip::new();
+        for i in 0..40 {
+            c.memory[0x0300 + i] = i as u8;
+        }
+        let text = ihex(&c, 0x0300..=0x0327);
+        assert_eq!(text.lines().count(), 4);
+        assert!(text.ends_with(":00000001FF\n"));
+
+        let image = ihex::parse(&text).unwrap();
+        assert_eq!(image.segments[0].address, 0x0300);
+        assert_eq!(image.segments[0].data, raw(&c, 0x0300..=0x0327));
+    }
+
+    #[test]
+    fn hexdump_ascii() {
+        let mut c = Chip::new();
+        c.memory[0x0200..0x0205].copy_from_slice(b"Hi!\n\0");
+        assert_eq!(
+            hexdump(&c, 0x0200..=0x0211, Charset::Ascii),
+            "0200  48 69 21 0a 00 00 00 00  00 00 00 00 00 00 00 00  |Hi!.............|\n\
+             0210  00 00                                             |..|\n"
+        );
+    }
+
+    #[test]
+    fn hexdump_petscii() {
+        let mut c = Chip::new();
+        c.memory[0x0200..0x0204].copy_from_slice(&[0xC8, 0x49, 0x21, 0x5C]);
+        let text = hexdump(&c, 0x0200..=0x0203, Charset::Petscii);
+        assert!(text.ends_with("|Hi!.|\n"));
+    }
+}
+
+#[cfg(test)]
+mod options {
+    use crate::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_range("$0200-$02FF"), Some(0x0200..=0x02FF));
+        assert_eq!(parse_range("0x0200+256"), Some(0x0200..=0x02FF));
+        assert_eq!(parse_range("$FFFF+1"), Some(0xFFFF..=0xFFFF));
+        assert_eq!(parse_range("$FFFF+2"), None);
+        assert_eq!(parse_range("$0300-$0200"), None);
+        assert_eq!(parse_range("nope"), None);
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(Format::parse("raw"), Some(Format::Raw));
+        assert_eq!(Format::parse("IHEX"), Some(Format::Ihex));
+        assert_eq!(
+            Format::parse("petscii"),
+            Some(Format::Hexdump(Charset::Petscii))
+        );
+        assert_eq!(Format::parse("pdf"), None);
+    }
+}
@@ -3,7 +3,6 @@ use sixfiveohtwo::chip::*;
 /// ==========================
 /// FLAG TESTS
 /// ==========================
-
 #[cfg(test)]
 mod clear_carry {
 
@@ -0,0 +1,234 @@
+use sixfiveohtwo::chip::Chip;
+use sixfiveohtwo::gdb::*;
+
+/// ==========================
+/// GDB TESTS
+/// ==========================
+#[cfg(test)]
+mod packets {
+    use crate::*;
+
+    fn reply(stub: &mut Stub, packet: &str) -> String {
+        stub.handle(packet, &mut || false).unwrap()
+    }
+
+    fn chip() -> Chip {
+        let mut c = Chip::new();
+        #[rustfmt::skip]
+        c.load_bytes(0x0200, &[
+            0xA2, 0x03,       // ldx #$03
+            0xE8,             // inx
+            0x8D, 0x00, 0x03, // sta $0300
+            0xAD, 0x10, 0x00, // lda $0010
+            0x4C, 0x09, 0x02, // jmp *
+        ]).unwrap();
+        c.pc = 0x0200;
+        c
+    }
+
+    #[test]
+    fn frames_packets() {
+        assert_eq!(checksum(b"OK"), 0x9A);
+        assert_eq!(frame("OK"), "$OK#9a");
+        assert_eq!(frame(""), "$#00");
+    }
+
+    #[test]
+    fn reads_and_writes_registers() {
+        let mut c = chip();
+        c.acc = 0x14;
+        c.rx = 0x02;
+        c.ry = 0x03;
+        c.f = 0x00;
+        c.sp = 0xFF;
+        let mut stub = Stub::new(&mut c);
+        assert_eq!(reply(&mut stub, "g"), "14020300ff0002");
+        assert_eq!(reply(&mut stub, "p5"), "0002");
+        assert_eq!(reply(&mut stub, "p6"), "E01");
+        assert_eq!(reply(&mut stub, "G0102030405ff10"), "OK");
+        assert_eq!(reply(&mut stub, "P0=aa"), "OK");
+        assert_eq!(reply(&mut stub, "P5=0004"), "OK");
+        assert_eq!(reply(&mut stub, "P5=04"), "E01");
+        drop(stub);
+        assert_eq!(
+            (c.acc, c.rx, c.ry, c.f, c.sp),
+            (0xAA, 0x02, 0x03, 0x04, 0x05)
+        );
+        assert_eq!(c.pc, 0x0400);
+    }
+
+    #[test]
+    fn reads_and_writes_memory() {
+        let mut c = chip();
+        let mut stub = Stub::new(&mut c);
+        assert_eq!(reply(&mut stub, "m200,3"), "a203e8");
+        assert_eq!(reply(&mut stub, "M10,2:beef"), "OK");
+        assert_eq!(reply(&mut stub, "m10,2"), "beef");
+        assert_eq!(reply(&mut stub, "mffff,2"), "E01");
+        assert_eq!(reply(&mut stub, "M10,2:be"), "E01");
+    }
+
+    #[test]
+    fn steps_and_continues_to_breakpoints() {
+        let mut c = chip();
+        let mut stub = Stub::new(&mut c);
+        assert_eq!(reply(&mut stub, "?"), "S05");
+        assert_eq!(reply(&mut stub, "s"), "S05");
+        assert_eq!(reply(&mut stub, "p5"), "0202");
+        assert_eq!(reply(&mut stub, "Z0,206,1"), "OK");
+        assert_eq!(reply(&mut stub, "c"), "S05");
+        assert_eq!(reply(&mut stub, "p5"), "0602");
+        assert_eq!(reply(&mut stub, "z0,206,1"), "OK");
+        // without breakpoints it stops at the trap
+        assert_eq!(reply(&mut stub, "c"), "S05");
+        assert_eq!(reply(&mut stub, "p5"), "0902");
+        assert_eq!(reply(&mut stub, "c200"), "S05");
+        assert_eq!(reply(&mut stub, "p1"), "04");
+    }
+
+    #[test]
+    fn stops_at_watchpoints() {
+        let mut c = chip();
+        let mut stub = Stub::new(&mut c);
+        assert_eq!(reply(&mut stub, "Z3,300,1"), "OK");
+        assert_eq!(reply(&mut stub, "Z2,2ff,2"), "OK");
+        // the write after the instruction that does it
+        assert_eq!(reply(&mut stub, "c"), "T05watch:0300;");
+        assert_eq!(reply(&mut stub, "p5"), "0602");
+        assert_eq!(reply(&mut stub, "Z4,10,1"), "OK");
+        assert_eq!(reply(&mut stub, "c"), "T05awatch:0010;");
+        assert_eq!(reply(&mut stub, "z4,10,1"), "OK");
+        assert_eq!(reply(&mut stub, "c200"), "T05watch:0300;");
+    }
+
+    #[test]
+    fn watchpoints_inside_of_the_memory() {
+        let mut c = chip();
+        let mut stub = Stub::new(&mut c);
+        assert_eq!(reply(&mut stub, "Z2,300,0"), "E01");
+        assert_eq!(reply(&mut stub, "Z2,ffff,2"), "E01");
+        assert_eq!(reply(&mut stub, "Z0,10000,1"), "E01");
+        // the whole memory
+        assert_eq!(reply(&mut stub, "Z2,0,10000"), "OK");
+        assert_eq!(reply(&mut stub, "c"), "T05watch:0300;");
+        assert_eq!(reply(&mut stub, "z2,0,10000"), "OK");
+        assert_eq!(reply(&mut stub, "Z2,ffff,1"), "OK");
+    }
+
+    #[test]
+    fn answers_queries() {
+        let mut c = chip();
+        let mut stub = Stub::new(&mut c);
+        let supported = reply(&mut stub, "qSupported:multiprocess+");
+        assert!(supported.contains("PacketSize=4000"));
+        assert!(supported.contains("qXfer:features:read+"));
+        let mut xml = String::new();
+        let mut offset = 0;
+        loop {
+            let chunk = reply(
+                &mut stub,
+                &format!("qXfer:features:read:target.xml:{:x},40", offset),
+            );
+            xml.push_str(&chunk[1..]);
+            offset += chunk.len() - 1;
+            if chunk.starts_with('l') {
+                break;
+            }
+        }
+        assert_eq!(xml, TARGET_XML);
+        assert_eq!(reply(&mut stub, "qAttached"), "1");
+        assert_eq!(reply(&mut stub, "vMustReplyEmpty"), "");
+        assert_eq!(reply(&mut stub, "D"), "OK");
+        assert!(!stub.attached());
+    }
+}
+
+#[cfg(test)]
+mod connection {
+    use crate::*;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn talks_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let debugger = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            let mut answers = String::new();
+            for packet in ["m200,2", "$bad#00", "D"] {
+                if packet.starts_with('$') {
+                    stream.write_all(packet.as_bytes()).unwrap();
+                } else {
+                    stream.write_all(frame(packet).as_bytes()).unwrap();
+                }
+            }
+            stream.read_to_string(&mut answers).unwrap();
+            answers
+        });
+
+        let mut c = Chip::new();
+        c.memory[0x0200] = 0xA9;
+        c.memory[0x0201] = 0x01;
+        let (stream, _) = listener.accept().unwrap();
+        let mut stub = Stub::new(&mut c);
+        stub.serve(stream).unwrap();
+        drop(stub);
+
+        assert_eq!(
+            debugger.join().unwrap(),
+            format!("+{}-+{}", frame("a901"), frame("OK"))
+        );
+    }
+
+    #[test]
+    fn packets_that_are_too_long() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let debugger = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            let long = format!("M200,2000:{}", "ea".repeat(0x2000));
+            stream.write_all(frame(&long).as_bytes()).unwrap();
+            stream.write_all(frame("D").as_bytes()).unwrap();
+            let mut answers = String::new();
+            stream.read_to_string(&mut answers).unwrap();
+            answers
+        });
+
+        let mut c = Chip::new();
+        let (stream, _) = listener.accept().unwrap();
+        Stub::new(&mut c).serve(stream).unwrap();
+
+        assert_eq!(debugger.join().unwrap(), format!("-+{}", frame("OK")));
+        assert_eq!(c.memory[0x0200], 0x00);
+    }
+
+    #[test]
+    fn keeps_packets_that_arrive_while_running() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let debugger = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(frame("c").as_bytes()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            stream.write_all(frame("qAttached").as_bytes()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            stream.write_all(&[0x03]).unwrap();
+            stream.write_all(frame("D").as_bytes()).unwrap();
+            let mut answers = String::new();
+            stream.read_to_string(&mut answers).unwrap();
+            answers
+        });
+
+        // inx, jmp $0200 (it never traps)
+        let mut c = Chip::new();
+        c.load_bytes(0x0200, &[0xE8, 0x4C, 0x00, 0x02]).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        Stub::new(&mut c).serve(stream).unwrap();
+
+        assert_eq!(
+            debugger.join().unwrap(),
+            format!("+{}+{}+{}", frame("S02"), frame("1"), frame("OK"))
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::loader::*;
+
+/// ==========================
+/// INTEL HEX LOADER TESTS
+/// ==========================
+#[cfg(test)]
+mod parse {
+    use crate::*;
+
+    #[test]
+    fn data_records() {
+        // Code:
+        // LDA #$01
+        // STA $0300
+        // BRK
+        let image = ihex::parse(":05020000A9018D0003BF\n:0102050000F8\n:00000001FF\n").unwrap();
+
+        assert_eq!(
+            image.segments,
+            [Segment {
+                address: 0x0200,
+                data: [0xA9, 0x01, 0x8D, 0x00, 0x03, 0x00].to_vec(),
+            }]
+        );
+        assert_eq!(image.start, None);
+    }
+
+    #[test]
+    fn extended_segment_address() {
+        let image = ihex::parse(":020000020FF0FD\n:020010000102EB\n:00000001FF\n").unwrap();
+
+        assert_eq!(image.segments[0].address, 0xFF10);
+    }
+
+    #[test]
+    fn start_linear_address() {
+        let image = ihex::parse(":020000040000FA\n:0400000500000200F5\n:00000001FF\n").unwrap();
+
+        assert_eq!(image.start, Some(0x0200));
+    }
+
+    #[test]
+    fn start_segment_address() {
+        let image = ihex::parse(":0400000300200000D9\n:00000001FF\n").unwrap();
+
+        assert_eq!(image.start, Some(0x0200));
+    }
+
+    #[test]
+    fn checksum_error_has_line() {
+        let err = ihex::parse(":05020000A9018D0003BF\n:0102050000F9\n:00000001FF\n").unwrap_err();
+
+        match err {
+            LoadError::Checksum {
+                line,
+                expected,
+                found,
+            } => {
+                assert_eq!(line, 2);
+                assert_eq!(expected, 0xF8);
+                assert_eq!(found, 0xF9);
+            }
+            _ => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn address_above_64k() {
+        let err = ihex::parse(":020000021000EC\n:020010000102EB\n:00000001FF\n").unwrap_err();
+
+        assert!(matches!(
+            err,
+            LoadError::OutOfRange {
+                address: 0x10010,
+                line: Some(2),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn data_crossing_the_end_of_memory() {
+        let err = ihex::parse(":02FFFF000102FD\n:00000001FF\n").unwrap_err();
+
+        assert!(matches!(
+            err,
+            LoadError::OutOfRange {
+                address: 0xFFFF,
+                length: 2,
+                line: Some(1)
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "line 1: 2 bytes at $FFFF do not fit into the memory"
+        );
+    }
+
+    #[test]
+    fn missing_end_of_file() {
+        let err = ihex::parse(":0102050000F8\n").unwrap_err();
+
+        assert!(matches!(err, LoadError::Syntax { line: 1, .. }));
+    }
+
+    #[test]
+    fn missing_colon() {
+        let err = ihex::parse("\n0102050000F8\n").unwrap_err();
+
+        assert!(matches!(err, LoadError::Syntax { line: 2, .. }));
+    }
+}
+
+#[cfg(test)]
+mod load {
+    use crate::*;
+
+    #[test]
+    fn load_and_run() {
+        let path = std::env::temp_dir().join("sixfiveohtwo_ihex_test.hex");
+        std::fs::write(
+            &path,
+            ":05020000A9018D0003BF\n:0400000500000200F5\n:00000001FF\n",
+        )
+        .unwrap();
+        let mut c = Chip::new();
+        c.pc = 0x0000;
+
+        c.load_ihex(path.to_string_lossy().to_string(), true)
+            .unwrap();
+        assert_eq!(c.pc, 0x0200);
+
+        c.execute_cycle();
+        c.execute_cycle();
+        assert_eq!(c.memory[0x0300], 0x01);
+    }
+}
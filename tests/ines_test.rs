@@ -0,0 +1,105 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::loader::*;
+
+/// ==========================
+/// INES LOADER TESTS
+/// ==========================
+#[cfg(test)]
+mod parse {
+    use crate::*;
+
+    #[test]
+    fn header() {
+        let rom = ines::parse(&rom(1, 1, 0x01, 0x00)).unwrap();
+
+        assert_eq!(rom.mapper, 0);
+        assert!(!rom.nes2);
+        assert!(rom.vertical_mirroring);
+        assert_eq!(rom.prg.len(), 0x4000);
+        assert_eq!(rom.chr.len(), 0x2000);
+        assert_eq!(rom.chr[0], 0x55);
+    }
+
+    #[test]
+    fn nes2_mapper() {
+        let mut bytes = rom(1, 0, 0x40, 0x08);
+        bytes[8] = 0x21;
+
+        let rom = ines::parse(&bytes).unwrap();
+        assert!(rom.nes2);
+        assert_eq!(rom.mapper, 0x104);
+        assert_eq!(rom.submapper, 2);
+    }
+
+    #[test]
+    fn not_ines() {
+        assert!(matches!(
+            ines::parse(b"NOT A ROM FILE!!"),
+            Err(LoadError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn truncated() {
+        let mut bytes = rom(2, 1, 0x00, 0x00);
+        bytes.truncate(0x5000);
+
+        assert!(matches!(ines::parse(&bytes), Err(LoadError::Format(_))));
+    }
+
+    #[test]
+    fn nrom_16k_is_mirrored() {
+        let image = ines::parse(&rom(1, 0, 0x00, 0x00))
+            .unwrap()
+            .image()
+            .unwrap();
+        let mut c = Chip::new();
+        c.load_image(&image).unwrap();
+
+        assert_eq!(c.memory[0x8000], 0xA9);
+        assert_eq!(c.memory[0xC000], 0xA9);
+        assert_eq!(image.start, Some(0x8000));
+    }
+
+    #[test]
+    fn unsupported_mapper() {
+        let rom = ines::parse(&rom(2, 0, 0x10, 0x00)).unwrap();
+
+        assert!(matches!(rom.image(), Err(LoadError::UnsupportedMapper(1))));
+    }
+}
+
+#[cfg(test)]
+mod load {
+    use crate::*;
+
+    #[test]
+    fn load_and_run() {
+        let path = std::env::temp_dir().join("sixfiveohtwo_ines_test.nes");
+        std::fs::write(&path, rom(2, 1, 0x00, 0x00)).unwrap();
+        let mut c = Chip::new();
+
+        let rom = c.load_ines(path.to_string_lossy().to_string()).unwrap();
+        assert_eq!(rom.chr.len(), 0x2000);
+        assert_eq!(c.pc, 0x8000);
+
+        c.execute_cycle();
+        assert_eq!(c.acc, 0x01);
+    }
+}
+
+/// Builds a ROM file with `prg` 16K units of PRG-ROM and `chr` 8K units of CHR-ROM.
+/// The PRG-ROM starts with `LDA #$01` and its reset vector points to its start.
+fn rom(prg: u8, chr: u8, flags6: u8, flags7: u8) -> Vec<u8> {
+    let mut bytes = [b'N', b'E', b'S', 0x1A, prg, chr, flags6, flags7].to_vec();
+    bytes.resize(16, 0);
+    let mut prg_rom = vec![0xEA; prg as usize * 0x4000];
+    prg_rom[0] = 0xA9;
+    prg_rom[1] = 0x01;
+    let len = prg_rom.len();
+    prg_rom[len - 4] = 0x00;
+    prg_rom[len - 3] = 0x80;
+    bytes.extend(prg_rom);
+    bytes.extend(vec![0x55; chr as usize * 0x2000]);
+    bytes
+}
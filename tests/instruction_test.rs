@@ -0,0 +1,172 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::instruction::*;
+
+/// ==========================
+/// INSTRUCTION TESTS
+/// ==========================
+#[cfg(test)]
+mod decode {
+    use crate::*;
+
+    #[test]
+    fn operands_are_typed() {
+        let decode = |bytes: &[u8]| Instruction::decode(Cpu::Mos6502, bytes).unwrap();
+        assert_eq!(
+            decode(&[0xA9, 0x01]),
+            Instruction {
+                mnemonic: Mnemonic::Lda,
+                operand: Operand::Immediate(0x01)
+            }
+        );
+        assert_eq!(
+            decode(&[0x9D, 0x34, 0x12]).operand,
+            Operand::AbsoluteX(0x1234)
+        );
+        assert_eq!(decode(&[0xB1, 0x10]).operand, Operand::IndirectY(0x10));
+        assert_eq!(decode(&[0xD0, 0xFB]).operand, Operand::Relative(-5));
+        assert_eq!(decode(&[0x0A]).operand, Operand::Accumulator);
+        assert_eq!(decode(&[0xE8]).operand, Operand::Implied);
+    }
+
+    #[test]
+    fn not_documented_or_cut_off() {
+        // SLO ($10,X)
+        assert_eq!(Instruction::decode(Cpu::Mos6502, &[0x03, 0x10]), None);
+        assert_eq!(Instruction::decode(Cpu::Mos6502, &[0xAD, 0x34]), None);
+        assert_eq!(Instruction::decode(Cpu::Mos6502, &[]), None);
+    }
+
+    #[test]
+    fn cmos_operands() {
+        let decode = |bytes: &[u8]| Instruction::decode(Cpu::Wdc65C02, bytes).unwrap();
+        assert_eq!(
+            decode(&[0xB2, 0x10]).operand,
+            Operand::ZeropageIndirect(0x10)
+        );
+        assert_eq!(
+            decode(&[0x7C, 0x34, 0x12]).operand,
+            Operand::AbsoluteXIndirect(0x1234)
+        );
+        assert_eq!(
+            decode(&[0x0F, 0x10, 0xFD]).operand,
+            Operand::ZeropageRelative(0x10, -3)
+        );
+    }
+}
+
+#[cfg(test)]
+mod encode {
+    use crate::*;
+
+    #[test]
+    fn all_documented_opcodes_round_trip() {
+        for cpu in [Cpu::Mos6502, Cpu::Wdc65C02] {
+            for opcode in 0..=255u8 {
+                let bytes = [opcode, 0x34, 0x12];
+                if let Some(instruction) = Instruction::decode(cpu, &bytes) {
+                    let encoded = instruction.encode(cpu).unwrap();
+                    assert_eq!(encoded, bytes[..instruction.length()]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn missing_modes() {
+        // there is no STA #
+        let sta = Instruction {
+            mnemonic: Mnemonic::Sta,
+            operand: Operand::Immediate(0x01),
+        };
+        assert_eq!(sta.encode(Cpu::Mos6502), None);
+        let stz = Instruction {
+            mnemonic: Mnemonic::Stz,
+            operand: Operand::Zeropage(0x10),
+        };
+        assert_eq!(stz.encode(Cpu::Mos6502), None);
+        assert_eq!(stz.encode(Cpu::Wdc65C02), Some([0x64, 0x10].to_vec()));
+    }
+}
+
+#[cfg(test)]
+mod display {
+    use crate::*;
+
+    #[test]
+    fn like_the_assembler() {
+        let text = |bytes: &[u8]| {
+            Instruction::decode(Cpu::Wdc65C02, bytes)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(text(&[0xA9, 0x01]), "LDA #$01");
+        assert_eq!(text(&[0xB5, 0x10]), "LDA $10,X");
+        assert_eq!(text(&[0x6C, 0x34, 0x12]), "JMP ($1234)");
+        assert_eq!(text(&[0xA1, 0x10]), "LDA ($10,X)");
+        assert_eq!(text(&[0x0A]), "ASL A");
+        assert_eq!(text(&[0xE8]), "INX");
+        assert_eq!(text(&[0xD0, 0xFB]), "BNE *-3");
+        assert_eq!(text(&[0xF0, 0x02]), "BEQ *+4");
+        assert_eq!(text(&[0x80, 0xFE]), "BRA *");
+        assert_eq!(text(&[0x0F, 0x10, 0xFD]), "BBR0 $10,*");
+    }
+
+    #[test]
+    fn mnemonics() {
+        assert_eq!(Mnemonic::parse("lda"), Some(Mnemonic::Lda));
+        assert_eq!(Mnemonic::parse("SMB7"), Some(Mnemonic::Smb(7)));
+        assert_eq!(Mnemonic::parse("SMB8"), None);
+        assert_eq!(Mnemonic::parse("SLO"), None);
+        assert_eq!(Mnemonic::Tsb.to_string(), "TSB");
+        assert_eq!(Mnemonic::Bbs(2).to_string(), "BBS2");
+    }
+}
+
+#[cfg(test)]
+mod execute {
+    use crate::*;
+
+    #[test]
+    fn without_memory() {
+        let mut c = Chip::new();
+        c.execute(&Instruction {
+            mnemonic: Mnemonic::Lda,
+            operand: Operand::Immediate(0x80),
+        });
+        assert_eq!(c.acc, 0x80);
+        assert_eq!(c.f & N, N);
+
+        c.execute(&Instruction {
+            mnemonic: Mnemonic::Sta,
+            operand: Operand::Absolute(0x1234),
+        });
+        assert_eq!(c.memory[0x1234], 0x80);
+    }
+
+    #[test]
+    fn branches_are_relative_to_the_program_counter() {
+        let mut c = Chip::new();
+        c.pc = 0x0202;
+        c.execute(&Instruction {
+            mnemonic: Mnemonic::Bne,
+            operand: Operand::Relative(-4),
+        });
+        assert_eq!(c.pc, 0x01FE);
+    }
+
+    #[test]
+    fn cmos_instructions_do_nothing() {
+        let mut c = Chip::new();
+        c.acc = 0x01;
+        c.execute(&Instruction {
+            mnemonic: Mnemonic::Stz,
+            operand: Operand::Zeropage(0x10),
+        });
+        c.execute(&Instruction {
+            mnemonic: Mnemonic::Inc,
+            operand: Operand::Accumulator,
+        });
+        assert_eq!(c.acc, 0x01);
+        assert_eq!(c.memory[0x0000], 0x00);
+    }
+}
@@ -3,7 +3,6 @@ use sixfiveohtwo::chip::*;
 /// ==========================
 /// INTERRUPT TESTS
 /// ==========================
-
 #[cfg(test)]
 mod break_software_interrupt {
     use crate::*;
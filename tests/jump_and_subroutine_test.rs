@@ -3,7 +3,6 @@ use sixfiveohtwo::chip::*;
 /// ==========================
 /// JUMP & SUBROUTINE TESTS
 /// ==========================
-
 #[cfg(test)]
 mod jump {
     use crate::*;
@@ -0,0 +1,79 @@
+use sixfiveohtwo::listing::*;
+
+const LISTING: &str = "\
+AS65 Assembler for R6502 [1.42].                                     Page    1
+---------------------------------- test.a65 ----------------------------------
+
+                        ; a comment
+0003 =                  count = 3
+0200 : a203             start   ldx #count
+0202 : ca               loop    dex
+0203 : d0fd                     bne loop
+                                trap
+0205 : 4c0502          >        jmp *
+                        
+0208 : 00                       brk
+No errors in pass 2.
+";
+
+/// ==========================
+/// LISTING TESTS
+/// ==========================
+#[cfg(test)]
+mod parse {
+    use crate::*;
+
+    #[test]
+    fn lines_and_source() {
+        let listing = Listing::parse(LISTING);
+        assert_eq!(listing.source, Some("test.a65".to_string()));
+        let line = listing.line_at(0x0204).unwrap();
+        assert_eq!(line.source_line, Some(5));
+        assert_eq!(line.bytes, [0xD0, 0xFD].to_vec());
+        assert_eq!(line.text, "        bne loop");
+    }
+
+    #[test]
+    fn macro_lines() {
+        let listing = Listing::parse(LISTING);
+        let line = listing.line_at(0x0205).unwrap();
+        assert!(line.macro_line);
+        assert_eq!(line.source_line, None);
+        let source = listing.source_line_at(0x0207).unwrap();
+        assert_eq!(source.source_line, Some(6));
+        assert_eq!(source.text.trim(), "trap");
+        // the empty line after the expansion is not counted
+        assert_eq!(listing.line_at(0x0208).unwrap().source_line, Some(7));
+        assert_eq!(listing.addresses_of(6), [0x0205].to_vec());
+    }
+
+    #[test]
+    fn labels_and_values() {
+        let listing = Listing::parse(LISTING);
+        let labels = listing.labels();
+        assert_eq!(labels.get("start"), Some(0x0200));
+        assert_eq!(labels.get("loop"), Some(0x0202));
+        assert_eq!(labels.get("count"), None);
+        assert_eq!(listing.value_before(0x0208, "count"), Some(3));
+        assert_eq!(listing.line_at(0x0209), None);
+    }
+
+    #[test]
+    fn describe() {
+        let listing = Listing::parse(LISTING);
+        assert_eq!(
+            listing.describe(0x0205).unwrap(),
+            "$0205 loop+3, line 6 of test.a65\n          trap\n>         jmp *"
+        );
+    }
+
+    #[test]
+    fn functional_test() {
+        let listing = Listing::load("bin/6502_functional_test.lst".to_string()).unwrap();
+        assert_eq!(listing.labels().get("start"), Some(0x0400));
+        let source = listing.source_line_at(0x0412).unwrap();
+        assert!(source.text.contains("trap        ;branch should be taken"));
+        assert_eq!(listing.value_before(0x058A, "test_num"), Some(2));
+        assert!(listing.describe(0x336D).unwrap().contains("success"));
+    }
+}
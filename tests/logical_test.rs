@@ -3,7 +3,6 @@ use sixfiveohtwo::chip::*;
 /// ==========================
 /// LOGICAL TESTS
 /// ==========================
-
 #[cfg(test)]
 mod and_with_accumulator {
     use crate::*;
@@ -0,0 +1,169 @@
+use sixfiveohtwo::monitor::*;
+
+/// ==========================
+/// MONITOR TESTS
+/// ==========================
+#[cfg(test)]
+mod commands {
+    use crate::*;
+
+    fn monitor(script: &str) -> Monitor {
+        let mut m = Monitor::new();
+        m.run_script(script).unwrap();
+        m
+    }
+
+    #[test]
+    fn sets_and_shows_registers() {
+        let mut m = Monitor::new();
+        let text = m
+            .command("r a=$01 x=2 y=0x03 sp=$fd p=$24 pc=$0300")
+            .unwrap();
+        assert_eq!(m.chip.acc, 0x01);
+        assert_eq!(m.chip.rx, 0x02);
+        assert_eq!(m.chip.ry, 0x03);
+        assert_eq!(m.chip.sp, 0xFD);
+        assert_eq!(m.chip.f, 0x24);
+        assert_eq!(m.chip.pc, 0x0300);
+        assert!(text.contains("0300 01 02 03 fd 00100100"));
+        assert!(m.command("r a=$100").is_err());
+        assert!(m.command("r q=1").is_err());
+    }
+
+    #[test]
+    fn writes_and_shows_memory() {
+        let mut m = monitor("> $0200 $a9 1 0x02");
+        assert_eq!(m.chip.memory[0x0200..0x0203], [0xA9, 0x01, 0x02]);
+        let text = m.command("m $0200+3").unwrap();
+        assert_eq!(
+            text,
+            "0200  a9 01 02                                          |...|\n"
+        );
+        assert!(m.command("> $0200 $100").is_err());
+    }
+
+    #[test]
+    fn assembles_and_disassembles() {
+        let mut m = monitor("a $0200 lda #$05\na $0202 sta $10");
+        assert_eq!(m.chip.memory[0x0200..0x0204], [0xA9, 0x05, 0x85, 0x10]);
+        let text = m.command("d $0200 2").unwrap();
+        assert_eq!(text, "0200  a9 05     LDA #$05\n0202  85 10     STA $10\n");
+        // `d` continues where it stopped
+        assert!(m.command("d").unwrap().starts_with("0204"));
+        assert!(m.command("a $0200 foo").is_err());
+    }
+
+    #[test]
+    fn steps() {
+        let mut m = monitor("a $0200 ldx #$03\na $0202 inx\nr pc=$0200");
+        let text = m.command("s 2").unwrap();
+        assert_eq!(m.chip.pc, 0x0203);
+        assert_eq!(m.chip.rx, 0x04);
+        // the registers are shown after every step
+        assert_eq!(text.matches("PC   A  X  Y  SP NV-BDIZC").count(), 2);
+    }
+
+    #[test]
+    fn runs_to_breakpoints_and_traps() {
+        let mut m = monitor("a $0200 inx\na $0201 inx\na $0202 jmp $0202\nr pc=$0200\nb $0201");
+        assert_eq!(m.breakpoints().collect::<Vec<u16>>(), [0x0201]);
+        assert!(m.command("g").unwrap().starts_with("breakpoint at $0201"));
+        assert!(m.command("g").unwrap().starts_with("trapped at $0202"));
+        assert_eq!(m.chip.rx, 2);
+        m.command("bd $0201").unwrap();
+        assert!(m.command("bl").unwrap().is_empty());
+        assert!(m.command("bd $0201").is_err());
+    }
+
+    #[test]
+    fn runs_at_most_max_instructions() {
+        // an endless loop that does not trap
+        let mut m = monitor("a $0200 inx\na $0201 jmp $0200");
+        let text = m.command("g $0200 100000").unwrap();
+        assert!(text.starts_with("stopped after 100000 instructions"));
+        assert_eq!(m.chip.rx, (50_000 % 256) as u8);
+        assert!(m.command("g $0200 lots").is_err());
+    }
+
+    #[test]
+    fn labels_are_addresses() {
+        let file = std::env::temp_dir().join("monitor_test.lbl");
+        std::fs::write(&file, "al C:0300 .start\n").unwrap();
+        let mut m = Monitor::new();
+        m.command(&format!("labels {}", file.display())).unwrap();
+        m.command("a start jmp start").unwrap();
+        m.command("r pc=start").unwrap();
+        assert_eq!(m.chip.pc, 0x0300);
+        assert_eq!(
+            m.command("d start 1").unwrap(),
+            "start:\n0300  4c 00 03  JMP start\n"
+        );
+    }
+
+    #[test]
+    fn assembles_with_any_label_names() {
+        let file = std::env::temp_dir().join("monitor_test_names.lbl");
+        std::fs::write(
+            &file,
+            "al C:0300 .Start\nal C:0310 .start\nal C:0320 .loop\nal C:0330 .loop:\n",
+        )
+        .unwrap();
+        let mut m = Monitor::new();
+        m.command(&format!("labels {}", file.display())).unwrap();
+        m.command("a $0200 jsr start").unwrap();
+        assert_eq!(m.chip.memory[0x0200..0x0203], [0x20, 0x10, 0x03]);
+        m.command("a $0203 jmp Start").unwrap();
+        assert_eq!(m.chip.memory[0x0203..0x0206], [0x4C, 0x00, 0x03]);
+        m.command("a $0206 jmp loop").unwrap();
+        assert_eq!(m.chip.memory[0x0206..0x0209], [0x4C, 0x20, 0x03]);
+    }
+}
+
+#[cfg(test)]
+mod session {
+    use crate::*;
+
+    #[test]
+    fn repeats_from_the_history() {
+        let mut m = Monitor::new();
+        m.command("r x=1").unwrap();
+        m.command("> $10 $ff").unwrap();
+        m.command("history").unwrap();
+        assert_eq!(m.history(), ["r x=1", "> $10 $ff"]);
+        m.command("> $10 0").unwrap();
+        m.command("!2").unwrap();
+        assert_eq!(m.chip.memory[0x10], 0xFF);
+        m.command("!!").unwrap();
+        assert_eq!(m.history().len(), 5);
+        assert!(m.command("!9").is_err());
+    }
+
+    #[test]
+    fn loads_binaries_and_scripts() {
+        let dir = std::env::temp_dir();
+        let binary = dir.join("monitor_test.bin");
+        let script = dir.join("monitor_test.txt");
+        std::fs::write(&binary, [0xA9, 0x07, 0x4C, 0x02, 0x04]).unwrap();
+        std::fs::write(
+            &script,
+            format!("; comment\nload {} $0400\ng\n", binary.display()),
+        )
+        .unwrap();
+        let mut m = Monitor::new();
+        let text = m.command(&format!("source {}", script.display())).unwrap();
+        assert!(text.starts_with("loaded $0400-$0404"));
+        assert_eq!(m.chip.acc, 0x07);
+        assert_eq!(m.chip.pc, 0x0402);
+    }
+
+    #[test]
+    fn scripts_stop_at_errors() {
+        let mut m = Monitor::new();
+        let output = m.run_script("r a=1\nfoo\nr a=2\n").unwrap_err();
+        assert!(output.contains("line 2: unknown command `foo`"));
+        assert_eq!(m.chip.acc, 1);
+        m.run_script("q\nr a=3").unwrap();
+        assert!(m.quit());
+        assert_eq!(m.chip.acc, 1);
+    }
+}
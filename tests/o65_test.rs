@@ -0,0 +1,147 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::loader::*;
+
+use std::collections::HashMap;
+
+/// ==========================
+/// O65 LOADER TESTS
+/// ==========================
+#[cfg(test)]
+mod parse {
+    use crate::*;
+
+    #[test]
+    fn header_and_tables() {
+        let object = o65::parse(&object()).unwrap();
+
+        assert_eq!(
+            object.bases,
+            o65::Bases {
+                text: 0x1000,
+                data: 0x2000,
+                bss: 0x3000,
+                zero: 0x0000,
+            }
+        );
+        assert_eq!(object.options, [(0x01, [b'x'].to_vec())]);
+        assert_eq!(object.text.len(), 6);
+        assert_eq!(object.data, [0x42]);
+        assert_eq!(object.undefined, ["ext"]);
+        assert_eq!(object.text_relocations.len(), 2);
+        assert_eq!(object.text_relocations[1].offset, 4);
+        assert_eq!(object.exports[0].name, "start");
+    }
+
+    #[test]
+    fn not_o65() {
+        assert!(matches!(
+            o65::parse(&[0x01, 0x00, b'o', b'6', b'6']),
+            Err(LoadError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn truncated() {
+        let mut bytes = object();
+        bytes.truncate(30);
+
+        assert!(matches!(o65::parse(&bytes), Err(LoadError::Format(_))));
+    }
+}
+
+#[cfg(test)]
+mod relocate {
+    use crate::*;
+
+    #[test]
+    fn moves_segments_and_symbols() {
+        let object = o65::parse(&object()).unwrap();
+        let bases = o65::Bases {
+            text: 0x0400,
+            data: 0x0600,
+            bss: 0x0700,
+            zero: 0x0080,
+        };
+        let imports = HashMap::from([("ext".to_string(), 0xC000)]);
+
+        let linked = object.relocate(&bases, &imports).unwrap();
+        let text = &linked.image.segments[0];
+        assert_eq!(text.address, 0x0400);
+        // LDA $0600
+        // JSR $C000
+        assert_eq!(text.data, [0xAD, 0x00, 0x06, 0x20, 0x00, 0xC0]);
+        assert_eq!(linked.image.segments[1].address, 0x0600);
+        assert_eq!(linked.exports["start"], 0x0400);
+    }
+
+    #[test]
+    fn missing_import() {
+        let object = o65::parse(&object()).unwrap();
+
+        assert!(matches!(
+            object.relocate(&object.bases, &HashMap::new()),
+            Err(LoadError::Format(_))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod load {
+    use crate::*;
+
+    #[test]
+    fn load_and_run() {
+        let path = std::env::temp_dir().join("sixfiveohtwo_o65_test.o65");
+        std::fs::write(&path, object()).unwrap();
+        let mut c = Chip::new();
+        let bases = o65::Bases {
+            text: 0x0200,
+            data: 0x0300,
+            bss: 0x0400,
+            zero: 0x0000,
+        };
+        let imports = HashMap::from([("ext".to_string(), 0x0500)]);
+
+        let exports = c
+            .load_o65(path.to_string_lossy().to_string(), &bases, &imports)
+            .unwrap();
+        c.startup(exports["start"]);
+
+        c.execute_cycle();
+        assert_eq!(c.acc, 0x42);
+        c.execute_cycle();
+        assert_eq!(c.pc, 0x0500);
+    }
+}
+
+/// An o65 file assembled for text at $1000 and data at $2000:
+///
+/// Code:
+/// start: LDA value
+///        JSR ext
+/// value: .byte $42 (data segment)
+#[rustfmt::skip]
+fn object() -> Vec<u8> {
+    [
+        // marker, magic, version, mode
+        0x01, 0x00, b'o', b'6', b'5', 0x00, 0x00, 0x00,
+        // tbase, tlen, dbase, dlen, bbase, blen, zbase, zlen, stack
+        0x00, 0x10, 0x06, 0x00, 0x00, 0x20, 0x01, 0x00, 0x00, 0x30, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        // options
+        0x03, 0x01, b'x', 0x00,
+        // text
+        0xAD, 0x00, 0x20, 0x20, 0x00, 0x00,
+        // data
+        0x42,
+        // undefined symbols
+        0x01, 0x00, b'e', b'x', b't', 0x00,
+        // text relocations: word to data at 1, word to ext at 4
+        0x02, 0x83, 0x03, 0x80, 0x00, 0x00, 0x00,
+        // data relocations
+        0x00,
+        // exported symbols
+        0x01, 0x00, b's', b't', b'a', b'r', b't', 0x00, 0x02, 0x00, 0x10,
+    ]
+    .to_vec()
+}
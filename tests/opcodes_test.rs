@@ -0,0 +1,215 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::opcodes::*;
+
+/// ==========================
+/// OPCODE TABLE TESTS
+/// ==========================
+#[cfg(test)]
+mod table {
+    use crate::*;
+
+    #[test]
+    fn documented_opcodes() {
+        assert_eq!(table().iter().filter(|m| m.documented).count(), 151);
+        for (i, m) in table().iter().enumerate() {
+            assert_eq!(m.opcode as usize, i);
+            assert_eq!(m.length, m.mode.length());
+            assert_eq!(m.instruction.is_some(), m.documented);
+        }
+    }
+
+    #[test]
+    fn cycles_and_penalties() {
+        let lda = metadata(0xBD);
+        assert_eq!((lda.mnemonic, lda.mode), ("LDA", Mode::AbsoluteX));
+        assert_eq!((lda.cycles, lda.page_penalty), (4, 1));
+        // stores always take the extra cycle
+        let sta = metadata(0x9D);
+        assert_eq!((sta.cycles, sta.page_penalty), (5, 0));
+        let lda = metadata(0xB1);
+        assert_eq!((lda.cycles, lda.page_penalty), (5, 1));
+        assert_eq!(metadata(0x20).cycles, 6);
+        assert_eq!(metadata(0x00).cycles, 7);
+        assert_eq!(metadata(0x6C).cycles, 5);
+        assert_eq!(metadata(0xD0).page_penalty, 1);
+    }
+
+    #[test]
+    fn flags() {
+        let adc = metadata(0x69);
+        assert_eq!((adc.reads, adc.writes), (C | D, N | V | Z | C));
+        let sta = metadata(0x8D);
+        assert_eq!((sta.reads, sta.writes), (0, 0));
+        let beq = metadata(0xF0);
+        assert_eq!((beq.reads, beq.writes), (Z, 0));
+        assert_eq!(metadata(0x28).writes, ALL_FLAGS);
+    }
+
+    #[test]
+    fn find_opcodes() {
+        assert_eq!(
+            find(Mnemonic::Lda, Mode::Immediate).map(|m| m.opcode),
+            Some(0xA9)
+        );
+        assert_eq!(
+            find(Mnemonic::Jmp, Mode::Indirect).map(|m| m.opcode),
+            Some(0x6C)
+        );
+        assert_eq!(find(Mnemonic::Sta, Mode::Immediate), None);
+        // 65C02
+        assert_eq!(find(Mnemonic::Stz, Mode::Zeropage), None);
+        // undocumented
+        assert_eq!(Mnemonic::parse("LAX"), None);
+        assert_eq!(metadata(0xA7).instruction, None);
+    }
+}
+
+#[cfg(test)]
+mod behavior {
+    use crate::*;
+
+    #[test]
+    fn only_the_written_flags_change() {
+        for m in table().iter().filter(|m| m.documented) {
+            for f in [0x00, ALL_FLAGS, N | Z, V | C, D | I] {
+                for value in [0x00, 0x80, 0x7F, 0x01] {
+                    let mut c = Chip::new();
+                    c.load_program([m.opcode, 0x10, 0x00].to_vec());
+                    c.startup(0x0200);
+                    c.memory[0x0010] = value;
+                    c.acc = value ^ 0x81;
+                    c.f = f;
+                    c.execute_cycle();
+                    let changed = (c.f ^ f) & ALL_FLAGS;
+                    assert_eq!(
+                        changed & !m.writes,
+                        0,
+                        "{} ({:02X}) changed {:08b}",
+                        m.mnemonic,
+                        m.opcode,
+                        changed
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_program_counter_moves_by_the_length() {
+        for m in table().iter().filter(|m| m.documented) {
+            if matches!(
+                m.mnemonic,
+                "JMP"
+                    | "JSR"
+                    | "RTS"
+                    | "RTI"
+                    | "BRK"
+                    | "BCC"
+                    | "BCS"
+                    | "BEQ"
+                    | "BMI"
+                    | "BNE"
+                    | "BPL"
+                    | "BVC"
+                    | "BVS"
+            ) {
+                continue;
+            }
+            let mut c = Chip::new();
+            c.load_program([m.opcode, 0x10, 0x00].to_vec());
+            c.startup(0x0200);
+            c.execute_cycle();
+            assert_eq!(c.pc, 0x0200 + m.length as u16, "{}", m.mnemonic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod cycles {
+    use crate::*;
+
+    #[test]
+    fn the_chip_counts_the_cycles_of_the_table() {
+        for m in table().iter().filter(|m| m.documented) {
+            let mut c = Chip::new();
+            c.load_program([m.opcode, 0x10, 0x00].to_vec());
+            c.startup(0x0200);
+            c.execute_cycle();
+            // only the branches are taken (with all flags clear)
+            let taken = matches!(m.mnemonic, "BCC" | "BNE" | "BPL" | "BVC") as u64;
+            assert_eq!(c.cycles, m.cycles as u64 + taken, "{}", m.mnemonic);
+        }
+    }
+
+    #[test]
+    fn page_crossings() {
+        let mut c = Chip::new();
+        // Code:
+        // LDA $02F0,X
+        // LDA $02F0,X
+        c.load_program([0xBD, 0xF0, 0x02, 0xBD, 0xF0, 0x02].to_vec());
+        c.startup(0x0200);
+        c.rx = 0x0F;
+        c.execute_cycle();
+        assert_eq!(c.cycles, 4);
+        c.rx = 0x10;
+        c.execute_cycle();
+        assert_eq!(c.cycles, 9);
+
+        // BNE to the page before
+        let mut c = Chip::new();
+        c.load_program([0xD0, 0x80].to_vec());
+        c.startup(0x0200);
+        c.execute_cycle();
+        assert_eq!(c.pc, 0x0182);
+        assert_eq!(c.cycles, 4);
+    }
+}
+
+#[cfg(test)]
+mod interrupts {
+    use crate::*;
+
+    #[test]
+    fn nmi_is_taken_before_the_next_instruction() {
+        let mut c = Chip::new();
+        c.load_program([0xEA].to_vec());
+        c.startup(0x0200);
+        c.memory[0xFFFA] = 0x00;
+        c.memory[0xFFFB] = 0x30;
+        c.f = C | B;
+        c.nmi = true;
+
+        c.execute_cycle();
+        assert_eq!(c.pc, 0x3000);
+        assert!(!c.nmi);
+        assert_eq!(c.cycles, 7);
+        assert_eq!(c.f & I, I);
+        // the return address and the flags without B
+        assert_eq!(c.memory[0x01FF], 0x02);
+        assert_eq!(c.memory[0x01FE], 0x00);
+        assert_eq!(c.memory[0x01FD], C | R);
+    }
+
+    #[test]
+    fn irq_waits_while_interrupts_are_disabled() {
+        let mut c = Chip::new();
+        // Code:
+        // SEI
+        // CLI
+        // NOP
+        c.load_program([0x78, 0x58, 0xEA].to_vec());
+        c.startup(0x0200);
+        c.memory[0xFFFE] = 0x00;
+        c.memory[0xFFFF] = 0x40;
+        c.execute_cycle();
+        c.irq = true;
+
+        c.execute_cycle();
+        assert_eq!(c.pc, 0x0202);
+        assert!(c.irq);
+        c.execute_cycle();
+        assert_eq!(c.pc, 0x4000);
+        assert!(!c.irq);
+    }
+}
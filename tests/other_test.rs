@@ -3,7 +3,6 @@ use sixfiveohtwo::chip::*;
 /// ==========================
 /// OTHER TESTS
 /// ==========================
-
 #[cfg(test)]
 mod bit_test {
     use crate::*;
@@ -0,0 +1,192 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::paravirt::*;
+
+use std::cell::RefCell;
+use std::io::{Cursor, Write};
+use std::rc::Rc;
+
+/// ==========================
+/// PARAVIRTUALIZATION TESTS
+/// ==========================
+#[cfg(test)]
+mod hooks {
+    use crate::*;
+
+    #[test]
+    fn write_and_exit() {
+        let mut c = Chip::new();
+        let out = Output::default();
+        let mut pv = Paravirt::with_io(
+            0x00,
+            Vec::new(),
+            Box::new(Cursor::new(Vec::new())),
+            Box::new(out.clone()),
+            Box::new(out.clone()),
+        );
+
+        // Code:
+        // LDA #$03
+        // LDX #$00
+        // JSR $FFF7 ; write(1, $0300, 3)
+        // STA $10
+        // LDA #$07
+        // JSR $FFF9 ; exit(7)
+        let prog: Vec<u8> = [
+            0xA9, 0x03, 0xA2, 0x00, 0x20, 0xF7, 0xFF, 0x85, 0x10, 0xA9, 0x07, 0x20, 0xF9, 0xFF,
+        ]
+        .to_vec();
+        c.load_program(prog);
+        c.startup(0x0200);
+        c.memory[0x0300..0x0303].copy_from_slice(b"hi\n");
+        push_params(&mut c, &[0x0300, 0x0001]);
+
+        assert_eq!(pv.run(&mut c, MAX_INSTRUCTIONS), Some(7));
+        assert_eq!(out.0.borrow().as_slice(), b"hi\n");
+        assert_eq!(c.memory[0x10], 3);
+        assert_eq!(c.memory[0x00], 0x00);
+        assert_eq!(c.memory[0x01], 0x04);
+    }
+
+    #[test]
+    fn stops_programs_that_do_not_exit() {
+        let mut c = Chip::new();
+        let mut pv = Paravirt::new(0x00, Vec::new());
+        // JMP $0200
+        c.load_program([0x4C, 0x00, 0x02].to_vec());
+        c.startup(0x0200);
+
+        assert_eq!(pv.run(&mut c, 1000), None);
+        assert_eq!(c.pc, 0x0200);
+    }
+
+    #[test]
+    fn read_from_stdin() {
+        let mut c = Chip::new();
+        let mut pv = Paravirt::with_io(
+            0x00,
+            Vec::new(),
+            Box::new(Cursor::new(b"abc".to_vec())),
+            Box::new(Output::default()),
+            Box::new(Output::default()),
+        );
+        push_params(&mut c, &[0x0300, 0x0000]);
+        c.acc = 0x02;
+        c.rx = 0x00;
+        call(&mut c, PV_READ);
+
+        assert_eq!(pv.step(&mut c), None);
+        assert_eq!(c.pc, 0x0203);
+        assert_eq!(c.acc, 0x02);
+        assert_eq!(&c.memory[0x0300..0x0303], b"ab\0");
+    }
+
+    #[test]
+    fn close_unknown_file() {
+        let mut c = Chip::new();
+        let mut pv = Paravirt::new(0x00, Vec::new());
+        c.acc = 0x09;
+        c.rx = 0x00;
+        call(&mut c, PV_CLOSE);
+
+        pv.step(&mut c);
+        assert_eq!((c.acc, c.rx), (0xFF, 0xFF));
+    }
+
+    #[test]
+    fn args() {
+        let mut c = Chip::new();
+        let mut pv = Paravirt::new(0x00, ["prog".to_string(), "x".to_string()].to_vec());
+        push_params(&mut c, &[]);
+        // argv goes to $0010
+        c.acc = 0x10;
+        c.rx = 0x00;
+        call(&mut c, PV_ARGS);
+
+        pv.step(&mut c);
+        assert_eq!(c.acc, 2);
+        let argv = word(&c, 0x0010);
+        assert_eq!(argv, 0x0400 - 6);
+        let arg0 = word(&c, argv) as usize;
+        let arg1 = word(&c, argv + 2) as usize;
+        assert_eq!(&c.memory[arg0..arg0 + 5], b"prog\0");
+        assert_eq!(&c.memory[arg1..arg1 + 2], b"x\0");
+        assert_eq!(word(&c, argv + 4), 0);
+        // the C stack pointer is below the strings
+        assert_eq!(word(&c, 0x0000) as usize, arg1);
+    }
+
+    #[test]
+    fn open_write_close_file() {
+        let path = std::env::temp_dir().join("sixfiveohtwo_paravirt_test.txt");
+        let name = path.to_string_lossy().to_string();
+        let mut c = Chip::new();
+        let mut pv = Paravirt::new(0x00, Vec::new());
+        c.memory[0x0320..0x0320 + name.len()].copy_from_slice(name.as_bytes());
+        c.memory[0x0300..0x0302].copy_from_slice(b"ok");
+
+        // open(name, O_WRONLY | O_CREAT | O_TRUNC)
+        push_params(&mut c, &[0x0032, 0x0320]);
+        c.ry = 4;
+        call(&mut c, PV_OPEN);
+        pv.step(&mut c);
+        let fd = u16::from_le_bytes([c.acc, c.rx]);
+        assert_eq!(fd, 3);
+
+        // write(fd, $0300, 2)
+        push_params(&mut c, &[0x0300, fd]);
+        c.acc = 0x02;
+        c.rx = 0x00;
+        call(&mut c, PV_WRITE);
+        pv.step(&mut c);
+        assert_eq!(c.acc, 0x02);
+
+        // close(fd)
+        c.acc = fd as u8;
+        c.rx = 0x00;
+        call(&mut c, PV_CLOSE);
+        pv.step(&mut c);
+        assert_eq!((c.acc, c.rx), (0x00, 0x00));
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"ok");
+    }
+}
+
+/// Collects everything that is written to it
+#[derive(Default, Clone)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Puts the parameters (first one on top) on a C stack that ends at $0400
+/// and points the C stack pointer at $00 to them
+fn push_params(c: &mut Chip, params: &[u16]) {
+    let sp = 0x0400 - params.len() * 2;
+    for (i, param) in params.iter().enumerate() {
+        let [ll, hh] = param.to_le_bytes();
+        c.memory[sp + i * 2] = ll;
+        c.memory[sp + i * 2 + 1] = hh;
+    }
+    c.memory[0x00] = sp as u8;
+    c.memory[0x01] = (sp >> 8) as u8;
+}
+
+/// Sets the chip up as if `JSR hook` was executed at $0200
+fn call(c: &mut Chip, hook: u16) {
+    c.memory[0x01FF] = 0x02;
+    c.memory[0x01FE] = 0x02;
+    c.sp = 0xFD;
+    c.pc = hook;
+}
+
+fn word(c: &Chip, address: u16) -> u16 {
+    u16::from_le_bytes([c.memory[address as usize], c.memory[address as usize + 1]])
+}
@@ -0,0 +1,155 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::loader::*;
+
+/// ==========================
+/// PATCH TESTS
+/// ==========================
+#[cfg(test)]
+mod ips {
+    use crate::*;
+
+    #[test]
+    fn records_and_runs() {
+        let mut patch = b"PATCH".to_vec();
+        // 2 bytes at 1
+        patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]);
+        // a run of 3 $CC at 6 (behind the end)
+        patch.extend_from_slice(&[0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x03, 0xCC]);
+        patch.extend_from_slice(b"EOF");
+
+        let target = patch::apply(&[0x00, 0x01, 0x02, 0x03], &patch).unwrap();
+        assert_eq!(
+            target,
+            [0x00, 0xAA, 0xBB, 0x03, 0x00, 0x00, 0xCC, 0xCC, 0xCC]
+        );
+    }
+
+    #[test]
+    fn truncate() {
+        let mut patch = b"PATCHEOF".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x02]);
+        assert_eq!(patch::apply_ips(&[1, 2, 3, 4], &patch).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn cut_off() {
+        assert!(matches!(
+            patch::apply_ips(&[1, 2], b"PATCH\x00\x00\x01\x00\x05\x01"),
+            Err(LoadError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn patch_memory() {
+        let path = std::env::temp_dir().join("sixfiveohtwo_patch_test.ips");
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x01, 0xEA]);
+        patch.extend_from_slice(b"EOF");
+        std::fs::write(&path, patch).unwrap();
+
+        let mut c = Chip::new();
+        c.load_program([0xA9, 0x01, 0x00].to_vec());
+        c.apply_patch(path.to_string_lossy().to_string(), 0x0200, 3)
+            .unwrap();
+        assert_eq!(&c.memory[0x0200..0x0203], [0xA9, 0xEA, 0x00]);
+    }
+}
+
+#[cfg(test)]
+mod bps {
+    use crate::*;
+
+    #[test]
+    fn crc() {
+        assert_eq!(patch::crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn all_actions() {
+        let patch = hello_patch();
+        assert_eq!(
+            patch::apply(b"hello world", &patch).unwrap(),
+            b"hello there!!!!"
+        );
+    }
+
+    #[test]
+    fn wrong_source() {
+        assert!(matches!(
+            patch::apply_bps(b"hello earth", &hello_patch()),
+            Err(LoadError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn damaged_patch() {
+        let mut patch = hello_patch();
+        patch[10] ^= 0x01;
+        assert!(matches!(
+            patch::apply_bps(b"hello world", &patch),
+            Err(LoadError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn patched_binary() {
+        let dir = std::env::temp_dir();
+        let file = dir.join("sixfiveohtwo_patch_test.bin");
+        let patch = dir.join("sixfiveohtwo_patch_test.bps");
+        std::fs::write(&file, b"hello world").unwrap();
+        std::fs::write(&patch, hello_patch()).unwrap();
+
+        let mut c = Chip::new();
+        let loaded = c
+            .load_binary_patched(
+                file.to_string_lossy().to_string(),
+                patch.to_string_lossy().to_string(),
+                0x0300,
+            )
+            .unwrap();
+        assert_eq!(loaded, 15);
+        assert_eq!(&c.memory[0x0300..0x030F], b"hello there!!!!");
+    }
+}
+
+/// A BPS patch from "hello world" to "hello there!!!!"
+fn hello_patch() -> Vec<u8> {
+    let mut patch = b"BPS1".to_vec();
+    patch.extend(varint(11));
+    patch.extend(varint(15));
+    patch.extend(varint(0));
+    // SourceRead "hello "
+    patch.extend(varint((6 - 1) << 2));
+    // TargetRead "ther"
+    patch.extend(varint((4 - 1) << 2 | 1));
+    patch.extend_from_slice(b"ther");
+    // SourceCopy the "e" at 1
+    patch.extend(varint(2));
+    patch.extend(varint(1 << 1));
+    // TargetRead "!"
+    patch.extend(varint(1));
+    patch.extend_from_slice(b"!");
+    // TargetCopy the "!" at 11 three times
+    patch.extend(varint((3 - 1) << 2 | 3));
+    patch.extend(varint(11 << 1));
+
+    patch.extend_from_slice(&patch::crc32(b"hello world").to_le_bytes());
+    patch.extend_from_slice(&patch::crc32(b"hello there!!!!").to_le_bytes());
+    let crc = patch::crc32(&patch);
+    patch.extend_from_slice(&crc.to_le_bytes());
+    patch
+}
+
+fn varint(mut number: usize) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    loop {
+        let x = (number & 0x7F) as u8;
+        number >>= 7;
+        if number == 0 {
+            bytes.push(0x80 | x);
+            return bytes;
+        }
+        bytes.push(x);
+        number -= 1;
+    }
+}
@@ -0,0 +1,89 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::power_on::*;
+
+/// ==========================
+/// POWER ON TESTS
+/// ==========================
+#[cfg(test)]
+mod memory_fill {
+    use crate::*;
+
+    #[test]
+    fn default_is_zeros() {
+        let c = Chip::new();
+
+        assert!(c.memory.iter().all(|&b| b == 0x00));
+        assert_eq!(c.pc, 0x200);
+        assert_eq!(c.sp, 0xFF);
+    }
+
+    #[test]
+    fn ones() {
+        let c = Chip::power_on(&PowerOn {
+            fill: MemoryFill::Ones,
+            ..PowerOn::default()
+        });
+
+        assert!(c.memory.iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn alternating() {
+        let c = Chip::power_on(&PowerOn {
+            fill: MemoryFill::Alternating,
+            ..PowerOn::default()
+        });
+
+        assert_eq!(c.memory[0x0000], 0x00);
+        assert_eq!(c.memory[0x0001], 0xFF);
+        assert_eq!(c.memory[0xFFFE], 0x00);
+        assert_eq!(c.memory[0xFFFF], 0xFF);
+    }
+
+    #[test]
+    fn random_is_reproducible() {
+        let config = PowerOn {
+            fill: MemoryFill::Random,
+            seed: 6502,
+            ..PowerOn::default()
+        };
+        let c1 = Chip::power_on(&config);
+        let c2 = Chip::power_on(&config);
+        let c3 = Chip::power_on(&PowerOn {
+            seed: 6510,
+            ..config
+        });
+
+        assert_eq!(c1.memory, c2.memory);
+        assert_ne!(c1.memory, c3.memory);
+    }
+}
+
+#[cfg(test)]
+mod registers {
+    use crate::*;
+
+    #[test]
+    fn random_registers_are_reproducible() {
+        let config = PowerOn {
+            random_registers: true,
+            seed: 42,
+            ..PowerOn::default()
+        };
+        let c1 = Chip::power_on(&config);
+        let c2 = Chip::power_on(&config);
+
+        assert_eq!((c1.acc, c1.rx, c1.ry, c1.f), (c2.acc, c2.rx, c2.ry, c2.f));
+        assert_ne!((c1.acc, c1.rx, c1.ry, c1.f), (0, 0, 0, 0));
+    }
+
+    #[test]
+    fn program_counter() {
+        let c = Chip::power_on(&PowerOn {
+            pc: 0x0400,
+            ..PowerOn::default()
+        });
+
+        assert_eq!(c.pc, 0x0400);
+    }
+}
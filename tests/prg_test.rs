@@ -0,0 +1,85 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::loader::*;
+
+/// ==========================
+/// PRG LOADER TESTS
+/// ==========================
+#[cfg(test)]
+mod parse {
+    use crate::*;
+
+    #[test]
+    fn load_address() {
+        let image = prg::parse(&[0x00, 0xC0, 0xEA, 0xEA]).unwrap();
+
+        assert_eq!(image.segments[0].address, 0xC000);
+        assert_eq!(image.segments[0].data, [0xEA, 0xEA]);
+    }
+
+    #[test]
+    fn too_short() {
+        assert!(matches!(
+            prg::parse(&[0x01, 0x08]),
+            Err(LoadError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn overruns_memory() {
+        assert!(matches!(
+            prg::parse(&[0xFF, 0xFF, 0xEA, 0xEA]),
+            Err(LoadError::OutOfRange { .. })
+        ));
+    }
+
+    #[test]
+    fn sys_address() {
+        // 10 SYS 2061
+        let image = prg::parse(&[
+            0x01, 0x08, 0x0C, 0x08, 0x0A, 0x00, 0x9E, 0x20, 0x32, 0x30, 0x36, 0x31, 0x00, 0x00,
+            0x00,
+        ])
+        .unwrap();
+
+        assert_eq!(prg::sys_address(&image), Some(2061));
+    }
+
+    #[test]
+    fn no_sys_line() {
+        // 10 PRINT
+        let image =
+            prg::parse(&[0x01, 0x08, 0x07, 0x08, 0x0A, 0x00, 0x99, 0x00, 0x00, 0x00]).unwrap();
+
+        assert_eq!(prg::sys_address(&image), None);
+    }
+}
+
+#[cfg(test)]
+mod load {
+    use crate::*;
+
+    #[test]
+    fn load_and_sys() {
+        // 10 SYS2061
+        // Code:
+        // LDA #$01
+        // STA $0300
+        let prg = [
+            0x01, 0x08, 0x0B, 0x08, 0x0A, 0x00, 0x9E, 0x32, 0x30, 0x36, 0x31, 0x00, 0x00, 0x00,
+            0xA9, 0x01, 0x8D, 0x00, 0x03,
+        ];
+        let path = std::env::temp_dir().join("sixfiveohtwo_prg_test.prg");
+        std::fs::write(&path, prg).unwrap();
+        let mut c = Chip::new();
+
+        let range = c
+            .load_prg(path.to_string_lossy().to_string(), true)
+            .unwrap();
+        assert_eq!(range, 0x0801..=0x0811);
+        assert_eq!(c.pc, 0x080D);
+
+        c.execute_cycle();
+        c.execute_cycle();
+        assert_eq!(c.memory[0x0300], 0x01);
+    }
+}
@@ -0,0 +1,164 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::instruction::*;
+use sixfiveohtwo::program::*;
+
+/// ==========================
+/// PROGRAM BUILDER TESTS
+/// ==========================
+#[cfg(test)]
+mod build {
+    use crate::*;
+
+    #[test]
+    fn bytes_of_the_instructions() {
+        let bytes = Program::new(0x0200)
+            .lda_imm(0x01)
+            .sta_abs(0x0300)
+            .ldx_zpy(0x10)
+            .lda_indy(0x20)
+            .asl_a()
+            .jmp_ind(0x1234)
+            .inx()
+            .build()
+            .unwrap();
+        #[rustfmt::skip]
+        let expected = [
+            0xA9, 0x01,
+            0x8D, 0x00, 0x03,
+            0xB6, 0x10,
+            0xB1, 0x20,
+            0x0A,
+            0x6C, 0x34, 0x12,
+            0xE8,
+        ];
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn labels_backwards_and_forwards() {
+        let program = Program::new(0x0200)
+            .label("loop")
+            .dex()
+            .bne("loop")
+            .beq("done")
+            .jsr("done")
+            .label("done")
+            .rts();
+        assert_eq!(
+            program.build().unwrap(),
+            [0xCA, 0xD0, 0xFD, 0xF0, 0x03, 0x20, 0x08, 0x02, 0x60]
+        );
+        assert_eq!(program.symbols().get("done"), Some(0x0208));
+        assert_eq!(program.address(), 0x0209);
+    }
+
+    #[test]
+    fn data() {
+        let bytes = Program::new(0x1000)
+            .label("table")
+            .bytes(&[1, 2])
+            .words(&[0x1234])
+            .address_of("table")
+            .build()
+            .unwrap();
+        assert_eq!(bytes, [1, 2, 0x34, 0x12, 0x00, 0x10]);
+    }
+
+    #[test]
+    fn typed_instructions() {
+        let bytes = Program::new(0x0200)
+            .instruction(Instruction {
+                mnemonic: Mnemonic::Ldy,
+                operand: Operand::AbsoluteX(0x1234),
+            })
+            .build()
+            .unwrap();
+        assert_eq!(bytes, [0xBC, 0x34, 0x12]);
+    }
+}
+
+#[cfg(test)]
+mod errors {
+    use crate::*;
+
+    #[test]
+    fn branch_out_of_range() {
+        let program = Program::new(0x0200)
+            .beq("far")
+            .bytes(&[0; 200])
+            .label("far")
+            .rts();
+        assert_eq!(
+            program.build(),
+            Err(ProgramError::BranchOutOfRange("far".to_string(), 200))
+        );
+        // the longest branches work
+        let program = Program::new(0x0200)
+            .label("back")
+            .bytes(&[0; 126])
+            .bne("back")
+            .beq("forward")
+            .bytes(&[0; 127])
+            .label("forward");
+        assert!(program.build().is_ok());
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(
+            Program::new(0x0200).jmp("nowhere").build(),
+            Err(ProgramError::UndefinedLabel("nowhere".to_string()))
+        );
+        assert_eq!(
+            Program::new(0x0200).label("a").nop().label("a").build(),
+            Err(ProgramError::DuplicateLabel("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn instructions_the_6502_does_not_have() {
+        let program = Program::new(0x0200).instruction(Instruction {
+            mnemonic: Mnemonic::Sta,
+            operand: Operand::Immediate(0x01),
+        });
+        assert_eq!(
+            program.build(),
+            Err(ProgramError::UnknownInstruction("STA #$01".to_string()))
+        );
+    }
+
+    #[test]
+    fn too_long() {
+        let program = Program::new(0xFFFF).nop().nop();
+        assert_eq!(program.build(), Err(ProgramError::TooLong(2)));
+    }
+}
+
+#[cfg(test)]
+mod run {
+    use crate::*;
+
+    #[test]
+    fn loads_with_load_program() {
+        let program = Program::new(0x0200)
+            .ldx_imm(5)
+            .lda_imm(0)
+            .label("loop")
+            .clc()
+            .adc_imm(3)
+            .dex()
+            .bne("loop")
+            .sta_abs(0x0300)
+            .label("done")
+            .jmp("done");
+        let done = program.symbols().get("done").unwrap();
+
+        let mut c = Chip::new();
+        c.load_program(program.build().unwrap());
+        c.startup(0x0200);
+        while c.pc != done {
+            c.execute_cycle();
+        }
+        assert_eq!(c.memory[0x0300], 15);
+    }
+}
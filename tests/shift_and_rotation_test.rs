@@ -3,7 +3,6 @@ use sixfiveohtwo::chip::*;
 /// ==========================
 /// SHIFT & ROTATE TESTS
 /// ==========================
-
 #[cfg(test)]
 mod arithmtic_shift_left {
     use crate::*;
@@ -0,0 +1,89 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::loader::*;
+
+/// ==========================
+/// SIM65 LOADER TESTS
+/// ==========================
+#[cfg(test)]
+mod parse {
+    use crate::*;
+
+    #[test]
+    fn header() {
+        let (header, image) = sim65::parse(&binary(0)).unwrap();
+
+        assert_eq!(
+            header,
+            sim65::Header {
+                cpu: Cpu::Mos6502,
+                sp_address: 0x00,
+                load: 0x0200,
+                reset: 0x0202,
+            }
+        );
+        assert_eq!(image.segments[0].address, 0x0200);
+        assert_eq!(image.start, Some(0x0202));
+    }
+
+    #[test]
+    fn wrong_version() {
+        let mut bytes = binary(0);
+        bytes[5] = 1;
+
+        assert!(matches!(sim65::parse(&bytes), Err(LoadError::Format(_))));
+    }
+
+    #[test]
+    fn overlaps_paravirtualization() {
+        let mut bytes = binary(0);
+        bytes[8] = 0xF2;
+        bytes[9] = 0xFF;
+
+        assert!(matches!(sim65::parse(&bytes), Err(LoadError::Format(_))));
+    }
+}
+
+#[cfg(test)]
+mod load {
+    use crate::*;
+
+    #[test]
+    fn load_and_reset() {
+        let path = std::env::temp_dir().join("sixfiveohtwo_sim65_test.bin");
+        std::fs::write(&path, binary(0)).unwrap();
+        let mut c = Chip::new();
+
+        let header = c.load_sim65(path.to_string_lossy().to_string()).unwrap();
+        assert_eq!(header.sp_address, 0x00);
+        assert_eq!(c.pc, 0x0202);
+        assert_eq!(c.memory[0xFFFC], 0x02);
+        assert_eq!(c.memory[0xFFFD], 0x02);
+        assert_eq!(c.f & I, I);
+
+        c.execute_cycle();
+        assert_eq!(c.acc, 0x01);
+    }
+
+    #[test]
+    fn rejects_65c02() {
+        let path = std::env::temp_dir().join("sixfiveohtwo_sim65c02_test.bin");
+        std::fs::write(&path, binary(1)).unwrap();
+        let mut c = Chip::new();
+
+        assert!(c.load_sim65(path.to_string_lossy().to_string()).is_err());
+    }
+}
+
+/// A sim65 binary loaded at $0200 that starts at $0202
+///
+/// Code:
+/// $0200: .byte $FF, $FF
+/// $0202: LDA #$01
+#[rustfmt::skip]
+fn binary(cpu: u8) -> Vec<u8> {
+    [
+        b's', b'i', b'm', b'6', b'5', 0x02, cpu, 0x00, 0x00, 0x02, 0x02, 0x02,
+        0xFF, 0xFF, 0xA9, 0x01,
+    ]
+    .to_vec()
+}
@@ -0,0 +1,157 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::power_on::*;
+use sixfiveohtwo::snapshot::*;
+
+/// ==========================
+/// SNAPSHOT TESTS
+/// ==========================
+#[cfg(test)]
+mod save_and_restore {
+    use crate::*;
+
+    #[test]
+    fn round_trip() {
+        let mut c = Chip::power_on(&PowerOn {
+            fill: MemoryFill::Random,
+            random_registers: true,
+            seed: 6502,
+            ..PowerOn::default()
+        });
+        c.pc = 0x1234;
+        c.cycles = 0x0123_4567_89AB;
+        c.nmi = true;
+        let bytes = save(&c);
+
+        let mut d = Chip::new();
+        d.irq = true;
+        restore(&mut d, &bytes).unwrap();
+        assert_eq!(
+            (d.acc, d.rx, d.ry, d.f, d.sp, d.pc),
+            (c.acc, c.rx, c.ry, c.f, c.sp, c.pc)
+        );
+        assert!(d.memory == c.memory);
+        assert_eq!((d.cycles, d.irq, d.nmi), (c.cycles, false, true));
+    }
+
+    #[test]
+    fn empty_memory_is_small() {
+        let c = Chip::new();
+        let bytes = save(&c);
+        assert_eq!(&bytes[0..8], MAGIC);
+        assert!(bytes.len() < 1200);
+
+        let mut d = Chip::new();
+        d.memory[0x0200] = 0xEA;
+        restore(&mut d, &bytes).unwrap();
+        assert_eq!(d.memory[0x0200], 0x00);
+    }
+
+    #[test]
+    fn runs_on_after_restore() {
+        let mut c = Chip::new();
+        // Code:
+        // LDA #$01
+        // ADC #$01
+        // ADC #$01
+        c.load_program([0xA9, 0x01, 0x69, 0x01, 0x69, 0x01].to_vec());
+        c.execute_cycle();
+        let bytes = save(&c);
+
+        let mut d = Chip::new();
+        restore(&mut d, &bytes).unwrap();
+        d.execute_cycle();
+        d.execute_cycle();
+        assert_eq!(d.acc, 0x03);
+        assert_eq!(d.cycles, 6);
+    }
+
+    #[test]
+    fn version_1_has_no_cycles_and_interrupts() {
+        let mut c = Chip::new();
+        c.acc = 0x42;
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        let saved = save(&c);
+        // REGS and MEM, but not CYCL and INTR
+        let chunks = &saved[10..];
+        let mem_length = u32::from_le_bytes(chunks[19..23].try_into().unwrap()) as usize;
+        bytes.extend_from_slice(&chunks[..23 + mem_length]);
+        bytes.extend_from_slice(b"END \0\0\0\0");
+
+        let mut d = Chip::new();
+        d.cycles = 100;
+        d.irq = true;
+        restore(&mut d, &bytes).unwrap();
+        assert_eq!(d.acc, 0x42);
+        assert_eq!((d.cycles, d.irq, d.nmi), (0, false, false));
+    }
+
+    #[test]
+    fn unknown_chunks_are_skipped() {
+        let c = Chip::new();
+        let mut bytes = save(&c);
+        let mut chunk = b"XTRA".to_vec();
+        chunk.extend_from_slice(&2u32.to_le_bytes());
+        chunk.extend_from_slice(&[1, 2]);
+        bytes.splice(10..10, chunk);
+
+        let mut d = Chip::new();
+        assert!(restore(&mut d, &bytes).is_ok());
+    }
+
+    #[test]
+    fn file() {
+        let path = std::env::temp_dir().join("sixfiveohtwo_snapshot_test.sav");
+        let mut c = Chip::new();
+        c.memory[0xFFFC] = 0x42;
+        c.acc = 0x17;
+        c.save_state(path.to_string_lossy().to_string()).unwrap();
+
+        let mut d = Chip::new();
+        d.load_state(path.to_string_lossy().to_string()).unwrap();
+        assert_eq!(d.memory[0xFFFC], 0x42);
+        assert_eq!(d.acc, 0x17);
+    }
+}
+
+#[cfg(test)]
+mod errors {
+    use crate::*;
+
+    #[test]
+    fn not_a_snapshot() {
+        let mut c = Chip::new();
+        assert!(restore(&mut c, b"hello world").is_err());
+    }
+
+    #[test]
+    fn newer_version() {
+        let mut bytes = save(&Chip::new());
+        bytes[8..10].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(restore(&mut Chip::new(), &bytes).is_err());
+    }
+
+    #[test]
+    fn reserved_chunks() {
+        let mut c = Chip::new();
+        for tag in RESERVED {
+            let mut bytes = save(&Chip::new());
+            let mut chunk = tag.to_vec();
+            chunk.extend_from_slice(&8u32.to_le_bytes());
+            chunk.extend_from_slice(&[0; 8]);
+            bytes.splice(10..10, chunk);
+            assert!(restore(&mut c, &bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn cut_off_is_not_restored() {
+        let mut c = Chip::new();
+        c.acc = 0x55;
+        let bytes = save(&c);
+
+        let mut d = Chip::new();
+        assert!(restore(&mut d, &bytes[..bytes.len() - 4]).is_err());
+        assert_eq!(d.acc, 0x00);
+    }
+}
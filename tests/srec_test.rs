@@ -0,0 +1,99 @@
+use sixfiveohtwo::chip::*;
+use sixfiveohtwo::loader::*;
+
+/// ==========================
+/// S-RECORD LOADER TESTS
+/// ==========================
+#[cfg(test)]
+mod parse {
+    use crate::*;
+
+    #[test]
+    fn s19() {
+        // Code:
+        // LDA #$01
+        // STA $0300
+        let image = srec::parse("S00600004844521B\nS1080200A9018D0003BB\nS9030200FA\n").unwrap();
+
+        assert_eq!(
+            image.segments,
+            [Segment {
+                address: 0x0200,
+                data: [0xA9, 0x01, 0x8D, 0x00, 0x03].to_vec(),
+            }]
+        );
+        assert_eq!(image.start, Some(0x0200));
+    }
+
+    #[test]
+    fn s28() {
+        let image = srec::parse("S20600C000EAEA65\nS80400C0003B\n").unwrap();
+
+        assert_eq!(image.segments[0].address, 0xC000);
+        assert_eq!(image.segments[0].data, [0xEA, 0xEA]);
+        assert_eq!(image.start, Some(0xC000));
+    }
+
+    #[test]
+    fn checksum_error_has_line() {
+        let err = srec::parse("S00600004844521B\nS1080200A9018D0003BC\n").unwrap_err();
+
+        assert!(matches!(
+            err,
+            LoadError::Checksum {
+                line: 2,
+                expected: 0xBB,
+                found: 0xBC
+            }
+        ));
+    }
+
+    #[test]
+    fn wrong_byte_count() {
+        let err = srec::parse("S1090200A9018D0003BB\n").unwrap_err();
+
+        assert!(matches!(err, LoadError::Syntax { line: 1, .. }));
+    }
+
+    #[test]
+    fn address_above_64k() {
+        let err = srec::parse("S205010000EA0F\n").unwrap_err();
+
+        assert!(matches!(
+            err,
+            LoadError::OutOfRange {
+                address: 0x10000,
+                line: Some(1),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn not_a_record() {
+        let err = srec::parse(":00000001FF\n").unwrap_err();
+
+        assert!(matches!(err, LoadError::Syntax { line: 1, .. }));
+    }
+}
+
+#[cfg(test)]
+mod load {
+    use crate::*;
+
+    #[test]
+    fn load_and_run() {
+        let path = std::env::temp_dir().join("sixfiveohtwo_srec_test.s19");
+        std::fs::write(&path, "S1080200A9018D0003BB\nS9030200FA\n").unwrap();
+        let mut c = Chip::new();
+        c.pc = 0x0000;
+
+        c.load_srec(path.to_string_lossy().to_string(), true)
+            .unwrap();
+        assert_eq!(c.pc, 0x0200);
+
+        c.execute_cycle();
+        c.execute_cycle();
+        assert_eq!(c.memory[0x0300], 0x01);
+    }
+}
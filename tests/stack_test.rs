@@ -3,7 +3,6 @@ use sixfiveohtwo::chip::*;
 /// ==============================
 /// STACK INSTRUCTIONS TEST
 /// ==============================
-
 #[cfg(test)]
 mod push_accumulator {
     use crate::*;
@@ -0,0 +1,99 @@
+use sixfiveohtwo::loader::LoadError;
+use sixfiveohtwo::symbols::*;
+
+/// ==========================
+/// SYMBOL TABLE TESTS
+/// ==========================
+#[cfg(test)]
+mod lookup {
+    use crate::*;
+
+    #[test]
+    fn both_ways() {
+        let mut table = SymbolTable::new();
+        table.insert("start", 0x0400);
+        table.insert("loop", 0x0410);
+
+        assert_eq!(table.get("loop"), Some(0x0410));
+        assert_eq!(table.label_at(0x0400), Some("start"));
+        assert_eq!(table.label_at(0x0401), None);
+        assert_eq!(table.len(), 2);
+    }
+
+    #[test]
+    fn nearest() {
+        let mut table = SymbolTable::new();
+        table.insert("start", 0x0400);
+        table.insert("loop", 0x0410);
+
+        assert_eq!(table.nearest(0x03FF), None);
+        assert_eq!(table.nearest(0x0400), Some(("start", 0)));
+        assert_eq!(table.nearest(0x040F), Some(("start", 15)));
+        assert_eq!(table.nearest(0x0412), Some(("loop", 2)));
+    }
+
+    #[test]
+    fn format_address() {
+        let mut table = SymbolTable::new();
+        table.insert("start", 0x0400);
+
+        assert_eq!(table.format_address(0x0400), "start");
+        assert_eq!(table.format_address(0x0402), "start+2");
+        assert_eq!(table.format_address(0x0600), "$0600");
+        assert_eq!(table.format_address(0x0200), "$0200");
+    }
+
+    #[test]
+    fn replace() {
+        let mut table = SymbolTable::new();
+        table.insert("start", 0x0400);
+        table.insert("start", 0x0500);
+
+        assert_eq!(table.label_at(0x0400), None);
+        assert_eq!(table.label_at(0x0500), Some("start"));
+        assert_eq!(table.len(), 1);
+    }
+}
+
+#[cfg(test)]
+mod parse {
+    use crate::*;
+
+    #[test]
+    fn vice() {
+        let table = SymbolTable::parse_vice(
+            "al C:0400 .start\nal C:0410 .loop\nbreak 0400\nal 00fe .ptr\n",
+        )
+        .unwrap();
+
+        assert_eq!(table.get("start"), Some(0x0400));
+        assert_eq!(table.get("loop"), Some(0x0410));
+        assert_eq!(table.get("ptr"), Some(0x00FE));
+    }
+
+    #[test]
+    fn vice_error() {
+        let err = SymbolTable::parse_vice("al C:0400 .start\nal C:04g0 .loop\n").unwrap_err();
+
+        assert!(matches!(err, LoadError::Syntax { line: 2, .. }));
+    }
+
+    #[test]
+    fn labels() {
+        let table = SymbolTable::parse_labels(
+            "; labels\nstart = $0400\nloop := 0x0410 ; the loop\nptr equ 254\n",
+        )
+        .unwrap();
+
+        assert_eq!(table.get("start"), Some(0x0400));
+        assert_eq!(table.get("loop"), Some(0x0410));
+        assert_eq!(table.get("ptr"), Some(0x00FE));
+    }
+
+    #[test]
+    fn labels_error() {
+        let err = SymbolTable::parse_labels("start = $0400\nloop\n").unwrap_err();
+
+        assert!(matches!(err, LoadError::Syntax { line: 2, .. }));
+    }
+}
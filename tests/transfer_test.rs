@@ -3,7 +3,6 @@ use sixfiveohtwo::chip::*;
 /// ==========================
 /// TRANSFER INSTRUCTIONS TEST
 /// ==========================
-
 #[cfg(test)]
 mod load_accumulator {
 
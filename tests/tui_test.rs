@@ -0,0 +1,150 @@
+use sixfiveohtwo::monitor::Monitor;
+use sixfiveohtwo::tui::*;
+
+/// ==========================
+/// TUI TESTS
+/// ==========================
+#[cfg(test)]
+mod keys {
+    use crate::*;
+
+    #[test]
+    fn parses_keys_and_escape_sequences() {
+        assert_eq!(
+            parse_keys(b"s \x1b[A\x1b[B\x1b[5~\x1b[6~\r\x7f\x1b"),
+            [
+                Key::Char('s'),
+                Key::Char(' '),
+                Key::Up,
+                Key::Down,
+                Key::PageUp,
+                Key::PageDown,
+                Key::Enter,
+                Key::Backspace,
+                Key::Escape,
+            ]
+        );
+        // unknown sequences are dropped
+        assert_eq!(parse_keys(b"\x1b[1;5Cg"), [Key::Char('g')]);
+    }
+}
+
+#[cfg(test)]
+mod screen {
+    use crate::*;
+
+    fn tui() -> Tui {
+        let mut monitor = Monitor::new();
+        monitor
+            .run_script(
+                "a $0200 ldx #$03\na $0202 inx\na $0203 pha\na $0204 jmp $0204\nr pc=$0200 a=$42",
+            )
+            .unwrap();
+        Tui::new(monitor)
+    }
+
+    /// The screen without the escape codes
+    fn text(tui: &Tui) -> String {
+        let mut text = String::new();
+        let mut escape = false;
+        for c in tui.render().chars() {
+            match c {
+                '\x1b' => escape = true,
+                c if escape => escape = !c.is_ascii_alphabetic(),
+                c => text.push(c),
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn fills_the_terminal() {
+        let screen = text(&tui());
+        let rows: Vec<&str> = screen.split("\r\n").collect();
+        assert_eq!(rows.len(), HEIGHT);
+        assert!(rows.iter().all(|row| row.chars().count() <= WIDTH));
+    }
+
+    #[test]
+    fn shows_the_panes() {
+        let screen = text(&tui());
+        assert!(screen.contains(">  0200  a2 03     LDX #$03"));
+        assert!(screen.contains("   0202  e8        INX"));
+        assert!(screen.contains(" A  $42   X  $00   Y  $00"));
+        assert!(screen.contains("-- Zero page $00"));
+        assert!(screen.contains("-- Memory $0200"));
+        assert!(screen.contains("0200  a2 03 e8 48 4c 04 02"));
+    }
+
+    #[test]
+    fn steps_and_runs() {
+        let mut t = tui();
+        t.key(Key::Char('s'));
+        t.key(Key::Char(' '));
+        assert_eq!(t.monitor.chip.rx, 0x04);
+        assert!(text(&t).contains(">  0203  48        PHA"));
+
+        t.key(Key::Char('g'));
+        assert!(t.running());
+        while t.running() {
+            t.run(1000);
+        }
+        assert_eq!(t.message(), "trapped at $0204");
+        // the accumulator was pushed
+        assert!(text(&t).contains(" 01FF  42"));
+    }
+
+    #[test]
+    fn any_key_stops_running() {
+        let mut monitor = Monitor::new();
+        monitor
+            .run_script("a $0200 inx\na $0201 jmp $0200\nr pc=$0200")
+            .unwrap();
+        let mut t = Tui::new(monitor);
+        t.key(Key::Char('g'));
+        t.run(1001);
+        assert!(t.running());
+        assert_eq!(t.monitor.chip.pc, 0x0201);
+
+        t.key(Key::Char('q'));
+        assert!(!t.running());
+        assert!(!t.quit());
+        assert_eq!(t.message(), "stopped at $0201");
+    }
+
+    #[test]
+    fn toggles_breakpoints() {
+        let mut t = tui();
+        t.key(Key::Char('s'));
+        t.key(Key::Char('b'));
+        assert!(text(&t).contains(">* 0202"));
+        t.key(Key::Char('b'));
+        assert_eq!(t.monitor.breakpoints().count(), 0);
+    }
+
+    #[test]
+    fn runs_monitor_commands() {
+        let mut t = tui();
+        for key in parse_keys(b":r x=$10x\x7f\r") {
+            t.key(key);
+        }
+        assert_eq!(t.monitor.chip.rx, 0x10);
+        for key in parse_keys(b":foo\r") {
+            t.key(key);
+        }
+        assert!(t.message().contains("unknown command"));
+        t.key(Key::Char('q'));
+        assert!(t.quit());
+    }
+
+    #[test]
+    fn scrolls_the_memory() {
+        let mut t = tui();
+        t.key(Key::Down);
+        assert!(text(&t).contains("-- Memory $0210"));
+        t.key(Key::PageUp);
+        assert!(text(&t).contains("-- Memory $01D0"));
+        t.key(Key::Char(']'));
+        assert!(text(&t).contains("-- Zero page $10"));
+    }
+}